@@ -0,0 +1,45 @@
+use f_analysis::{BaseSeq, CompVector, EvoParam, FResult, Interval};
+
+/*
+
+   Compares the evolutionary search for sets of k bases against selecting bases successively by k_iterative,
+   over the composites of the strong heuristic. Both are reported with the number of composites that pass every base
+
+   Execute using cargo run --release --example evolution
+
+*/
+
+fn failures(ce: &CompVector<u64>, bases: &BaseSeq<u64>) -> usize {
+    ce.filter_bvector(bases).unwrap().len()
+}
+
+fn main() {
+    let mut intr = Interval::new(3u64, 1u64 << 32);
+    intr.set_strong_heuristic();
+    let ce = intr.compute_heuristic(None).unwrap();
+    println!("{} heuristic composites", ce.len());
+
+    // A single base eliminating every composite, for reference
+    println!("terminating {}", ce.terminating_search().unwrap());
+
+    for k in 3..8 {
+        let iterative = ce.k_iterative(k);
+        println!("k = {} iterative   {} passes {}", k, iterative, failures(&ce, &iterative));
+
+        let mut param = EvoParam::new(k);
+        param.set_seed(k as u64);
+        let start = std::time::Instant::now();
+        let evolved = match ce.evo_search(&param) {
+            FResult::Value(x) => x,
+            FResult::Partial(x, _) => x,
+            _ => panic!("Search failed"),
+        };
+        println!(
+            "k = {} evolution   {} passes {} in {:?}",
+            k,
+            evolved,
+            failures(&ce, &evolved),
+            start.elapsed()
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use f_analysis::filter::{Base, Frobenius, SFrobenius, BPSW, SLPRP};
+use f_analysis::{BaseSeq, Interval};
+
+/*
+
+   Compares the composites of the strong heuristic that survive the combined Fermat-Lucas tests against
+   multi-base strong fermat tests
+
+   Execute using cargo run --release --example frobenius
+
+*/
+
+fn main() {
+    let mut intr = Interval::new(3u64, 1u64 << 36);
+    intr.set_strong_heuristic();
+    let ce = intr.compute_heuristic(None).unwrap();
+    println!("{} heuristic composites", ce.len());
+
+    let count = |name: &str, x: usize| println!("{:>24} {}", name, x);
+
+    count("sprp 2", ce.filter_sprp::<Base<2>>(None).unwrap().len());
+    count("strong lucas", ce.filter_lucas::<SLPRP>(None).unwrap().len());
+    count("frobenius", ce.filter_lucas::<Frobenius>(None).unwrap().len());
+    count("sprp 2 and frobenius", ce.filter_generic::<SFrobenius<2>>(None).unwrap().len());
+    count("bpsw", ce.filter_generic::<BPSW>(None).unwrap().len());
+
+    for bases in [vec![2, 3], vec![2, 3, 5], vec![2, 3, 5, 7]] {
+        let name = format!("sprp {:?}", bases);
+        count(&name, ce.filter_bvector(&BaseSeq::new(bases)).unwrap().len());
+    }
+}
@@ -64,7 +64,7 @@ fn main() {
     // Proves that the hashtable is in fact deterministic against the composite vector it was constructed against. 
     // In practice this will almost surely never fail, excepting hardware failure. However it has application in 
     // testing if it is deterministic against other composite sets
-    if epseudo.filter_hashtable(&hashtable).len() !=0{
+    if epseudo.filter_hashtable(&hashtable).unwrap().len() !=0{
        panic!("Oh, no a silent error prevented us from producing a deterministic hashtable. Report to https://github.com/JASory/f-analysis/issues immediately")
     }
     
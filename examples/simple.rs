@@ -19,7 +19,7 @@ fn main() {
    println!("Computed heuristic");
    for i in 0..100{
       let fermat_base = z.k_rand(3);
-      let res = q.filter_bvector(&fermat_base);
+      let res = q.filter_bvector(&fermat_base).unwrap();
       let ce_count = res.len();
       if ce_count == 0{
          println!("{:?}", fermat_base);
@@ -45,7 +45,7 @@ fn main() {
    let ht = CompVector::<u64>::from_file("heuristic.bin").unwrap().load_to_memory().unwrap().to_hashtable(Some(262144),Some(1565855935),None).unwrap();//.filter_sprp::<Base<15>>(Some("heuristic-66-15.bin"));
    //ht.to_file("ht-66");
    let sprp2 = CompVector::<u64>::from_file("/home/jasory/sprp2.bin").unwrap().load_to_memory().unwrap();
-   let htce = sprp2.filter_hashtable(&ht); //ht.list_failure(&sprp2);
+   let htce = sprp2.filter_hashtable(&ht).unwrap(); //ht.list_failure(&sprp2);
    
    for _ in 0..1000{
    println!("{:?}",htce.bs_rand());
@@ -134,7 +134,7 @@ fn main() {
   //let htce = sprp2.filter_hashtable(&p);
   let ht = sprp2.to_hashtable(Some(32768),None,None).unwrap();
   let z = CompVector::<u64>::from_file("/home/jasory/sprp2.bin").unwrap().filter_sprp::<Base<60>>(None).unwrap();
-  let htce = z.filter_hashtable(&ht);
+  let htce = z.filter_hashtable(&ht).unwrap();
   
   for i in 0..1000{
     println!("{}",htce.bs_rand());
@@ -220,7 +220,7 @@ fn main() {
    
    let ht = ce.to_hashtable(Some(262144),None,None).unwrap();
    ht.to_file("heuristic-66.ht").unwrap();
-   let htce = ht.list_failure(&sprp15);
+   let htce = ht.list_failure(&sprp15).unwrap();
    */
    //for i in 0..100{
    //   println!("{:?}",htce.bs_rand());
@@ -233,12 +233,12 @@ fn main() {
    //    ht.to_file("heuristic2.ht").unwrap();
        println!("Hashtable computed");
        let mut counter = 0u64;
-       let htce = ht.list_failure(&sprp2);
+       let htce = ht.list_failure(&sprp2).unwrap();
        
        for i in 0..10_000{
       // println!("{}",i);
        let b = BaseVector::<u64>::rand_initialise(4);//u64::gen_k(64).unwrap();
-       let fin = htce.filter_bvector(&b);
+       let fin = htce.filter_bvector(&b).unwrap();
        
        if fin.len() != 0{
           counter+=1;
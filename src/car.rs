@@ -1,4 +1,5 @@
 // List of Carmichael numbers of the form pqr where p,q,r are all primes 3 mod 4 and pqr < 10^18
+// Reproduced by Carmichael::mrc(), Carmichael::check_tabulated compares the two
 #[rustfmt::skip]
 pub(crate) const MRC_18: [u64; 2837] = [
     8911, 1024651, 1152271, 5481451, 10267951, 14913991,
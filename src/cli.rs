@@ -0,0 +1,112 @@
+/*
+  Command-line interface
+
+  Each command parses its own arguments with Args, failures are reported as CliError rather than panicking.
+  Usage errors print the usage and exit with 2, failures of the computation exit with 1
+*/
+mod args;
+mod commands;
+
+use args::{usage, CliError, CliResult};
+
+pub(crate) const USAGE: &str = "\
+usage: f-analysis <command> [arguments]
+
+Generating composites
+  generate fermat <base> <inf> <sup> [--strong] [-o FILE]       fermat (or strong) pseudoprimes by exhaustive search
+  generate pinch <base> <inf> <sup> [-o FILE]                   fermat pseudoprimes by Pinch's algorithm
+  generate carmichael <k> <inf> <sup> [--residue R,M] [-o FILE] Carmichael numbers with k prime factors
+  generate heuristic <inf> <sup> [--strong] [-o FILE]           composites of the weak or strong heuristic
+  generate sprp <bases> <sup> [-o FILE]                         strong pseudoprimes to all bases (Sorenson-Webster)
+  generate kform <inf> <sup> (--form A,B,.. | --factors F [--max M]) [--bases B,..] [--u128] [-o FILE]
+      products of primes (A k+1)(B k+1).., or of every form of F factors with multipliers at most M (default 16),
+      that are pseudoprimes to all bases if given
+
+Processing composite files
+  filter <file> [--bases B,..] [--filter NAME] [--range INF,SUP] [--hashtable FILE] [--expr EXPR]
+         [--expr-file FILE] [--reject] [-o FILE]
+      keeps the composites passing every given check, or failing one with --reject
+      NAME is one of prime, square, nqr, epf
+      EXPR combines filters with & | ! and parentheses e.g \"sprp(2) & sprp(3) & !spk(2) & range(1e12, 2^64)\"
+      the filters are true, false, prime, square, power(k), sprp(a,..), fermat(a,..), ej(a,..), coprime(a),
+      trial(s), spk(q), spk(p,q), spkh(p,q), range(inf,sup), table(\"file\"). FILE may contain # comments
+  convert <input> <output> [--to binary|utf8]
+
+Searching bases
+  search strongest <file> <inf> <sup>        strongest base within the interval
+  search terminating <file>                  a single base eliminating every composite
+  search iterative <file> <k>                k bases selected successively
+  search evolution <file> <k> [--population N] [--generations G] [--mutation P] [--interval INF,SUP] [--seed S]
+      k bases eliminating every composite by evolutionary search, otherwise the fittest set found
+  search bound <bases>
+      least strong pseudoprime to the comma-separated bases, with the families of composites searched to prove it
+  search wieferich <bases> <inf> <sup> [--u128]
+      wieferich primes to each of the comma-separated bases
+  hashtable build <file> [--dimension D] [--multiplier M] [--bound B] [--fixed B,..] [--stride S] [-o FILE]
+      --fixed bases are tested before the S hashed bases of each bucket, as needed for --u128
+  hashtable build <file> --levels D1,D2,.. [--bound B] [--fixed B,..] [--stride S] [-o FILE]
+      chained tables of each dimension, every level hashes the composites passing the levels before it
+  hashtable anneal <file> [--dimension D] [--table FILE] [--bound B] [--restarts R] [--iterations N] [--time SECS]
+                   [--seed S] [-o FILE]
+      optimises the multiplier and bases by simulated annealing, from scratch or from an existing table
+  hashtable verify <table> <file>            checks that no composite passes the table
+  hashtable export <table> [--lang rust|c|python] [-o FILE]
+      primality test source with the table as constants, the language defaults to the output extension
+  hashtable import <source> [-o FILE]        reads the table back from exported source
+
+Certificates
+  certify create <file> <bound> (--bases B,.. | --hashtable FILE) [--u128] [-o FILE]
+      records the checksum of the composites, the bases or table, and the composites less than bound passing them
+  certify verify <certificate>
+      re-reads the composites and repeats the tests with arithmetic independent of the library
+
+Long running jobs, resumed from the folder
+  job wieferich init <base> <inf> <sup> <folder>
+  job wieferich run <folder>
+  job psi init <k> <bound> <folder> [--memory BYTES] [--bound-search]
+  job psi run <folder> [--bound-search]
+  job fermat init <base> <inf> <sup> <folder> [--stride S]
+  job heuristic init <inf> <sup> <folder> [--strong] [--stride S]
+  job fermat|heuristic run <folder>
+      the interval is evaluated in strides of S integers (default 2^32), results are in <folder>/results
+
+Commands reading composite files accept
+  --u128    elements are 128-bit, default is 64-bit
+  --utf8    input is newline separated utf-8 rather than binary";
+
+/// Runs the command returning the exit code
+pub(crate) fn run(argv: &[String]) -> i32 {
+    match dispatch(argv) {
+        Ok(()) => 0,
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            2
+        }
+        Err(CliError::Failure(message)) => {
+            eprintln!("error: {}", message);
+            1
+        }
+    }
+}
+
+fn dispatch(argv: &[String]) -> CliResult<()> {
+    let (cmd, rest) = match argv.split_first() {
+        Some(x) => x,
+        None => return usage("no command given"),
+    };
+
+    match cmd.as_str() {
+        "generate" => commands::generate(rest),
+        "filter" => commands::filter(rest),
+        "convert" => commands::convert(rest),
+        "search" => commands::search(rest),
+        "hashtable" => commands::hashtable(rest),
+        "certify" => commands::certify(rest),
+        "job" => commands::job(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => usage(&format!("unknown command {}", cmd)),
+    }
+}
@@ -0,0 +1,158 @@
+use f_analysis::FResult;
+
+/// Failure of a command
+pub(crate) enum CliError {
+    /// Malformed command line, the usage of the command is printed
+    Usage(String),
+    /// The command was well-formed but failed to execute
+    Failure(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Failure(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+pub(crate) type CliResult<T> = Result<T, CliError>;
+
+pub(crate) fn usage<T>(message: &str) -> CliResult<T> {
+    Err(CliError::Usage(message.to_string()))
+}
+
+/// Converts the result of a library call, labelling failures with the operation that produced them
+pub(crate) fn value<T: Clone>(res: FResult<T>, context: &str) -> CliResult<T> {
+    let message = match res {
+        FResult::Value(x) => return Ok(x),
+        FResult::Partial(x, _) => return Ok(x),
+        FResult::NotSupported => "not supported for this configuration".to_string(),
+        FResult::MemoryExceeded(mem) => format!("requires {} bytes which exceeds the memory bound", mem),
+        FResult::Failure => "failed".to_string(),
+        FResult::InsufficientCandidates(x) => format!("only {} candidates found", x),
+        FResult::NoCandidate => "no candidate exists".to_string(),
+        FResult::IOError(message) => message.to_string(),
+        FResult::FileDNE => "file not found".to_string(),
+        FResult::Err(message) => message.to_string(),
+        FResult::Success => "no value returned".to_string(),
+        FResult::Critical => "critical error".to_string(),
+    };
+    Err(CliError::Failure(context.to_string() + ": " + &message))
+}
+
+/// Converts the result of a library call that returns no value
+pub(crate) fn success<T: Clone>(res: FResult<T>, context: &str) -> CliResult<()> {
+    match res {
+        FResult::Success | FResult::Value(_) => Ok(()),
+        x => value(x, context).map(|_| ()),
+    }
+}
+
+/// Arguments of a subcommand split into positional values, options taking a value and boolean flags
+pub(crate) struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    /// Parses the arguments, accepting only the listed options and flags.
+    /// Options may be written as --name value or --name=value, -o is short for --output
+    pub(crate) fn parse(argv: &[String], options: &[&str], flags: &[&str]) -> CliResult<Self> {
+        let mut res = Args {
+            positional: vec![],
+            options: vec![],
+            flags: vec![],
+        };
+        let mut idx = 0usize;
+
+        while idx < argv.len() {
+            let arg = &argv[idx];
+            idx += 1;
+
+            if arg == "-o" || (arg.starts_with("--") && arg.len() > 2) {
+                let stripped = if arg == "-o" { "output" } else { &arg[2..] };
+                let (name, inline) = match stripped.split_once('=') {
+                    Some((n, v)) => (n, Some(v.to_string())),
+                    None => (stripped, None),
+                };
+
+                if flags.contains(&name) && inline.is_none() {
+                    res.flags.push(name.to_string());
+                } else if options.contains(&name) {
+                    let val = match inline {
+                        Some(v) => v,
+                        None => {
+                            if idx >= argv.len() {
+                                return usage(&format!("--{} requires a value", name));
+                            }
+                            idx += 1;
+                            argv[idx - 1].clone()
+                        }
+                    };
+                    res.options.push((name.to_string(), val));
+                } else {
+                    return usage(&format!("unknown option {}", arg));
+                }
+            } else {
+                res.positional.push(arg.clone());
+            }
+        }
+        Ok(res)
+    }
+
+    /// Fails if more than count positional arguments were provided
+    pub(crate) fn expect_at_most(&self, count: usize) -> CliResult<()> {
+        if self.positional.len() > count {
+            return usage(&format!("unexpected argument {}", self.positional[count]));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn positional(&self, idx: usize, name: &str) -> CliResult<&str> {
+        match self.positional.get(idx) {
+            Some(x) => Ok(x),
+            None => usage(&format!("missing argument <{}>", name)),
+        }
+    }
+
+    pub(crate) fn parse_positional<T: std::str::FromStr>(&self, idx: usize, name: &str) -> CliResult<T> {
+        parse_value(self.positional(idx, name)?, name)
+    }
+
+    pub(crate) fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn parse_option<T: std::str::FromStr>(&self, name: &str) -> CliResult<Option<T>> {
+        match self.option(name) {
+            Some(x) => parse_value(x, name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|n| n == name)
+    }
+}
+
+pub(crate) fn parse_value<T: std::str::FromStr>(x: &str, name: &str) -> CliResult<T> {
+    match x.parse::<T>() {
+        Ok(v) => Ok(v),
+        Err(_) => usage(&format!("invalid value {} for {}", x, name)),
+    }
+}
+
+/// Parses a comma-separated list e.g 2,3,5
+pub(crate) fn parse_list<T: std::str::FromStr>(x: &str, name: &str) -> CliResult<Vec<T>> {
+    x.split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| parse_value(s.trim(), name))
+        .collect()
+}
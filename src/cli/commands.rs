@@ -0,0 +1,723 @@
+use crate::cli::args::{parse_list, success, usage, value, Args, CliError, CliResult};
+use f_analysis::filter::{FilterExpr, GenericFilter, Prime, Square, EPF, NQR};
+use f_analysis::{
+    AnnealParam, BaseSeq, Carmichael, Certificate, Checkpoint, CompVector, CompositeFamily, Epz, EvoParam, FResult,
+    FermatEval, HashTable, HeuristicEval, Interval, Job, Natural, Persistent, PsiEval, SourceLang, WieferichEval, Witness,
+};
+use std::io::Write;
+
+// Options shared by the commands that read composite files
+const INPUT_FLAGS: [&str; 2] = ["u128", "utf8"];
+
+/*
+   Output of the composites, if written to file report the count otherwise print one per line.
+   Results of generators that already wrote to the file are not rewritten
+*/
+fn emit<T: Natural>(res: CompVector<T>, output: Option<&str>, metadata: &str) -> CliResult<()> {
+    match output {
+        Some(x) => {
+            let stored = if res.is_loaded() {
+                value(res.to_file_with_metadata(x, metadata), "writing output")?
+            } else {
+                res
+            };
+            eprintln!("{} composites written to {}", stored.len(), x);
+            Ok(())
+        }
+        None => {
+            let loaded = if res.is_loaded() {
+                res
+            } else {
+                value(res.load_to_memory(), "loading result")?
+            };
+            let stdout = std::io::stdout();
+            let mut out = std::io::BufWriter::new(stdout.lock());
+            for i in loaded.to_vector() {
+                if let Err(message) = writeln!(out, "{}", i) {
+                    return Err(CliError::Failure(message.to_string()));
+                }
+            }
+            out.flush()
+                .map_err(|message| CliError::Failure(message.to_string()))
+        }
+    }
+}
+
+fn open<T: Natural>(args: &Args, idx: usize) -> CliResult<CompVector<T>> {
+    let locale = args.positional(idx, "file")?;
+    let mut res = value(CompVector::<T>::from_file(locale), locale)?;
+    if args.flag("utf8") {
+        res.set_utf8();
+    }
+    Ok(res)
+}
+
+// Loads the file into memory, as required by the base searches
+fn open_loaded<T: Natural>(args: &Args, idx: usize) -> CliResult<CompVector<T>> {
+    let res = open::<T>(args, idx)?;
+    value(res.load_to_memory(), "loading file")
+}
+
+fn bounds<T: Natural>(args: &Args, idx: usize) -> CliResult<(T, T)> {
+    let inf = args.parse_positional::<T>(idx, "inf")?;
+    let sup = args.parse_positional::<T>(idx + 1, "sup")?;
+    if inf >= sup {
+        return usage("inf must be less than sup");
+    }
+    Ok((inf, sup))
+}
+
+fn interval<T: Natural>(args: &Args, idx: usize) -> CliResult<Interval<T>> {
+    let (inf, sup) = bounds::<T>(args, idx)?;
+    Ok(Interval::new(inf, sup))
+}
+
+fn subcommand<'a>(argv: &'a [String], name: &str) -> CliResult<(&'a str, &'a [String])> {
+    match argv.split_first() {
+        Some((sub, rest)) => Ok((sub.as_str(), rest)),
+        None => usage(&format!("{} requires a subcommand", name)),
+    }
+}
+
+pub(crate) fn generate(argv: &[String]) -> CliResult<()> {
+    let (sub, rest) = subcommand(argv, "generate")?;
+    let args = Args::parse(
+        rest,
+        &["output", "residue", "form", "factors", "max", "bases"],
+        &["strong", "u128"],
+    )?;
+    let output = args.option("output");
+
+    match sub {
+        "fermat" => {
+            args.expect_at_most(3)?;
+            let base = args.parse_positional::<u64>(0, "base")?;
+            let (inf, sup) = bounds::<u64>(&args, 1)?;
+            // The exhaustive search is only defined from 2
+            let mut res = Interval::new(inf.max(2), sup.max(3)).generate_fermat_rt(base);
+            if args.flag("strong") {
+                res = value(res.filter_sprp_rt(base), "fermat")?;
+            }
+            let metadata = format!("interval {},{} fermat base {}", inf, sup, base);
+            emit(res, output, &metadata)
+        }
+        "pinch" => {
+            args.expect_at_most(3)?;
+            let base = args.parse_positional::<u64>(0, "base")?;
+            let intr = interval::<u64>(&args, 1)?;
+            let res = value(intr.generate_fermat_pinch(base, output), "pinch")?;
+            emit(res, output, "")
+        }
+        "carmichael" => {
+            args.expect_at_most(3)?;
+            let k = args.parse_positional::<usize>(0, "k")?;
+            let intr = interval::<u128>(&args, 1)?;
+            let mut generator = Carmichael::new(k);
+            if let Some(r) = args.option("residue") {
+                let class = parse_list::<u64>(r, "residue")?;
+                if class.len() != 2 || class[1] == 0 {
+                    return usage("--residue expects R,M with M nonzero");
+                }
+                generator.set_residue(class[0], class[1]);
+            }
+            let res = value(intr.generate_carmichael(&generator, output), "carmichael")?;
+            emit(res, output, "")
+        }
+        "heuristic" => {
+            args.expect_at_most(2)?;
+            let mut intr = interval::<u64>(&args, 0)?;
+            if args.flag("strong") {
+                intr.set_strong_heuristic();
+            } else {
+                intr.set_weak_heuristic();
+            }
+            let res = value(intr.compute_heuristic(output), "heuristic")?;
+            emit(res, output, "")
+        }
+        "kform" => {
+            args.expect_at_most(2)?;
+            if args.flag("u128") {
+                k_form::<u128>(&args, output)
+            } else {
+                k_form::<u64>(&args, output)
+            }
+        }
+        "sprp" => {
+            args.expect_at_most(2)?;
+            let bases = BaseSeq::new(parse_list::<u64>(args.positional(0, "bases")?, "bases")?);
+            let sup = args.parse_positional::<u128>(1, "sup")?;
+            let res = value(bases.sorenson_webster(sup), "sorenson-webster")?;
+            let metadata = format!("bases {} interval 0,{} strong pseudoprimes", bases, sup);
+            emit(res, output, &metadata)
+        }
+        _ => usage(&format!("unknown generator {}", sub)),
+    }
+}
+
+fn k_form<T: Natural>(args: &Args, output: Option<&str>) -> CliResult<()> {
+    let intr = interval::<T>(args, 0)?;
+    let families = match (args.option("form"), args.parse_option::<usize>("factors")?) {
+        (Some(f), None) => vec![CompositeFamily::KForm(parse_list::<u64>(f, "form")?)],
+        (None, Some(k)) if k >= 2 => CompositeFamily::k_forms(k, args.parse_option::<u64>("max")?.unwrap_or(16)),
+        (None, Some(_)) => return usage("k-forms have at least 2 factors"),
+        _ => return usage("kform requires one of --form or --factors"),
+    };
+    let bases = match args.option("bases") {
+        Some(b) => Some(BaseSeq::new(parse_list::<T>(b, "bases")?)),
+        None => None,
+    };
+    let res = value(intr.generate_families(families, bases.as_ref(), output), "kform")?;
+    emit(res, output, "")
+}
+
+fn named_filter<T: Natural>(name: &str) -> CliResult<fn(T) -> bool> {
+    match name {
+        "prime" => Ok(Prime::filter_check::<T>),
+        "square" => Ok(Square::filter_check::<T>),
+        "nqr" => Ok(NQR::filter_check::<T>),
+        "epf" => Ok(EPF::filter_check::<T>),
+        _ => usage(&format!("unknown filter {}", name)),
+    }
+}
+
+type Check<T> = Box<dyn Fn(T) -> bool + Send + Sync>;
+
+// A malformed expression is a usage error, failing to load a hashtable it references is not
+fn parse_expr<T: Natural>(expr: &str) -> CliResult<FilterExpr<T>> {
+    match FilterExpr::<T>::parse(expr) {
+        FResult::Value(x) => Ok(x),
+        FResult::Err(message) => usage(&format!("invalid filter expression: {}", message)),
+        failure => value(failure, "filter expression"),
+    }
+}
+
+fn filter_typed<T: Natural>(args: &Args) -> CliResult<()> {
+    args.expect_at_most(1)?;
+    let input = open::<T>(args, 0)?;
+    let mut checks: Vec<Check<T>> = vec![];
+
+    if let Some(b) = args.option("bases") {
+        let bases = BaseSeq::new(parse_list::<T>(b, "bases")?);
+        checks.push(Box::new(move |x: T| bases.primality(x)));
+    }
+
+    if let Some(name) = args.option("filter") {
+        checks.push(Box::new(named_filter::<T>(name)?));
+    }
+
+    if let Some(r) = args.option("range") {
+        let bound = parse_list::<T>(r, "range")?;
+        if bound.len() != 2 {
+            return usage("--range expects INF,SUP");
+        }
+        let (inf, sup) = (bound[0], bound[1]);
+        checks.push(Box::new(move |x: T| x.is_bounded_by(inf, sup)));
+    }
+
+    if let Some(locale) = args.option("hashtable") {
+        let ht = value(HashTable::from_persistent(locale), locale)?;
+        checks.push(Box::new(move |x: T| ht.primality(x)));
+    }
+
+    if let Some(e) = args.option("expr") {
+        let expr = parse_expr::<T>(e)?;
+        checks.push(Box::new(move |x: T| expr.check(x)));
+    }
+
+    if let Some(locale) = args.option("expr-file") {
+        let text = std::fs::read_to_string(locale)
+            .map_err(|message| CliError::Failure(locale.to_string() + ": " + &message.to_string()))?;
+        let expr = parse_expr::<T>(&text)?;
+        checks.push(Box::new(move |x: T| expr.check(x)));
+    }
+
+    if checks.is_empty() {
+        return usage("filter requires at least one of --bases, --filter, --range, --hashtable, --expr or --expr-file");
+    }
+
+    let reject = args.flag("reject");
+    let output = args.option("output");
+    let res = value(
+        input.filter_streaming(move |x: T| checks.iter().all(|c| c(x)) != reject, output),
+        "filter",
+    )?;
+    emit(res, output, "")
+}
+
+pub(crate) fn filter(argv: &[String]) -> CliResult<()> {
+    let args = Args::parse(
+        argv,
+        &["output", "bases", "filter", "range", "hashtable", "expr", "expr-file"],
+        &["u128", "utf8", "reject"],
+    )?;
+    if args.flag("u128") {
+        filter_typed::<u128>(&args)
+    } else {
+        filter_typed::<u64>(&args)
+    }
+}
+
+fn convert_typed<T: Natural>(args: &Args) -> CliResult<()> {
+    args.expect_at_most(2)?;
+    let input = open::<T>(args, 0)?;
+    let output = args.positional(1, "output")?;
+
+    match args.option("to").unwrap_or("binary") {
+        // Streamed, so files larger than memory can be converted
+        "binary" => {
+            let res = value(input.filter_streaming(|_: T| true, Some(output)), "convert")?;
+            emit(res, Some(output), "")
+        }
+        "utf8" => {
+            let mut res = value(input.load_to_memory(), "loading file")?;
+            res.set_utf8();
+            value(res.to_file(output), "writing output")?;
+            eprintln!("{} composites written to {}", res.len(), output);
+            Ok(())
+        }
+        x => usage(&format!("unknown format {}", x)),
+    }
+}
+
+pub(crate) fn convert(argv: &[String]) -> CliResult<()> {
+    let args = Args::parse(argv, &["to"], &INPUT_FLAGS)?;
+    if args.flag("u128") {
+        convert_typed::<u128>(&args)
+    } else {
+        convert_typed::<u64>(&args)
+    }
+}
+
+fn search_typed<T: Natural>(sub: &str, args: &Args) -> CliResult<()> {
+    match sub {
+        "strongest" => {
+            args.expect_at_most(3)?;
+            let ce = open_loaded::<T>(args, 0)?;
+            let inf = args.parse_positional::<u64>(1, "inf")?;
+            let sup = args.parse_positional::<u64>(2, "sup")?;
+            if inf >= sup {
+                return usage("inf must be less than sup");
+            }
+            println!("{}", value(ce.strongest_search(inf, sup), "search")?);
+        }
+        "terminating" => {
+            args.expect_at_most(1)?;
+            let ce = open_loaded::<T>(args, 0)?;
+            println!("{}", value(ce.terminating_search(), "search")?);
+        }
+        "iterative" => {
+            args.expect_at_most(2)?;
+            let ce = open_loaded::<T>(args, 0)?;
+            let k = args.parse_positional::<usize>(1, "k")?;
+            if k == 0 {
+                return usage("k must be at least 1");
+            }
+            println!("{}", ce.k_iterative(k));
+        }
+        "evolution" => {
+            args.expect_at_most(2)?;
+            let ce = open_loaded::<T>(args, 0)?;
+            let k = args.parse_positional::<usize>(1, "k")?;
+            if k == 0 {
+                return usage("k must be at least 1");
+            }
+            let mut param = EvoParam::new(k);
+            if let Some(p) = args.parse_option::<usize>("population")? {
+                param.set_population(p);
+            }
+            if let Some(g) = args.parse_option::<usize>("generations")? {
+                param.set_generations(g);
+            }
+            if let Some(m) = args.parse_option::<f64>("mutation")? {
+                param.set_mutation(m);
+            }
+            if let Some(r) = args.option("interval") {
+                let bound = parse_list::<u64>(r, "interval")?;
+                if bound.len() != 2 {
+                    return usage("--interval expects INF,SUP");
+                }
+                param.set_interval(bound[0], bound[1]);
+            }
+            if let Some(seed) = args.parse_option::<u64>("seed")? {
+                param.set_seed(seed);
+            }
+            match ce.evo_search(&param) {
+                FResult::Partial(bases, count) => println!("{} passes {} composites", bases, count),
+                res => println!("{}", value(res, "search")?),
+            }
+        }
+        _ => return usage(&format!("unknown search {}", sub)),
+    }
+    Ok(())
+}
+
+// Table of the Wieferich primes to each of the comma-separated bases, one base per line
+fn wieferich<T: Natural>(args: &Args) -> CliResult<()> {
+    args.expect_at_most(3)?;
+    let bases = parse_list::<T>(args.positional(0, "bases")?, "bases")?;
+    if bases.is_empty() {
+        return usage("at least one base is required");
+    }
+    let intr = interval::<T>(args, 1)?;
+    for row in intr.wieferich_table(&bases) {
+        println!("{}", row);
+    }
+    Ok(())
+}
+
+fn jaeschke(args: &Args) -> CliResult<()> {
+    args.expect_at_most(1)?;
+    let bases = parse_list::<u64>(args.positional(0, "bases")?, "bases")?;
+    if bases.is_empty() {
+        return usage("at least one base is required");
+    }
+    let cert = value(BaseSeq::new(bases).jaeschke_bound(), "bound")?;
+    print!("{}", cert);
+    Ok(())
+}
+
+pub(crate) fn search(argv: &[String]) -> CliResult<()> {
+    let (sub, rest) = subcommand(argv, "search")?;
+    let args = Args::parse(
+        rest,
+        &["population", "generations", "mutation", "interval", "seed"],
+        &INPUT_FLAGS,
+    )?;
+
+    if sub == "bound" {
+        return jaeschke(&args);
+    }
+
+    if sub == "wieferich" {
+        return if args.flag("u128") {
+            wieferich::<u128>(&args)
+        } else {
+            wieferich::<u64>(&args)
+        };
+    }
+
+    if args.flag("u128") {
+        search_typed::<u128>(sub, &args)
+    } else {
+        search_typed::<u64>(sub, &args)
+    }
+}
+
+fn hashtable_typed<T: Natural>(sub: &str, args: &Args) -> CliResult<()> {
+    match sub {
+        "build" => {
+            args.expect_at_most(1)?;
+            let ce = open::<T>(args, 0)?;
+            let fixed = match args.option("fixed") {
+                Some(b) => parse_list::<u64>(b, "fixed")?,
+                None => vec![],
+            };
+            let stride = args.parse_option::<usize>("stride")?.unwrap_or(1);
+            if stride == 0 {
+                return usage("the stride must be at least 1");
+            }
+            let levels = match args.option("levels") {
+                Some(d) => parse_list::<usize>(d, "levels")?,
+                None => vec![],
+            };
+            if levels.iter().any(|d| !d.is_power_of_two()) {
+                return usage("the dimension of each level must be a power of two");
+            }
+            let res = if !levels.is_empty() {
+                ce.to_hashtable_levels(&fixed, &levels, stride, args.parse_option::<u64>("bound")?)
+            } else if fixed.is_empty() && stride == 1 {
+                ce.compute_hashtable(
+                    args.parse_option::<usize>("dimension")?,
+                    args.parse_option::<u32>("multiplier")?,
+                    args.parse_option::<u64>("bound")?,
+                )
+            } else {
+                ce.to_hashtable_multi(
+                    &fixed,
+                    args.parse_option::<usize>("dimension")?,
+                    stride,
+                    args.parse_option::<u32>("multiplier")?,
+                    args.parse_option::<u64>("bound")?,
+                )
+            };
+            let ht = value(res, "hashtable")?;
+            match args.option("output") {
+                Some(x) => success(ht.to_persistent(x), x)?,
+                None => println!("{}", ht),
+            }
+            Ok(())
+        }
+        "anneal" => {
+            args.expect_at_most(1)?;
+            let ce = open::<T>(args, 0)?;
+            let initial = match args.option("table") {
+                Some(locale) => Some(value(HashTable::from_persistent(locale), locale)?),
+                None => None,
+            };
+            let dimen = match (args.parse_option::<usize>("dimension")?, &initial) {
+                (Some(d), _) => d,
+                (None, Some(ht)) => ht.values().0,
+                (None, None) => return usage("anneal requires --dimension or --table"),
+            };
+            if !dimen.is_power_of_two() {
+                return usage("the dimension must be a power of two");
+            }
+            let mut param = AnnealParam::new(dimen);
+            if let Some(b) = args.parse_option::<u64>("bound")? {
+                param.set_bound(b);
+            }
+            if let Some(r) = args.parse_option::<usize>("restarts")? {
+                param.set_restarts(r);
+            }
+            if let Some(i) = args.parse_option::<usize>("iterations")? {
+                param.set_iterations(i);
+            }
+            if let Some(t) = args.parse_option::<u64>("time")? {
+                param.set_time(std::time::Duration::from_secs(t));
+            }
+            if let Some(seed) = args.parse_option::<u64>("seed")? {
+                param.set_seed(seed);
+            }
+            let res = match &initial {
+                Some(ht) => ht.anneal(&ce, &param),
+                None => ce.anneal_hashtable(&param),
+            };
+            let ht = match res {
+                FResult::Partial(ht, count) => {
+                    eprintln!("{} composites pass the best table found", count);
+                    ht
+                }
+                res => value(res, "anneal")?,
+            };
+            match args.option("output") {
+                Some(x) => success(ht.to_persistent(x), x)?,
+                None => println!("{}", ht),
+            }
+            Ok(())
+        }
+        "verify" => {
+            args.expect_at_most(2)?;
+            let locale = args.positional(0, "table")?;
+            let ht = value(HashTable::from_persistent(locale), locale)?;
+            let ce = open::<T>(args, 1)?;
+            let failures = value(ce.filter_hashtable_stream(&ht, None), "verify")?;
+
+            if failures.len() == 0 {
+                println!("verified, no composite of {} passes", ce.len());
+                return Ok(());
+            }
+            emit(failures.clone(), None, "")?;
+            Err(CliError::Failure(format!(
+                "{} composites pass the hashtable",
+                failures.len()
+            )))
+        }
+        "export" => {
+            args.expect_at_most(1)?;
+            let locale = args.positional(0, "table")?;
+            let ht = value(HashTable::from_persistent(locale), locale)?;
+            // The language defaults to that of the output extension
+            let lang = match args.option("lang").or(args.option("output").and_then(|x| x.rsplit('.').next())) {
+                Some("rust" | "rs") => SourceLang::Rust,
+                Some("c" | "h") => SourceLang::C,
+                Some("python" | "py") => SourceLang::Python,
+                _ => return usage("export requires --lang rust, c or python"),
+            };
+            match args.option("output") {
+                Some(x) => success(ht.write_source(lang, x), x)?,
+                None => print!("{}", ht.to_source(lang)),
+            }
+            Ok(())
+        }
+        "import" => {
+            args.expect_at_most(1)?;
+            let locale = args.positional(0, "source")?;
+            let ht = value(HashTable::read_source(locale), locale)?;
+            match args.option("output") {
+                Some(x) => success(ht.to_persistent(x), x)?,
+                None => println!("{}", ht),
+            }
+            Ok(())
+        }
+        _ => usage(&format!("unknown hashtable command {}", sub)),
+    }
+}
+
+pub(crate) fn hashtable(argv: &[String]) -> CliResult<()> {
+    let (sub, rest) = subcommand(argv, "hashtable")?;
+    let args = Args::parse(
+        rest,
+        &[
+            "output",
+            "dimension",
+            "multiplier",
+            "bound",
+            "fixed",
+            "stride",
+            "levels",
+            "lang",
+            "table",
+            "restarts",
+            "iterations",
+            "time",
+            "seed",
+        ],
+        &INPUT_FLAGS,
+    )?;
+    if args.flag("u128") {
+        hashtable_typed::<u128>(sub, &args)
+    } else {
+        hashtable_typed::<u64>(sub, &args)
+    }
+}
+
+fn certify_typed<T: Natural>(args: &Args) -> CliResult<()> {
+    args.expect_at_most(2)?;
+    let locale = args.positional(0, "file")?;
+    let bound = args.parse_positional::<T>(1, "bound")?;
+    let witness = match (args.option("bases"), args.option("hashtable")) {
+        (Some(b), None) => Witness::Bases(parse_list::<u64>(b, "bases")?),
+        (None, Some(t)) => Witness::Table(value(HashTable::from_persistent(t), t)?),
+        _ => return usage("certify create requires one of --bases or --hashtable"),
+    };
+    let cert = value(Certificate::new(locale, witness, bound), locale)?;
+    match args.option("output") {
+        Some(x) => success(cert.to_persistent(x), x)?,
+        None => print!("{}", cert),
+    }
+    eprintln!(
+        "{} of {} composites less than the bound pass",
+        cert.passing().len(),
+        cert.evaluated()
+    );
+    Ok(())
+}
+
+pub(crate) fn certify(argv: &[String]) -> CliResult<()> {
+    let (sub, rest) = subcommand(argv, "certify")?;
+    let args = Args::parse(rest, &["bases", "hashtable", "output"], &["u128"])?;
+    match sub {
+        "create" if args.flag("u128") => certify_typed::<u128>(&args),
+        "create" => certify_typed::<u64>(&args),
+        "verify" => {
+            args.expect_at_most(1)?;
+            let locale = args.positional(0, "certificate")?;
+            let cert = value(Certificate::from_persistent(locale), locale)?;
+            success(cert.verify(), "verify")?;
+            if cert.is_deterministic() {
+                println!(
+                    "verified, no composite of {} less than {} passes",
+                    cert.source(),
+                    cert.bound()
+                );
+            } else {
+                println!(
+                    "verified, {} composites of {} less than {} pass",
+                    cert.passing().len(),
+                    cert.source(),
+                    cert.bound()
+                );
+            }
+            Ok(())
+        }
+        _ => usage(&format!("unknown certify command {}", sub)),
+    }
+}
+
+fn existing_folder(args: &Args, idx: usize) -> CliResult<&str> {
+    let folder = args.positional(idx, "folder")?;
+    if !std::path::Path::new(folder).is_dir() {
+        return Err(CliError::Failure(format!("{} is not a job folder", folder)));
+    }
+    Ok(folder)
+}
+
+// Progress of a job after it has run
+fn report<C: Checkpoint>(folder: &str) -> CliResult<()> {
+    let job = value(Job::<C>::recover(folder), folder)?;
+    println!(
+        "{} units {}/{} complete {}, results in {}/results",
+        C::KIND,
+        job.index(),
+        job.units(),
+        job.is_complete(),
+        folder
+    );
+    Ok(())
+}
+
+fn run_job<C: Checkpoint>(folder: &str) -> CliResult<()> {
+    let mut job = value(Job::<C>::recover(folder), folder)?;
+    success(job.run(), folder)?;
+    report::<C>(folder)
+}
+
+pub(crate) fn job(argv: &[String]) -> CliResult<()> {
+    let (kind, rest) = subcommand(argv, "job")?;
+    let (sub, rest) = subcommand(rest, "job")?;
+    let args = Args::parse(rest, &["memory", "stride"], &["bound-search", "strong"])?;
+    let stride = args.parse_option::<u64>("stride")?.unwrap_or(1 << 32);
+
+    match (kind, sub) {
+        ("wieferich", "init") => {
+            args.expect_at_most(4)?;
+            let base = args.parse_positional::<u64>(0, "base")?;
+            let (inf, sup) = bounds::<u64>(&args, 1)?;
+            let folder = args.positional(3, "folder")?;
+            success(WieferichEval::initialise(base, inf, sup, folder), folder)
+        }
+        ("wieferich", "run") => {
+            args.expect_at_most(1)?;
+            let folder = existing_folder(&args, 0)?;
+            let eval = value(WieferichEval::recover(folder), folder)?;
+            success(eval.run(), folder)?;
+            report::<WieferichEval>(folder)
+        }
+        ("psi", "init") => {
+            args.expect_at_most(3)?;
+            let k = args.parse_positional::<u64>(0, "k")?;
+            let bound = args.parse_positional::<Epz<3>>(1, "bound")?;
+            let folder = args.positional(2, "folder")?;
+            let memory = args.parse_option::<u64>("memory")?.unwrap_or(1 << 30);
+            success(
+                PsiEval::initialise(Some(k), memory, Some(bound), folder, args.flag("bound-search")),
+                folder,
+            )
+        }
+        ("psi", "run") => {
+            args.expect_at_most(1)?;
+            let folder = existing_folder(&args, 0)?;
+            let mut eval = value(PsiEval::recover(folder), folder)?;
+            if args.flag("bound-search") {
+                success(eval.bound_search(), folder)?;
+            } else {
+                success(eval.enumerate_pseudo(), folder)?;
+            }
+            report::<PsiEval>(folder)
+        }
+        ("fermat", "init") => {
+            args.expect_at_most(4)?;
+            let base = args.parse_positional::<u64>(0, "base")?;
+            let (inf, sup) = bounds::<u64>(&args, 1)?;
+            let folder = args.positional(3, "folder")?;
+            let eval = value(FermatEval::new(base, inf, sup, stride), "fermat")?;
+            value(Job::initialise(eval, folder), folder).map(|_| ())
+        }
+        ("fermat", "run") => {
+            args.expect_at_most(1)?;
+            run_job::<FermatEval<u64>>(existing_folder(&args, 0)?)
+        }
+        ("heuristic", "init") => {
+            args.expect_at_most(3)?;
+            let (inf, sup) = bounds::<u64>(&args, 0)?;
+            let folder = args.positional(2, "folder")?;
+            let eval = value(HeuristicEval::new(inf, sup, stride, args.flag("strong")), "heuristic")?;
+            value(Job::initialise(eval, folder), folder).map(|_| ())
+        }
+        ("heuristic", "run") => {
+            args.expect_at_most(1)?;
+            run_job::<HeuristicEval<u64>>(existing_folder(&args, 0)?)
+        }
+        _ => usage(&format!("unknown job {} {}", kind, sub)),
+    }
+}
@@ -2,13 +2,25 @@
    Large scale computation
    
    These involve computations that can take months and must be able to recover from a prior state
+
+   Each computation implements Checkpoint and is run as a Job, which stores its state, progress and results
+   in a folder. Progress is checkpointed atomically after each unit of work so that a job resumes after a crash
+   or reboot from the first unfinished unit.
 */
 
 mod psi;
 mod compio;
+mod job;
 mod wieferich;
+mod fermat;
+mod bound;
+mod corrector;
 pub use psi::PsiEval;
 pub use wieferich::WieferichEval;
+pub use job::{Checkpoint, Job, JobState, Progress};
+pub use fermat::{FermatEval, HeuristicEval};
+pub use bound::MRBoundEval;
+pub use corrector::CorrectorEval;
 
 /*
 computation 
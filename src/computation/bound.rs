@@ -0,0 +1,96 @@
+use crate::computation::job::{locale, persist, Checkpoint, JobState};
+use crate::structures::{store::Persistent, BaseSeq, ResidueClass};
+use crate::FResult;
+
+/// Checkpointed BaseSeq::mr_bound_par, the first Monier-Rabin semiprime passing the witnesses whose scalar lies
+/// in [inf;sup] of the residue classes
+///
+/// The scalars are evaluated in strides, the job terminates at the first stride containing a semiprime
+#[derive(Clone)]
+pub struct MRBoundEval {
+    witness: BaseSeq<u64>,
+    residue: ResidueClass,
+    inf: u64,
+    sup: u64,
+    stride: u64,
+}
+
+impl MRBoundEval {
+    pub fn new(witness: BaseSeq<u64>, residue: ResidueClass, inf: u64, sup: u64, stride: u64) -> FResult<Self> {
+        if inf >= sup || sup == u64::MAX || stride < 2 {
+            return FResult::Err("Interval must be nonempty and less than 2^64-1, stride at least 2");
+        }
+        FResult::Value(Self {
+            witness,
+            residue,
+            inf,
+            sup,
+            stride,
+        })
+    }
+}
+
+impl Checkpoint for MRBoundEval {
+    const KIND: &'static str = "mrbound";
+
+    fn save_state(&self, folder: &str) -> FResult<()> {
+        match persist(&self.witness, folder, "witness") {
+            FResult::Success => (),
+            failure => return failure,
+        }
+        match persist(&self.residue, folder, "residue") {
+            FResult::Success => (),
+            failure => return failure,
+        }
+        let mut state = JobState::new();
+        state.set("inf", self.inf);
+        state.set("sup", self.sup);
+        state.set("stride", self.stride);
+        state.save(folder)
+    }
+
+    fn load_state(folder: &str) -> FResult<Self> {
+        let state = match JobState::load(folder) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let witness = match BaseSeq::from_persistent(&locale(folder, "witness")) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let residue = match ResidueClass::from_persistent(&locale(folder, "residue")) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let mut bounds = [0u64; 3];
+        for (i, key) in ["inf", "sup", "stride"].iter().enumerate() {
+            match state.get::<u64>(key) {
+                FResult::Value(x) => bounds[i] = x,
+                failure => return failure.recast(),
+            }
+        }
+        Self::new(witness, residue, bounds[0], bounds[1], bounds[2])
+    }
+
+    fn units(&self) -> u64 {
+        (self.sup - self.inf) / self.stride + 1
+    }
+
+    fn evaluate(&self, unit: u64) -> FResult<Vec<String>> {
+        let mut lo = self.inf + unit * self.stride;
+        let hi = (lo + self.stride - 1).min(self.sup);
+        // mr_bound_par requires a nonempty interval, the prior scalar has already been evaluated
+        if lo == hi {
+            lo -= 1;
+        }
+        match self.witness.mr_bound_par(self.residue.clone(), lo, hi) {
+            FResult::Value(x) => FResult::Value(vec![x.to_string()]),
+            FResult::NoCandidate => FResult::Value(vec![]),
+            failure => failure.recast(),
+        }
+    }
+
+    fn terminates(&self, lines: &[String]) -> bool {
+        !lines.is_empty()
+    }
+}
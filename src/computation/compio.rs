@@ -1,40 +1,40 @@
-pub fn create_file(file: &str){
-    std::fs::File::create(file).unwrap();
+/*
+   State and result files of computations
+
+   Files are never overwritten in place, a replacement is written to a temporary file, synced and renamed over
+   the prior file so that a crash leaves either the prior or the new contents. Appends are synced before
+   returning
+*/
+
+pub fn create_file(file: &str) -> std::io::Result<()>{
+   write_atomic(&[],file)
 }
 
-pub fn appender(data: &str, file: &str){
-   use std::io::{Write,Seek,SeekFrom};
-   let mut file = std::fs::OpenOptions::new().write(true).open(file).unwrap();
-   file.seek(SeekFrom::End(0));
-   file.write(&data.as_bytes());
+pub fn appender(data: &str, file: &str) -> std::io::Result<()>{
+   append_synced(data.as_bytes(),file)
 }
 
-pub fn write_bounds(x: u64, y: u64, file: &str){
-   use std::io::Write;
-   let mut fout = std::fs::File::create(file).unwrap();//std::fs::OpenOptions::new().write(true).open(file).unwrap();
+pub fn write_bounds(x: u64, y: u64, file: &str) -> std::io::Result<()>{
    let data = x.to_string() + " " + &y.to_string();
-   fout.write(&data.as_bytes());
+   write_atomic(data.as_bytes(),file)
 }
 
-pub fn write_single(x: u64, file: &str){
-   use std::io::Write;
-   let mut fout = std::fs::File::create(file).unwrap();//std::fs::OpenOptions::new().write(true).open(file).unwrap();
-   let data = x.to_string();
-   fout.write(&data.as_bytes());
+pub fn write_single(x: u64, file: &str) -> std::io::Result<()>{
+   write_atomic(x.to_string().as_bytes(),file)
 }
 
 //pub fn update_single
 
-
+/// Reads a single integer, None if the file is missing, empty or malformed
 pub fn load_idx(input: &str) -> Option<u64>{
-      let strin = std::fs::read_to_string(input).unwrap();
-      let k = strin.trim();
-      Some(k.parse::<u64>().unwrap())
+      let strin = std::fs::read_to_string(input).ok()?;
+      strin.trim().parse::<u64>().ok()
 }
 
 // 
+/// Reads "inf sup" or "sup" with an implicit inf of 0, None if the file is missing or malformed
 pub fn load_bounds(input: &str) -> Option<(u64,u64)>{
-   let strin = std::fs::read_to_string(input).unwrap();
+   let strin = std::fs::read_to_string(input).ok()?;
    
    let args = strin.split_whitespace().collect::<Vec<&str>>();
    match args.len(){
@@ -54,3 +54,39 @@ pub fn load_bounds(input: &str) -> Option<(u64,u64)>{
    }
 }
 
+
+// Syncs the directory containing the file so that a rename or creation survives a crash
+pub fn sync_parent(file: &str) -> std::io::Result<()>{
+   let parent = match std::path::Path::new(file).parent(){
+     Some(p) if !p.as_os_str().is_empty() => p,
+     _=> std::path::Path::new("."),
+   };
+   std::fs::File::open(parent)?.sync_all()
+}
+
+/// Syncs a completely written temporary file and renames it to replace the file
+pub fn commit(tmp: &str, file: &str) -> std::io::Result<()>{
+   std::fs::File::open(tmp)?.sync_all()?;
+   std::fs::rename(tmp,file)?;
+   sync_parent(file)
+}
+
+/// Temporary file used to write a replacement of the file
+pub fn temporary(file: &str) -> String{
+   file.to_string()+".tmp"
+}
+
+/// Replaces the contents of the file, a crash leaves either the prior or the new contents never a partial write
+pub fn write_atomic(data: &[u8], file: &str) -> std::io::Result<()>{
+   let tmp = temporary(file);
+   std::fs::write(&tmp,data)?;
+   commit(&tmp,file)
+}
+
+/// Appends to the file and flushes to disk before returning
+pub fn append_synced(data: &[u8], file: &str) -> std::io::Result<()>{
+   use std::io::Write;
+   let mut fout = std::fs::OpenOptions::new().append(true).create(true).open(file)?;
+   fout.write_all(data)?;
+   fout.sync_data()
+}
@@ -0,0 +1,154 @@
+use crate::computation::job::{locale, persist, Checkpoint, JobState};
+use crate::structures::store::Persistent;
+use crate::{CompVector, FResult, HashTable};
+
+/// Checkpointed HashTable::corrector_set, selects the bases of the table indices eliminating the composites
+///
+/// The indices are evaluated in chunks, each result is a line "index base". The corrected table is constructed
+/// from the results by CorrectorEval::table
+#[derive(Clone)]
+pub struct CorrectorEval {
+    table: HashTable,
+    // Absolute path of the composites
+    composites: String,
+    integer_max: u64,
+    indices: Vec<usize>,
+    chunk: usize,
+    total: bool,
+}
+
+impl CorrectorEval {
+    pub fn new(
+        table: HashTable,
+        composites: &str,
+        integer_max: u64,
+        indices: Vec<usize>,
+        chunk: usize,
+        total: bool,
+    ) -> FResult<Self> {
+        if chunk == 0 {
+            return FResult::Err("Chunk length must be nonzero");
+        }
+        if indices.iter().any(|i| *i >= table.values().0) {
+            return FResult::Err("Index exceeds the dimension of the table");
+        }
+        // Recovery may occur from another working directory
+        let composites = match std::fs::canonicalize(composites) {
+            Ok(x) => x.to_string_lossy().to_string(),
+            Err(message) => return FResult::IOError(message),
+        };
+        FResult::Value(Self {
+            table,
+            composites,
+            integer_max,
+            indices,
+            chunk,
+            total,
+        })
+    }
+
+    /// Hashtable with the bases of the results, results are the lines of Job::results
+    pub fn table(&self, results: &[String]) -> FResult<HashTable> {
+        let mut res = self.table.clone();
+        for line in results.iter() {
+            let parsed = line
+                .split_once(' ')
+                .map(|(i, b)| (i.parse::<usize>(), b.parse::<u64>()));
+            match parsed {
+                Some((Ok(idx), Ok(base))) if idx < res.values().0 => res.set_idx(base, idx),
+                _ => return FResult::Err("Malformed corrector result"),
+            }
+        }
+        FResult::Value(res)
+    }
+}
+
+impl Checkpoint for CorrectorEval {
+    const KIND: &'static str = "corrector";
+
+    fn save_state(&self, folder: &str) -> FResult<()> {
+        match persist(&self.table, folder, "table") {
+            FResult::Success => (),
+            failure => return failure,
+        }
+        let indices = self
+            .indices
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        let mut state = JobState::new();
+        state.set("composites", &self.composites);
+        state.set("max", self.integer_max);
+        state.set("indices", indices);
+        state.set("chunk", self.chunk);
+        state.set("total", self.total);
+        state.save(folder)
+    }
+
+    fn load_state(folder: &str) -> FResult<Self> {
+        let state = match JobState::load(folder) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let table = match HashTable::from_persistent(&locale(folder, "table")) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let composites = match state.get::<String>("composites") {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let integer_max = match state.get::<u64>("max") {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let indices = match state.get::<String>("indices") {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let chunk = match state.get::<usize>("chunk") {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let total = match state.get::<bool>("total") {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let mut parsed = vec![];
+        for i in indices.split(',').filter(|s| !s.is_empty()) {
+            match i.parse::<usize>() {
+                Ok(x) => parsed.push(x),
+                Err(_) => return FResult::Err("Malformed indices in job state"),
+            }
+        }
+        Self::new(table, &composites, integer_max, parsed, chunk, total)
+    }
+
+    fn units(&self) -> u64 {
+        self.indices.len().div_ceil(self.chunk) as u64
+    }
+
+    fn evaluate(&self, unit: u64) -> FResult<Vec<String>> {
+        let start = unit as usize * self.chunk;
+        let stop = (start + self.chunk).min(self.indices.len());
+        let indices = self.indices[start..stop].to_vec();
+
+        let cvec = match CompVector::<u64>::from_file(&self.composites) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+
+        let mut table = self.table.clone();
+        match table.corrector_set(cvec, self.integer_max, indices.clone(), self.total) {
+            FResult::Value(bases) => FResult::Value(
+                indices
+                    .iter()
+                    .zip(bases.to_vector().iter())
+                    .map(|(i, b)| i.to_string() + " " + &b.to_string())
+                    .collect(),
+            ),
+            failure => failure.recast(),
+        }
+    }
+}
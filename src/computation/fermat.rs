@@ -0,0 +1,210 @@
+use crate::computation::job::{Checkpoint, JobState};
+use crate::{FResult, Interval, Natural};
+
+/*
+   Checkpointed composite generation
+
+   The interval is split into strides evaluated as separate units, the composites of each stride are appended
+   to the results in ascending order of the strides
+*/
+
+// Number of strides covering [inf;sup)
+fn stride_count<T: Natural>(inf: T, sup: T, stride: u64) -> u64 {
+    let (q, r) = sup.finite_sub(inf).euclidean(T::from(stride));
+    q.to_u64() + if r == T::ZERO { 0 } else { 1 }
+}
+
+// Bounds [lo;hi) of the stride
+fn stride_bounds<T: Natural>(inf: T, sup: T, stride: u64, unit: u64) -> (T, T) {
+    let lo = T::from(unit).finite_mul(T::from(stride)).finite_add(inf);
+    let hi = lo.finite_add(T::from(stride));
+    if hi > sup || hi < lo {
+        return (lo, sup);
+    }
+    (lo, hi)
+}
+
+fn check_width<T: Natural>(state: &JobState) -> FResult<()> {
+    match state.get::<usize>("width") {
+        FResult::Value(x) if x == T::BYTE_LENGTH => FResult::Success,
+        FResult::Value(_) => FResult::Err("Element length of job does not match type"),
+        failure => failure.recast(),
+    }
+}
+
+fn load_interval<T: Natural>(state: &JobState) -> FResult<(T, T, u64)> {
+    match check_width::<T>(state) {
+        FResult::Success => (),
+        failure => return failure.recast(),
+    }
+    let inf = match state.get::<T>("inf") {
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+    };
+    let sup = match state.get::<T>("sup") {
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+    };
+    let stride = match state.get::<u64>("stride") {
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+    };
+    if inf >= sup || stride == 0 {
+        return FResult::Err("Invalid interval in job state");
+    }
+    FResult::Value((inf, sup, stride))
+}
+
+fn interval_state<T: Natural>(inf: T, sup: T, stride: u64) -> JobState {
+    let mut state = JobState::new();
+    state.set("width", T::BYTE_LENGTH);
+    state.set("inf", inf);
+    state.set("sup", sup);
+    state.set("stride", stride);
+    state
+}
+
+/// Checkpointed Interval::generate_fermat_rt, Fermat pseudoprimes to the base in [inf;sup)
+#[derive(Clone, Debug)]
+pub struct FermatEval<T: Natural> {
+    base: T,
+    inf: T,
+    sup: T,
+    stride: u64,
+}
+
+impl<T: Natural> FermatEval<T> {
+    pub fn new(base: T, inf: T, sup: T, stride: u64) -> FResult<Self> {
+        // 0 and 1 are not composites and satisfy the fermat test to any base
+        let inf = if inf < T::from(2) { T::from(2) } else { inf };
+        if inf >= sup || stride == 0 {
+            return FResult::Err("Interval must be nonempty and stride nonzero");
+        }
+        FResult::Value(Self {
+            base,
+            inf,
+            sup,
+            stride,
+        })
+    }
+}
+
+impl<T: Natural> Checkpoint for FermatEval<T> {
+    const KIND: &'static str = "fermat";
+
+    fn save_state(&self, folder: &str) -> FResult<()> {
+        let mut state = interval_state(self.inf, self.sup, self.stride);
+        state.set("base", self.base);
+        state.save(folder)
+    }
+
+    fn load_state(folder: &str) -> FResult<Self> {
+        let state = match JobState::load(folder) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let (inf, sup, stride) = match load_interval::<T>(&state) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        match state.get::<T>("base") {
+            FResult::Value(base) => FResult::Value(Self {
+                base,
+                inf,
+                sup,
+                stride,
+            }),
+            failure => failure.recast(),
+        }
+    }
+
+    fn units(&self) -> u64 {
+        stride_count(self.inf, self.sup, self.stride)
+    }
+
+    fn evaluate(&self, unit: u64) -> FResult<Vec<String>> {
+        let (lo, hi) = stride_bounds(self.inf, self.sup, self.stride, unit);
+        let res = Interval::new(lo, hi).generate_fermat_rt(self.base).to_vector();
+        FResult::Value(res.iter().map(|x| x.to_string()).collect())
+    }
+}
+
+/// Checkpointed Interval::compute_heuristic, composites of the weak or strong heuristic in (inf;sup)
+#[derive(Clone, Debug)]
+pub struct HeuristicEval<T: Natural> {
+    inf: T,
+    sup: T,
+    stride: u64,
+    strong: bool,
+}
+
+impl<T: Natural> HeuristicEval<T> {
+    pub fn new(inf: T, sup: T, stride: u64, strong: bool) -> FResult<Self> {
+        if inf >= sup || stride == 0 {
+            return FResult::Err("Interval must be nonempty and stride nonzero");
+        }
+        FResult::Value(Self {
+            inf,
+            sup,
+            stride,
+            strong,
+        })
+    }
+}
+
+impl<T: Natural> Checkpoint for HeuristicEval<T> {
+    const KIND: &'static str = "heuristic";
+
+    fn save_state(&self, folder: &str) -> FResult<()> {
+        let mut state = interval_state(self.inf, self.sup, self.stride);
+        state.set("strong", self.strong);
+        state.save(folder)
+    }
+
+    fn load_state(folder: &str) -> FResult<Self> {
+        let state = match JobState::load(folder) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let (inf, sup, stride) = match load_interval::<T>(&state) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        match state.get::<bool>("strong") {
+            FResult::Value(strong) => FResult::Value(Self {
+                inf,
+                sup,
+                stride,
+                strong,
+            }),
+            failure => failure.recast(),
+        }
+    }
+
+    fn units(&self) -> u64 {
+        stride_count(self.inf, self.sup, self.stride)
+    }
+
+    fn evaluate(&self, unit: u64) -> FResult<Vec<String>> {
+        // compute_heuristic excludes both bounds, so each stride (lo;hi] is evaluated as (lo;hi+1)
+        // except the last which is bounded by sup
+        let (lo, hi) = stride_bounds(self.inf, self.sup, self.stride, unit);
+        let hi = if hi < self.sup { hi.finite_add(T::ONE) } else { hi };
+        let mut interval = Interval::new(lo, hi);
+
+        if self.strong {
+            interval.set_strong_heuristic();
+        } else {
+            interval.set_weak_heuristic();
+        }
+
+        match interval.compute_heuristic(None) {
+            FResult::Value(res) => {
+                let mut res = res.to_vector();
+                res.sort();
+                FResult::Value(res.iter().map(|x| x.to_string()).collect())
+            }
+            failure => failure.recast(),
+        }
+    }
+}
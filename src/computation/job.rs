@@ -0,0 +1,669 @@
+use crate::computation::compio::{append_synced, commit, sync_parent, temporary, write_atomic};
+use crate::structures::store::Persistent;
+use crate::FResult;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/*
+   Checkpointed jobs
+
+   A job splits a computation into units of work evaluated in order. After each unit its output is appended to
+   the results file and the checkpoint is atomically replaced, so an interrupted job resumes from the first
+   unit that was not completed, discarding any output of the unit that was interrupted.
+
+   Folder layout
+
+   state        parameters of the computation written by Checkpoint::save_state, computations may store
+                further files (e.g Persistent structures) alongside it
+   checkpoint   kind of the computation, index of the first unfinished unit, units completed beyond it when run
+                in parallel, length of the results in bytes and completion
+   results      output lines of the completed units
+   complete     marker created once the job is complete
+*/
+
+const STATE: &str = "state";
+const CHECKPOINT: &str = "checkpoint";
+const RESULTS: &str = "results";
+const COMPLETE: &str = "complete";
+
+pub(crate) fn locale(folder: &str, file: &str) -> String {
+    folder.to_string() + "/" + file
+}
+
+/// Writes the structure to the file of the job folder, replacing any prior file atomically
+pub(crate) fn persist<P: Persistent>(x: &P, folder: &str, file: &str) -> FResult<()> {
+    let target = locale(folder, file);
+    let tmp = temporary(&target);
+    match x.to_persistent(&tmp) {
+        FResult::Success => (),
+        failure => return failure,
+    }
+    match commit(&tmp, &target) {
+        Ok(_) => FResult::Success,
+        Err(message) => FResult::IOError(message),
+    }
+}
+
+/// Parameters of a computation stored as one "key value" pair per line
+#[derive(Clone, Default, Debug)]
+pub struct JobState {
+    entries: Vec<(String, String)>,
+}
+
+impl JobState {
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Sets the value of the key, replacing any prior value
+    pub fn set<T: std::fmt::Display>(&mut self, key: &str, value: T) {
+        let value = value.to_string();
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn get<T: std::str::FromStr + Clone>(&self, key: &str) -> FResult<T> {
+        match self.entries.iter().find(|(k, _)| k == key) {
+            Some((_, v)) => match v.parse::<T>() {
+                Ok(x) => FResult::Value(x),
+                Err(_) => FResult::Err("Malformed value in job state"),
+            },
+            None => FResult::Err("Missing value in job state"),
+        }
+    }
+
+    /// Writes the state, replacing the file atomically
+    pub fn write(&self, locale: &str) -> FResult<()> {
+        let data = self
+            .entries
+            .iter()
+            .map(|(k, v)| k.clone() + " " + v + "\n")
+            .collect::<String>();
+        match write_atomic(data.as_bytes(), locale) {
+            Ok(_) => FResult::Success,
+            Err(message) => FResult::IOError(message),
+        }
+    }
+
+    pub fn read(locale: &str) -> FResult<Self> {
+        let data = match std::fs::read_to_string(locale) {
+            Ok(x) => x,
+            Err(message) => {
+                if message.kind() == std::io::ErrorKind::NotFound {
+                    return FResult::FileDNE;
+                }
+                return FResult::IOError(message);
+            }
+        };
+        let mut res = Self::new();
+        for line in data.lines().filter(|l| !l.trim().is_empty()) {
+            match line.split_once(' ') {
+                Some((k, v)) => res.set(k, v.trim()),
+                None => return FResult::Err("Malformed line in job state"),
+            }
+        }
+        FResult::Value(res)
+    }
+
+    /// Reads the state file of the job folder
+    pub fn load(folder: &str) -> FResult<Self> {
+        Self::read(&locale(folder, STATE))
+    }
+
+    /// Writes the state file of the job folder
+    pub fn save(&self, folder: &str) -> FResult<()> {
+        self.write(&locale(folder, STATE))
+    }
+}
+
+/// Computation that can be run as a checkpointed Job
+///
+/// The computation is split into units evaluated in ascending order, each producing lines of output.
+/// Evaluating a unit must not depend on the results of prior units, as an interrupted unit is evaluated again
+pub trait Checkpoint: Sized + Clone {
+    /// Name of the computation, a job can only be recovered as the same kind of computation
+    const KIND: &'static str;
+
+    /// Writes the parameters of the computation to the job folder
+    fn save_state(&self, folder: &str) -> FResult<()>;
+
+    /// Restores the computation from the job folder
+    fn load_state(folder: &str) -> FResult<Self>;
+
+    /// Number of units the computation is split into
+    fn units(&self) -> u64;
+
+    /// Evaluates a unit returning the lines to append to the results
+    fn evaluate(&self, unit: u64) -> FResult<Vec<String>>;
+
+    /// Whether the computation is finished given the output of the latest unit, by default all units are evaluated
+    fn terminates(&self, _lines: &[String]) -> bool {
+        false
+    }
+}
+
+/**
+Checkpointed execution of a computation stored in a folder, resumable after crashes or reboots.
+
+```ignore
+ // Fermat pseudoprimes to base 2 under 2^40 in units of 2^32
+ let mut job = Job::initialise(FermatEval::new(2u64,0,1<<40,1<<32).unwrap(),"fermat2").unwrap();
+ job.run();
+ // after an interruption
+ let mut job = Job::<FermatEval<u64>>::recover("fermat2").unwrap();
+ job.run();
+ let pseudoprimes = job.results().unwrap();
+```
+*/
+#[derive(Clone)]
+pub struct Job<C: Checkpoint> {
+    folder: String,
+    computation: C,
+    // First unit that has not been completed
+    index: u64,
+    // Units completed beyond the index in ascending order, only nonempty when run in parallel
+    done: Vec<u64>,
+    // Units from the first that terminated the computation onwards are not evaluated
+    limit: u64,
+    results_len: u64,
+    complete: bool,
+}
+
+impl<C: Checkpoint> Job<C> {
+    /// Creates the job folder and stores the computation, fails if the folder already contains a job
+    pub fn initialise(computation: C, folder: &str) -> FResult<Self> {
+        Self::initialise_at(computation, folder, 0, &[])
+    }
+
+    /// Creates a job resuming at the unit with the results of the prior units, used to convert the folders of
+    /// prior versions. The checkpoint is written last so an interrupted conversion leaves no job
+    pub(crate) fn initialise_at(computation: C, folder: &str, index: u64, lines: &[String]) -> FResult<Self> {
+        if let Err(message) = std::fs::create_dir_all(folder).and_then(|_| sync_parent(folder)) {
+            return FResult::IOError(message);
+        }
+
+        match std::fs::exists(locale(folder, CHECKPOINT)) {
+            Ok(true) => return FResult::Err("Folder already contains a job"),
+            Ok(false) => (),
+            Err(message) => return FResult::IOError(message),
+        }
+
+        match computation.save_state(folder) {
+            FResult::Success => (),
+            failure => return failure.recast(),
+        }
+
+        let data = if lines.is_empty() {
+            String::new()
+        } else {
+            lines.join("\n") + "\n"
+        };
+
+        if let Err(message) = write_atomic(data.as_bytes(), &locale(folder, RESULTS)) {
+            return FResult::IOError(message);
+        }
+
+        let job = Self {
+            folder: folder.to_string(),
+            complete: index >= computation.units(),
+            computation,
+            index,
+            done: vec![],
+            limit: u64::MAX,
+            results_len: data.len() as u64,
+        };
+
+        match job.write_checkpoint() {
+            FResult::Success => FResult::Value(job),
+            failure => failure.recast(),
+        }
+    }
+
+    /// Restores the job from its last checkpoint, discarding any results of an interrupted unit
+    pub fn recover(folder: &str) -> FResult<Self> {
+        let checkpoint = match read_checkpoint(folder) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+
+        if checkpoint.kind != C::KIND {
+            return FResult::Err("Folder contains a different kind of job");
+        }
+
+        let computation = match C::load_state(folder) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+
+        let results = locale(folder, RESULTS);
+
+        match std::fs::OpenOptions::new().write(true).open(&results) {
+            Ok(file) => {
+                let len = match file.metadata() {
+                    Ok(m) => m.len(),
+                    Err(message) => return FResult::IOError(message),
+                };
+                if len < checkpoint.results {
+                    return FResult::Err("Results are shorter than recorded by the checkpoint");
+                }
+                // Output of the unit in progress when the job was interrupted
+                if len > checkpoint.results {
+                    if let Err(message) = file.set_len(checkpoint.results).and_then(|_| file.sync_all()) {
+                        return FResult::IOError(message);
+                    }
+                }
+            }
+            Err(message) => return FResult::IOError(message),
+        }
+
+        FResult::Value(Self {
+            folder: folder.to_string(),
+            computation,
+            index: checkpoint.index,
+            done: checkpoint.done,
+            limit: checkpoint.limit,
+            results_len: checkpoint.results,
+            complete: checkpoint.complete,
+        })
+    }
+
+    fn write_checkpoint(&self) -> FResult<()> {
+        let mut state = JobState::new();
+        state.set("kind", C::KIND);
+        state.set("index", self.index);
+        state.set("results", self.results_len);
+        state.set("complete", self.complete);
+        state.set("limit", self.limit);
+        state.set(
+            "done",
+            self.done.iter().map(|u| u.to_string()).collect::<Vec<String>>().join(","),
+        );
+
+        match state.write(&locale(&self.folder, CHECKPOINT)) {
+            FResult::Success => (),
+            failure => return failure,
+        }
+
+        if self.complete {
+            if let Err(message) = write_atomic(&[], &locale(&self.folder, COMPLETE)) {
+                return FResult::IOError(message);
+            }
+        }
+        FResult::Success
+    }
+
+    // Index after the last unit to evaluate
+    fn end(&self) -> u64 {
+        self.computation.units().min(self.limit)
+    }
+
+    // Records the output of a completed unit and checkpoints
+    fn complete_unit(&mut self, unit: u64, lines: &[String]) -> FResult<()> {
+        // Units that were evaluated concurrently with a unit terminating the computation
+        if unit < self.index || unit >= self.end() || self.done.binary_search(&unit).is_ok() {
+            return FResult::Success;
+        }
+
+        if !lines.is_empty() {
+            let data = lines.join("\n") + "\n";
+            if let Err(message) = append_synced(data.as_bytes(), &locale(&self.folder, RESULTS)) {
+                return FResult::IOError(message);
+            }
+            self.results_len += data.len() as u64;
+        }
+
+        if let Err(pos) = self.done.binary_search(&unit) {
+            self.done.insert(pos, unit);
+        }
+        // Advance past every consecutive completed unit
+        while self.done.first() == Some(&self.index) {
+            self.done.remove(0);
+            self.index += 1;
+        }
+
+        if self.computation.terminates(lines) {
+            self.limit = self.limit.min(unit + 1);
+            let limit = self.limit;
+            self.done.retain(|u| *u < limit);
+        }
+
+        self.complete = self.index >= self.end();
+        self.write_checkpoint()
+    }
+
+    /// Evaluates the next unit and checkpoints, returning true if the job is complete
+    pub fn step(&mut self) -> FResult<bool> {
+        if self.complete {
+            return FResult::Value(true);
+        }
+
+        let lines = match self.computation.evaluate(self.index) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+
+        match self.complete_unit(self.index, &lines) {
+            FResult::Success => FResult::Value(self.complete),
+            failure => failure.recast(),
+        }
+    }
+
+    /// Evaluates units until the job is complete
+    pub fn run(&mut self) -> FResult<()> {
+        loop {
+            match self.step() {
+                FResult::Value(true) => return FResult::Success,
+                FResult::Value(false) => (),
+                failure => return failure.recast(),
+            }
+        }
+    }
+
+    /// Evaluates the remaining units concurrently over the workers, suited to computations whose units are
+    /// single-threaded. report is called after each unit is checkpointed
+    ///
+    /// Results are appended in order of completion. The checkpoint records every completed unit so that recovery
+    /// evaluates exactly the unfinished units. If a unit terminates the computation no later units are started
+    pub fn run_par(&mut self, workers: usize, report: Arc<dyn Fn(&Progress) + Send + Sync>) -> FResult<()>
+    where
+        C: Send + Sync + 'static,
+    {
+        if self.complete {
+            return FResult::Success;
+        }
+
+        let start = std::time::Instant::now();
+        let computation: Arc<C> = Arc::new(self.computation.clone());
+        // Completed units are skipped
+        let done: Arc<Vec<u64>> = Arc::new(self.done.clone());
+        let next: Arc<AtomicU64> = Arc::new(AtomicU64::new(self.index));
+        let limit: Arc<AtomicU64> = Arc::new(AtomicU64::new(self.end()));
+        let halt: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        // The job and the number of units evaluated by this run
+        let shared: Arc<Mutex<(Self, u64)>> = Arc::new(Mutex::new((self.clone(), 0)));
+        let failure: Arc<Mutex<Option<FResult<()>>>> = Arc::new(Mutex::new(None));
+        let mut thread_vec: Vec<std::thread::JoinHandle<()>> = Vec::new();
+
+        for _ in 0..workers.max(1) {
+            let c_i = Arc::clone(&computation);
+            let d_i = Arc::clone(&done);
+            let n_i = Arc::clone(&next);
+            let l_i = Arc::clone(&limit);
+            let h_i = Arc::clone(&halt);
+            let s_i = Arc::clone(&shared);
+            let f_i = Arc::clone(&failure);
+            let r_i = Arc::clone(&report);
+
+            thread_vec.push(std::thread::spawn(move || loop {
+                if h_i.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let unit = n_i.fetch_add(1, Ordering::SeqCst);
+
+                if unit >= l_i.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                if d_i.binary_search(&unit).is_ok() {
+                    continue;
+                }
+
+                let res = match c_i.evaluate(unit) {
+                    FResult::Value(lines) => {
+                        let mut guard = s_i.lock().unwrap();
+                        let (job, evaluated) = &mut *guard;
+                        let res = job.complete_unit(unit, &lines);
+                        *evaluated += 1;
+                        l_i.fetch_min(job.end(), Ordering::SeqCst);
+                        r_i(&Progress {
+                            completed: job.completed(),
+                            units: job.end(),
+                            evaluated: *evaluated,
+                            elapsed: start.elapsed(),
+                        });
+                        res
+                    }
+                    failure => failure.recast(),
+                };
+
+                match res {
+                    FResult::Success => (),
+                    fail => {
+                        f_i.lock().unwrap().get_or_insert(fail);
+                        h_i.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                }
+            }));
+        }
+
+        for handle in thread_vec {
+            handle.join().unwrap();
+        }
+
+        *self = shared.lock().unwrap().0.clone();
+
+        let fail = failure.lock().unwrap().take();
+        fail.unwrap_or(FResult::Success)
+    }
+
+    pub fn folder(&self) -> &str {
+        &self.folder
+    }
+
+    pub fn computation(&self) -> &C {
+        &self.computation
+    }
+
+    /// Index of the next unit to evaluate
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    pub fn units(&self) -> u64 {
+        self.computation.units()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Number of completed units
+    pub fn completed(&self) -> u64 {
+        self.index + self.done.len() as u64
+    }
+
+    /// Output lines of all completed units
+    pub fn results(&self) -> FResult<Vec<String>> {
+        match std::fs::read(locale(&self.folder, RESULTS)) {
+            Ok(mut data) => {
+                data.truncate(self.results_len as usize);
+                match String::from_utf8(data) {
+                    Ok(x) => FResult::Value(x.lines().map(|l| l.to_string()).collect()),
+                    Err(_) => FResult::Err("Results are not valid utf-8"),
+                }
+            }
+            Err(message) => FResult::IOError(message),
+        }
+    }
+}
+
+// Progress recorded by the checkpoint
+#[derive(Clone)]
+struct Record {
+    kind: String,
+    index: u64,
+    done: Vec<u64>,
+    limit: u64,
+    results: u64,
+    complete: bool,
+}
+
+fn read_checkpoint(folder: &str) -> FResult<Record> {
+    let state = match JobState::read(&locale(folder, CHECKPOINT)) {
+        FResult::Value(x) => x,
+        FResult::Err(_) => return FResult::Err("Checkpoint is corrupt"),
+        failure => return failure.recast(),
+    };
+    let (kind, index, results, complete) = match (
+        state.get::<String>("kind"),
+        state.get::<u64>("index"),
+        state.get::<u64>("results"),
+        state.get::<bool>("complete"),
+    ) {
+        (FResult::Value(kind), FResult::Value(index), FResult::Value(results), FResult::Value(complete)) => {
+            (kind, index, results, complete)
+        }
+        _ => return FResult::Err("Checkpoint is missing the kind, index, results length or completion of the job"),
+    };
+    // Checkpoints of sequential runs from earlier versions record neither
+    let limit = match state.get::<u64>("limit") {
+        FResult::Value(x) => x,
+        _ => u64::MAX,
+    };
+    let mut done = vec![];
+    if let FResult::Value(list) = state.get::<String>("done") {
+        for u in list.split(',').filter(|u| !u.is_empty()) {
+            match u.parse::<u64>() {
+                Ok(x) if x > index => done.push(x),
+                _ => return FResult::Err("Checkpoint has a malformed list of completed units"),
+            }
+        }
+    }
+    done.sort();
+    done.dedup();
+    FResult::Value(Record {
+        kind,
+        index,
+        done,
+        limit,
+        results,
+        complete,
+    })
+}
+
+/// Progress of a job run in parallel
+#[derive(Clone, Debug)]
+pub struct Progress {
+    /// Units completed, including those of prior runs
+    pub completed: u64,
+    /// Total units to evaluate
+    pub units: u64,
+    /// Units evaluated by this run
+    pub evaluated: u64,
+    /// Time since this run started
+    pub elapsed: std::time::Duration,
+}
+
+impl Progress {
+    /// Units evaluated per second by this run
+    pub fn rate(&self) -> f64 {
+        self.evaluated as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+
+    /// Estimated time to complete the remaining units at the current rate
+    pub fn remaining(&self) -> std::time::Duration {
+        let rate = self.rate();
+        if rate == 0.0 {
+            return std::time::Duration::MAX;
+        }
+        std::time::Duration::from_secs_f64(self.units.saturating_sub(self.completed) as f64 / rate)
+    }
+}
+
+impl std::fmt::Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} units ({:.2}%) {:.3} units/s remaining {}s",
+            self.completed,
+            self.units,
+            100.0 * self.completed as f64 / self.units.max(1) as f64,
+            self.rate(),
+            self.remaining().as_secs()
+        )
+    }
+}
+
+// Computation of the squares of the units, for testing recovery
+#[cfg(test)]
+#[derive(Clone)]
+struct Squares {
+    units: u64,
+}
+
+#[cfg(test)]
+impl Checkpoint for Squares {
+    const KIND: &'static str = "squares";
+
+    fn save_state(&self, folder: &str) -> FResult<()> {
+        let mut state = JobState::new();
+        state.set("units", self.units);
+        state.save(folder)
+    }
+
+    fn load_state(folder: &str) -> FResult<Self> {
+        match JobState::load(folder) {
+            FResult::Value(state) => match state.get::<u64>("units") {
+                FResult::Value(units) => FResult::Value(Self { units }),
+                failure => failure.recast(),
+            },
+            failure => failure.recast(),
+        }
+    }
+
+    fn units(&self) -> u64 {
+        self.units
+    }
+
+    fn evaluate(&self, unit: u64) -> FResult<Vec<String>> {
+        FResult::Value(vec![(unit * unit).to_string()])
+    }
+}
+
+#[test]
+fn job_recovery() {
+    let folder = std::env::temp_dir().join(format!("f-analysis-job-{}", std::process::id()));
+    let folder = folder.to_str().unwrap();
+    let _ = std::fs::remove_dir_all(folder);
+    let expected = (0..8u64).map(|u| (u * u).to_string()).collect::<Vec<String>>();
+
+    let mut job = Job::initialise(Squares { units: 8 }, folder).unwrap();
+    for _ in 0..3 {
+        job.step().unwrap();
+    }
+    // Output of a unit interrupted before its checkpoint
+    append_synced(b"9\n16", &locale(folder, RESULTS)).unwrap();
+
+    let mut job = Job::<Squares>::recover(folder).unwrap();
+    assert_eq!(job.index(), 3);
+    assert_eq!(job.results().unwrap(), expected[..3]);
+    // Units completed out of order by a parallel run
+    assert!(matches!(job.complete_unit(5, &[expected[5].clone()]), FResult::Success));
+    assert!(matches!(job.complete_unit(6, &[expected[6].clone()]), FResult::Success));
+
+    let mut job = Job::<Squares>::recover(folder).unwrap();
+    assert_eq!((job.index(), job.completed()), (3, 5));
+    assert!(matches!(job.run_par(2, Arc::new(|_: &Progress| ())), FResult::Success));
+    let mut results = job.results().unwrap();
+    results.sort_by_key(|x| x.parse::<u64>().unwrap());
+    assert_eq!(results, expected);
+
+    let checkpoint = locale(folder, CHECKPOINT);
+    let data = std::fs::read_to_string(&checkpoint).unwrap();
+    // Truncated checkpoint
+    std::fs::write(&checkpoint, &data[..data.len() / 2]).unwrap();
+    assert!(matches!(Job::<Squares>::recover(folder), FResult::Err(_)));
+    // Corrupt checkpoint
+    std::fs::write(&checkpoint, data.replace("index", "index x")).unwrap();
+    assert!(matches!(Job::<Squares>::recover(folder), FResult::Err(_)));
+    // Results shorter than recorded
+    std::fs::write(&checkpoint, &data).unwrap();
+    std::fs::write(locale(folder, RESULTS), "0\n").unwrap();
+    assert!(matches!(Job::<Squares>::recover(folder), FResult::Err(_)));
+
+    std::fs::remove_dir_all(folder).unwrap();
+}
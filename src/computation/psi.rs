@@ -1,4 +1,5 @@
 use crate::{structures::{BaseSeq,ResidueClass,store::Persistent},search::thread_count,Epz,FResult, computation::compio::*};
+use crate::computation::job::{locale,persist,Checkpoint,Job,JobState};
 /*
 
   Estimation of Jaeschke's psi_k function 
@@ -43,26 +44,55 @@ pub struct PsiEval{
      witness: BaseSeq<u64>,
      floor : u64,
      ceil : u64,
-     index: u64,
      // Execution Mode 
    //  exec: Execution,
+     mode: Mode,
      folder: String,
 }
 
+// Search performed over the secondary residues, selected by the first run of the job
+#[derive(Clone,Copy,PartialEq,Debug)]
+enum Mode{
+   Unset,
+   BoundSearch,
+   Enumerate,
+}
+
+impl std::fmt::Display for Mode{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+       match self{
+         Mode::Unset => write!(f,"unset"),
+         Mode::BoundSearch => write!(f,"bound"),
+         Mode::Enumerate => write!(f,"enumerate"),
+       }
+    }
+}
 
+impl std::str::FromStr for Mode{
+   type Err = ();
+   
+   fn from_str(x: &str) -> Result<Self,()>{
+      match x{
+        "unset" => Ok(Mode::Unset),
+        "bound" => Ok(Mode::BoundSearch),
+        "enumerate" => Ok(Mode::Enumerate),
+        _=> Err(()),
+      }
+   }
+}
 
 impl PsiEval{
 
-  pub fn initialise(k: Option<u64>, memory_bound: u64, bound: Option<Epz<3>>, folder: &str, bound_search: bool){
-      let psi = BaseSeq::<u64>::first_primes(k.unwrap());
-      psi.to_persistent(&(folder.to_owned()+"/witness"));
+  pub fn initialise(k: Option<u64>, memory_bound: u64, bound: Option<Epz<3>>, folder: &str, bound_search: bool) -> FResult<()>{
+      let (k,bnd) = match (k,bound){
+        (Some(x),Some(y)) => (x,y),
+        _=> return FResult::Err("Number of witnesses and bound are required"),
+      };
+      let psi = BaseSeq::<u64>::first_primes(k);
       let res1 = BaseSeq::<u64>::first_primes(12).mr_residues(1u64<<32);  
-      res1.to_persistent(&(folder.to_owned()+"/primaryresidue"));
        
       let res2 = ResidueClass::from_qr(41).unify(&ResidueClass::from_qr(43)).unify(&ResidueClass::from_qr(47));
       
-      res2.to_persistent(&(folder.to_owned()+"/secondaryresidue"));
-      
       let ring = Epz::<3>::from(2459559130353965640u64);
       
       let mut ceil = 1;
@@ -71,8 +101,6 @@ impl PsiEval{
       if bound_search{
          tc = thread_count() as u64;
       }
-
-      let bnd = bound.unwrap();
       
       while Epz::<3>::from(ceil*tc)*ring < bnd{
          ceil+=1;
@@ -81,92 +109,190 @@ impl PsiEval{
       ceil*=tc;
       let floor = 0u64;
       
-      std::fs::write(folder.to_owned()+"/height",(floor.to_string()+"\n"+&ceil.to_string()).as_bytes());
-      std::fs::File::create(folder.to_owned()+"/pseudoprimes");
+      let eval = PsiEval{
+         res1,
+         res2,
+         witness: psi,
+         floor,
+         ceil,
+         mode: Mode::Unset,
+         folder: folder.to_string(),
+      };
+      
+      match Job::initialise(eval,folder){
+        FResult::Value(_) => FResult::Success,
+        failure => failure.recast(),
+      }
   }
   
   
-  
+  /// Restores the evaluation, folders of prior versions are converted resuming after the last recorded index
   pub fn recover(folder : &str) -> FResult<Self>{
      match std::fs::exists(folder){
         Ok(existence) => {
-           if existence{
-              let witness = BaseSeq::from_persistent(&(folder.to_owned()+"/witness")).unwrap();
-              let primary = ResidueClass::from_persistent(&(folder.to_owned()+"/primaryresidue")).unwrap();
-              let secondary = ResidueClass::from_persistent(&(folder.to_owned()+"/secondaryresidue")).unwrap();
-              let indexfile = folder.to_owned()+"/index";
-              let mut idx = 0u64;
-              let (floor,ceil) = load_bounds(&(folder.to_owned()+"/height")).unwrap();
-              
-              if std::fs::exists(indexfile.clone()).unwrap(){
-               idx= std::fs::read_to_string(indexfile).unwrap().trim().parse::<u64>().unwrap();
-              }
-              return FResult::Value(PsiEval{
-               res1: primary,
-               res2: secondary,
-               witness: witness,
-               index : idx,
-               floor : floor,
-               ceil: ceil,
-               //height: height,
-               folder: folder.to_string(),
-              });
-           }
-           else{
-             return FResult::NoCandidate;
+           if !existence{
+             return FResult::FileDNE;
            }
         }
-        Err(mess) => FResult::IOError(mess), 
+        Err(mess) => return FResult::IOError(mess), 
+     }
+     
+     if !std::fs::exists(locale(folder,"checkpoint")).unwrap_or(false) && std::fs::exists(locale(folder,"height")).unwrap_or(false){
+        match Self::migrate(folder){
+          FResult::Success => (),
+          failure => return failure.recast(),
+        }
+     }
+     
+     match Job::<Self>::recover(folder){
+        FResult::Value(job) => FResult::Value(job.computation().clone()),
+        failure => failure.recast(),
      }
   }
   
-  /// Enumerate possible bounds
-  pub fn bound_search(&mut self){
-
-     
-     for (idx,el) in self.res2.iter().enumerate(){
+  // Converts the height, index and pseudoprimes files to a job
+  fn migrate(folder: &str) -> FResult<()>{
+     let (floor,ceil) = match load_bounds(&locale(folder,"height")){
+       Some(x) => x,
+       None => return FResult::Err("Legacy height file is missing or corrupt"),
+     };
+     let eval = match Self::load_residues(folder,floor,ceil,Mode::Unset){
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+     };
+     // The index records the last completed residue
+     let indexfile = locale(folder,"index");
+     let mut next = 0u64;
+     if std::fs::exists(&indexfile).unwrap_or(false){
+        match load_idx(&indexfile){
+          Some(idx) => next = idx+1,
+          None => return FResult::Err("Legacy index file is corrupt"),
+        }
+     }
+     let found = std::fs::read_to_string(locale(folder,"pseudoprimes")).unwrap_or_default().lines().map(|l| l.to_string()).collect::<Vec<String>>();
      
-       if (idx as u64) < self.index{
-          continue;
-       }
-       
-            let mut residues = self.res1.clone();
-            
-       residues.coprime_promote(*el,82861);
-       
-       match self.witness.mr_bound_epz_par(residues.clone(),self.floor,self.ceil){
-         FResult::NoCandidate => {std::fs::write(self.folder.clone()+"/index",idx.to_string().as_bytes());},
-         FResult::Value(p) => {
-               appender(&p.to_string(),&(self.folder.clone()+"/pseudoprimes"));               
-         },
-         _=> panic!("Unknown error"),
-       }
+     match Job::initialise_at(eval,folder,next,&found){
+       FResult::Value(_) => FResult::Success,
+       failure => failure.recast(),
+     }
+  }
+  
+  fn load_residues(folder: &str, floor: u64, ceil: u64, mode: Mode) -> FResult<Self>{
+     let witness = match BaseSeq::from_persistent(&locale(folder,"witness")){
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+     };
+     let primary = match ResidueClass::from_persistent(&locale(folder,"primaryresidue")){
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+     };
+     let secondary = match ResidueClass::from_persistent(&locale(folder,"secondaryresidue")){
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+     };
+     FResult::Value(PsiEval{
+        res1: primary,
+        res2: secondary,
+        witness,
+        floor,
+        ceil,
+        mode,
+        folder: folder.to_string(),
+     })
+  }
+  
+  // Runs the job in the mode, the first run selects the mode of the job
+  fn run(&mut self, mode: Mode) -> FResult<()>{
+     if self.mode == Mode::Unset{
+        self.mode = mode;
+        match self.save_state(&self.folder){
+          FResult::Success => (),
+          failure => return failure,
+        }
+     }
+     if self.mode != mode{
+        return FResult::Err("Job was started with a different search");
+     }
+     match Job::<Self>::recover(&self.folder){
+        FResult::Value(mut job) => job.run(),
+        failure => failure.recast(),
      }
   }
   
+  /// Enumerate possible bounds, the pseudoprimes are appended to the results file of the folder
+  pub fn bound_search(&mut self) -> FResult<()>{
+     self.run(Mode::BoundSearch)
+  }
+  
    // Calculate height as bound/ring during initialisation
-  pub fn enumerate_pseudo(&mut self){
-
-     
-     for (idx,el) in self.res2.iter().enumerate(){
-     
-       if (idx as u64) < self.index{
-         continue;
-       }
-
-            let mut residues = self.res1.clone();
-            
-       residues.coprime_promote(*el,82861);
-       
-       let p = self.witness.mr_semiprimes_par(&residues,self.floor,self.ceil).to_string();
-       
-       if p.len() != 0{
-       appender(&p,&(self.folder.clone()+"/pseudoprimes"));  
-       }  
-       std::fs::write(self.folder.clone()+"/index",idx.to_string().as_bytes()); 
-       
+  /// Enumerate the pseudoprimes, appended to the results file of the folder
+  pub fn enumerate_pseudo(&mut self) -> FResult<()>{
+     self.run(Mode::Enumerate)
   }
   
 }
-  
+
+impl Checkpoint for PsiEval{
+   const KIND: &'static str = "psi";
+   
+   fn save_state(&self, folder: &str) -> FResult<()>{
+      // The residues are only written by initialise
+      if !std::fs::exists(locale(folder,"secondaryresidue")).unwrap_or(false){
+        for (file,res) in [("primaryresidue",&self.res1),("secondaryresidue",&self.res2)]{
+          match persist(res,folder,file){
+            FResult::Success => (),
+            failure => return failure,
+          }
+        }
+        match persist(&self.witness,folder,"witness"){
+          FResult::Success => (),
+          failure => return failure,
+        }
+      }
+      let mut state = JobState::new();
+      state.set("floor",self.floor);
+      state.set("ceil",self.ceil);
+      state.set("mode",self.mode);
+      state.save(folder)
+   }
+   
+   fn load_state(folder: &str) -> FResult<Self>{
+      let state = match JobState::load(folder){
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+      };
+      match (state.get::<u64>("floor"),state.get::<u64>("ceil"),state.get::<String>("mode")){
+        (FResult::Value(floor),FResult::Value(ceil),FResult::Value(mode)) => {
+           match mode.parse::<Mode>(){
+             Ok(m) => Self::load_residues(folder,floor,ceil,m),
+             Err(_) => FResult::Err("Psi state has an unknown search mode"),
+           }
+        }
+        _=> FResult::Err("Psi state is missing the floor, ceiling or search mode"),
+      }
+   }
+   
+   fn units(&self) -> u64{
+      self.res2.cardinality() as u64
+   }
+   
+   fn evaluate(&self, unit: u64) -> FResult<Vec<String>>{
+      let mut residues = self.res1.clone();
+      residues.coprime_promote(self.res2.elements[unit as usize],82861);
+      
+      match self.mode{
+        Mode::BoundSearch => {
+          match self.witness.mr_bound_epz_par(residues,self.floor,self.ceil){
+            FResult::NoCandidate => FResult::Value(vec![]),
+            FResult::Value(p) => FResult::Value(vec![p.to_string()]),
+            failure => failure.recast(),
+          }
+        }
+        Mode::Enumerate => {
+          let p = self.witness.mr_semiprimes_par(&residues,self.floor,self.ceil).to_string();
+          FResult::Value(p.lines().map(|l| l.to_string()).collect())
+        }
+        Mode::Unset => FResult::Err("Search mode has not been selected"),
+      }
+   }
 }
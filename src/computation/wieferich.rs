@@ -1,55 +1,167 @@
 use crate::computation::compio::*;
-use crate::Interval;
-
-pub struct WieferichEval{
-   base: u64,
-   index: u64,
-   lowerbound: u64,
-   upperbound: u64,
-   folder: String
+use crate::computation::job::{locale, Checkpoint, Job, JobState, Progress};
+use crate::search::thread_count;
+use crate::{FResult, Interval};
+use std::sync::Arc;
+
+// Length of the interval searched by each unit
+const STRIDE: u64 = 1u64 << 28;
+
+/// Search for Wieferich primes to a base in strides of 2^28 integers, each checkpointed on completion
+#[derive(Clone, Debug)]
+pub struct WieferichEval {
+    base: u64,
+    lowerbound: u64,
+    upperbound: u64,
+    folder: String,
+}
+
+impl WieferichEval {
+    pub fn initialise(base: u64, lowerbound: u64, upperbound: u64, folder: &str) -> FResult<()> {
+        if lowerbound >= upperbound {
+            return FResult::Err("Lower bound must be less than the upper bound");
+        }
+        let eval = Self {
+            base,
+            lowerbound,
+            upperbound,
+            folder: folder.to_string(),
+        };
+        match Job::initialise(eval, folder) {
+            FResult::Value(_) => FResult::Success,
+            failure => failure.recast(),
+        }
+    }
+
+    /// Restores the search, folders of prior versions storing only the index of the last stride are converted
+    pub fn recover(folder: &str) -> FResult<Self> {
+        if !std::fs::exists(locale(folder, "checkpoint")).unwrap_or(false)
+            && std::fs::exists(locale(folder, "index")).unwrap_or(false)
+        {
+            match Self::migrate(folder) {
+                FResult::Success => (),
+                failure => return failure.recast(),
+            }
+        }
+        match Job::<Self>::recover(folder) {
+            FResult::Value(job) => FResult::Value(job.computation().clone()),
+            failure => failure.recast(),
+        }
+    }
+
+    // Converts the base, bound, index and primes files to a job resuming at the stride of the index
+    fn migrate(folder: &str) -> FResult<()> {
+        let (base, index, (lowerbound, upperbound)) = match (
+            load_idx(&locale(folder, "base")),
+            load_idx(&locale(folder, "index")),
+            load_bounds(&locale(folder, "bound")),
+        ) {
+            (Some(b), Some(i), Some(bnd)) => (b, i, bnd),
+            _ => return FResult::Err("Legacy base, index or bound file is missing or corrupt"),
+        };
+
+        if index < lowerbound || lowerbound >= upperbound {
+            return FResult::Err("Legacy index lies outside the bounds");
+        }
+
+        let eval = Self {
+            base,
+            lowerbound,
+            upperbound,
+            folder: folder.to_string(),
+        };
+
+        // The stride starting at the index may not have been completed
+        let unit = (index - lowerbound) / STRIDE;
+        let start = lowerbound + unit * STRIDE;
+
+        let primes = match std::fs::read_to_string(locale(folder, "primes")) {
+            Ok(x) => x,
+            Err(message) => return FResult::IOError(message),
+        };
+
+        let found = primes
+            .lines()
+            .filter_map(|p| p.trim().parse::<u64>().ok())
+            .filter(|p| *p < start)
+            .map(|p| p.to_string())
+            .collect::<Vec<String>>();
+
+        match Job::initialise_at(eval, folder, unit, &found) {
+            FResult::Value(_) => FResult::Success,
+            failure => failure.recast(),
+        }
+    }
+
+    /// Resumes the search until the upper bound, the primes are appended to the results file of the folder
+    ///
+    /// Strides are searched concurrently by thread_count() workers, the progress and throughput are printed
+    /// as each stride completes
+    pub fn run(&self) -> FResult<()> {
+        let base = self.base;
+        let report = move |p: &Progress| {
+            println!(
+                "wieferich base {} {} {:.4e} integers/s",
+                base,
+                p,
+                p.rate() * STRIDE as f64
+            )
+        };
+        match Job::<Self>::recover(&self.folder) {
+            FResult::Value(mut job) => job.run_par(thread_count(), Arc::new(report)),
+            failure => failure.recast(),
+        }
+    }
 }
 
-impl WieferichEval{
-   pub fn initialise(base: u64, lowerbound: u64, upperbound: u64, folder: &str){
-      assert!(lowerbound<upperbound);
-      std::fs::create_dir(folder);
-      let basefile = folder.to_string()+"/base";
-      write_single(base,&basefile);
-      let boundfile = folder.to_string()+"/bound";
-      let idxfile = folder.to_string()+"/index";
-      write_single(lowerbound,&idxfile);
-      write_bounds(lowerbound,upperbound,&boundfile);
-      create_file(&(folder.to_string()+"/primes"));
-   }
-   
-   pub fn recover(folder: &str) -> Self{
-      let base = load_idx(&(folder.to_string()+"/base")).unwrap();
-      let index = load_idx((&(folder.to_string()+"/index"))).unwrap();
-      let (lowerbound,upperbound) = load_bounds(&(folder.to_string()+"/bound")).unwrap();
-      Self{base,index,lowerbound,upperbound,folder: folder.to_string()}
-   }
-   
-   pub fn run(&self){
-      const STRIDE : u64 = 1u64<<28;
-      let mut start = self.index;
-      loop {
-         let interval = Interval::<u64>::new(start,start+STRIDE);
-         let wieferichs = interval.wieferich_search(self.base);
-         if wieferichs.cardinality() != 0{
-            let pstring = wieferichs.iter().map(|p|p.to_string()).collect::<Vec<String>>();
-            let dataout = pstring.join("\n")+"\n";
-            appender(&dataout,&(self.folder.clone()+"/primes"));
-         }
-         write_single(start,&(self.folder.clone()+"/index"));
-         
-         start+=STRIDE;
-
-         if start >= self.upperbound{
-           break;
-         }
-      }
-      // Split the large computation over several intervals
-      
-   }  
-        
-  }
+impl Checkpoint for WieferichEval {
+    const KIND: &'static str = "wieferich";
+
+    fn save_state(&self, folder: &str) -> FResult<()> {
+        let mut state = JobState::new();
+        state.set("base", self.base);
+        state.set("inf", self.lowerbound);
+        state.set("sup", self.upperbound);
+        state.save(folder)
+    }
+
+    fn load_state(folder: &str) -> FResult<Self> {
+        let state = match JobState::load(folder) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        match (state.get::<u64>("base"), state.get::<u64>("inf"), state.get::<u64>("sup")) {
+            (FResult::Value(base), FResult::Value(lowerbound), FResult::Value(upperbound))
+                if lowerbound < upperbound =>
+            {
+                FResult::Value(Self {
+                    base,
+                    lowerbound,
+                    upperbound,
+                    folder: folder.to_string(),
+                })
+            }
+            _ => FResult::Err("Wieferich state is missing or has invalid base or bounds"),
+        }
+    }
+
+    fn units(&self) -> u64 {
+        (self.upperbound - self.lowerbound).div_ceil(STRIDE)
+    }
+
+    fn evaluate(&self, unit: u64) -> FResult<Vec<String>> {
+        let start = self.lowerbound + unit * STRIDE;
+        let stop = start.saturating_add(STRIDE).min(self.upperbound);
+        // Units are evaluated concurrently so each is single-threaded. The search evaluates whole blocks of the
+        // primorial, so values outside the stride are removed
+        let wieferichs = Interval::<u64>::new(start, stop).wieferich_search_st(self.base);
+        let inf = start.max(2);
+        FResult::Value(
+            wieferichs
+                .iter()
+                .filter(|p| **p >= inf && **p < stop)
+                .map(|p| p.to_string())
+                .collect(),
+        )
+    }
+}
@@ -70,6 +70,23 @@ impl<T: Clone> FResult<T> {
           _=> panic!("Value does not exist"),
         }
     }
+
+    /// Propagates a failure to a result of another type. Success is preserved, values cannot be converted and
+    /// are Critical
+    pub(crate) fn recast<K: Clone>(self) -> FResult<K> {
+        match self{
+          FResult::NotSupported => FResult::NotSupported,
+          FResult::MemoryExceeded(mem) => FResult::MemoryExceeded(mem),
+          FResult::Failure => FResult::Failure,
+          FResult::InsufficientCandidates(x) => FResult::InsufficientCandidates(x),
+          FResult::NoCandidate => FResult::NoCandidate,
+          FResult::IOError(message) => FResult::IOError(message),
+          FResult::FileDNE => FResult::FileDNE,
+          FResult::Err(message) => FResult::Err(message),
+          FResult::Success => FResult::Success,
+          FResult::Critical | FResult::Partial(_,_) | FResult::Value(_) => FResult::Critical,
+        }
+    }
     /*
     pub fn map<K : Clone>(&self, func: &dyn Fn(Self) -> FResult<K>) -> FResult<K>{
          
@@ -79,8 +96,10 @@ impl<T: Clone> FResult<T> {
 
 /// Classification of a number according to Fermat primality tests
 ///
-/// Prime ⊂ Strong ⊂ EulerJacobi ⊂ Euler ⊂ Fermat ⊂  Composite  
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+/// Prime ⊂ Strong ⊂ EulerPlumb ⊂ EulerJacobi ⊂ Euler ⊂ Fermat ⊂  Composite  
+///
+/// Classes are ordered by the strength of the test, EulerPlumb was added after the other discriminants were fixed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Pseudoprime {
     Composite = 0,
     Fermat = 1,
@@ -88,12 +107,52 @@ pub enum Pseudoprime {
     EulerJacobi = 3,
     Strong = 4,
     Prime = 5,
+    EulerPlumb = 6,
+}
+
+impl Pseudoprime {
+    // Position in the order of strength
+    fn strength(&self) -> u8 {
+        match self {
+            Pseudoprime::Composite => 0,
+            Pseudoprime::Fermat => 1,
+            Pseudoprime::Euler => 2,
+            Pseudoprime::EulerJacobi => 3,
+            Pseudoprime::EulerPlumb => 4,
+            Pseudoprime::Strong => 5,
+            Pseudoprime::Prime => 6,
+        }
+    }
+}
+
+impl PartialOrd for Pseudoprime {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pseudoprime {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.strength().cmp(&other.strength())
+    }
+}
+
+/// Languages that hashtables are exported to as primality tests
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceLang {
+    /// Constant arrays and `pub fn is_prime(n: u64) -> bool`
+    Rust,
+    /// Header of static arrays and `static inline bool is_prime(uint64_t n)`, using unsigned __int128
+    C,
+    /// Module of tuples and `is_prime(n)`
+    Python,
 }
 
 /// Enum of search variants
 /// WeakHeuristic, StrongHeuristic, and Deterministic are successively supersets of the previous one
 /// i.e all the values tested by the WeakHeuristic will be tested by the StrongHeuristic
-/// Other variants if implemented will have no guarantee of overlap
+/// Other variants have no guarantee of overlap. The composites of each are listed by FamilyGenerator::from_search
+/// for Interval and FamilyGenerator::from_base_search for BaseSeq
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Search {
     // Use a weak heuristic; very fast but only an estimate
@@ -102,19 +161,21 @@ pub enum Search {
     /// Performs poorly at proving a base set eliminates all composites
     /// Almost certainly fails at generating all pseudoprimes to a set of bases
     /// # Forms
-    // Currently the semiprimes of the form (2x+1)(4x+1)
+    /// Currently the semiprimes of the form (2x+1)(ax+1) where a is 3, 4 or 6 for BaseSeq and ranges from 3 to 6 for
+    /// Interval, and the tabulated Carmichael numbers
     WeakHeuristic,
     // Use a strong heuristic, slower but much more likely to be correct
     /// # Accuracy
     /// Same as WeakHeuristic but stronger in all cases, and over large intervals
     /// appears to frequently correctly show that the base sets eliminate all composites
     /// # Forms
-    /// Currently the WeakHeuristic and semiprimes of the form (ak+1)(k+1) where a ranges from 2 to 63 inclusive.
+    /// Currently the WeakHeuristic and semiprimes of the form (ak+1)(k+1) where a ranges from 2 to 63 inclusive for
+    /// BaseSeq and from 2 to 2047 inclusive for Interval.
     StrongHeuristic,
     // Very slow but provides guaranteed correctness
     Deterministic,
 
-    /// Use the candidates for the Monier-Rabin bound
+    /// Use the candidates for the Monier-Rabin bound, the semiprimes (k+1)(2k+1) and the tabulated Carmichael numbers
     MRHeuristic,
     /// Use the composites of the form (ak+1)(k+1) where ak+1 and k+1 are prime
     SPKHeuristic(usize),
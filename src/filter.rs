@@ -24,11 +24,15 @@
 
    ```
 */
+pub(crate) mod expr;
 pub(crate) mod fermat;
 pub(crate) mod filtertype;
 pub(crate) mod form;
 pub(crate) mod ftraits;
 pub(crate) mod gfilter;
+pub(crate) mod lucas;
+pub(crate) mod parse;
 
+pub use expr::FilterExpr;
 pub use filtertype::*;
-pub use ftraits::{Coprime, EulerFermat, FormCheck, GenericFilter, StrongFermat, WeakFermat};
+pub use ftraits::{Coprime, EulerFermat, FormCheck, GenericFilter, Lucas, StrongFermat, WeakFermat};
@@ -0,0 +1,111 @@
+use crate::{HashTable, Natural};
+use std::ops::{BitAnd, BitOr, Not};
+
+/// Filter composed at runtime, the run-time equivalent to the GenericFilter types
+///
+/// Expressions are combined with the &, | and ! operators, and evaluated by check
+/// ```
+/// use f_analysis::filter::FilterExpr;
+///
+/// // Passes strong pseudoprimes to base 2 and 3 that are not of the form (2k+1)(4k+1)
+/// let expr = FilterExpr::Sprp(2u64) & FilterExpr::Sprp(3) & !FilterExpr::Spk(2, 4);
+/// assert!(expr.check(25326001));
+/// // 1373653 = 829*1657 is eliminated by the form check
+/// assert!(!expr.check(1373653));
+/// ```
+#[derive(Clone)]
+pub enum FilterExpr<T: Natural> {
+    /// Always true
+    True,
+    /// Always false
+    False,
+    /// Fermat test to the base
+    Fermat(T),
+    /// Euler-Jacobi test to the base
+    EulerJacobi(T),
+    /// Strong fermat test to the base
+    Sprp(T),
+    /// Strong fermat test to each of the bases
+    Bases(Vec<T>),
+    /// Coprime to the integer
+    Coprime(T),
+    /// Coprime to the first S primes, where 1 <= S <= 128. False for any other S
+    Trial(usize),
+    /// Prime
+    Prime,
+    /// Perfect square
+    Square,
+    /// Perfect power of the exponent, not supported by Epz
+    Power(usize),
+    /// Semiprime of the form (pk+1)(qk+1)
+    Spk(u64, u64),
+    /// Semiprime of the form (pk+1)(qk+1), calculated probabilistically
+    Spkh(u64, u64),
+    /// Within the interval (inf;sup), the same bounds as CompVector::filter_range
+    Range(T, T),
+    /// Passes the strong fermat test selected by the hashtable
+    Table(HashTable),
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Not(Box<Self>),
+}
+
+impl<T: Natural> FilterExpr<T> {
+    /// Conjunction of the expressions, True if empty
+    pub fn all(exprs: Vec<Self>) -> Self {
+        exprs.into_iter().reduce(|acc, e| acc & e).unwrap_or(Self::True)
+    }
+
+    /// Disjunction of the expressions, False if empty
+    pub fn any(exprs: Vec<Self>) -> Self {
+        exprs.into_iter().reduce(|acc, e| acc | e).unwrap_or(Self::False)
+    }
+
+    /// Evaluates the expression, returning true if the integer satisfies it
+    pub fn check(&self, x: T) -> bool {
+        match self {
+            Self::True => true,
+            Self::False => false,
+            Self::Fermat(a) => x.fermat(*a),
+            Self::EulerJacobi(a) => x.euler_jacobi(*a),
+            Self::Sprp(a) => x.sprp(*a),
+            Self::Bases(b) => b.iter().all(|a| x.sprp(*a)),
+            Self::Coprime(a) => x.gcd(*a) == T::ONE,
+            Self::Trial(s) => (1..=128).contains(s) && x.trial_bound(*s),
+            Self::Prime => x.is_prime(),
+            Self::Square => x.is_square(),
+            Self::Power(a) => x.is_power_of(*a),
+            Self::Spk(p, q) => x.is_spk(*p, *q),
+            Self::Spkh(p, q) => x.is_spkh(*p, *q),
+            Self::Range(inf, sup) => x.is_bounded_by(*inf, *sup),
+            Self::Table(ht) => ht.primality(x),
+            Self::And(a, b) => a.check(x) && b.check(x),
+            Self::Or(a, b) => a.check(x) || b.check(x),
+            Self::Not(a) => !a.check(x),
+        }
+    }
+}
+
+impl<T: Natural> BitAnd for FilterExpr<T> {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self::And(Box::new(self), Box::new(other))
+    }
+}
+
+impl<T: Natural> BitOr for FilterExpr<T> {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self::Or(Box::new(self), Box::new(other))
+    }
+}
+
+impl<T: Natural> Not for FilterExpr<T> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+}
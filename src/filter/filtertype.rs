@@ -11,9 +11,32 @@ pub struct NQR;
 /// Euler-Plumb Fermat test (modified 2-fermat)
 pub struct EPF;
 
+/// Weak Fermat test to base S
+pub struct FBase<const S: usize>;
+/// Euler test, a^(n-1)/2 = ±1, to base S
+pub struct EBase<const S: usize>;
+/// Euler-Jacobi test to base S
+pub struct EJBase<const S: usize>;
+/// Euler-Plumb test to base S, strictly between Euler-Jacobi and Strong Fermat
+pub struct EPBase<const S: usize>;
+
 // First quadratic residue fermat base selection
 //pub struct QR;
 
+/// Lucas probable prime test with Selfridge's parameters
+pub struct LPRP;
+/// Strong Lucas probable prime test with Selfridge's parameters
+pub struct SLPRP;
+/// Extra strong Lucas probable prime test with Baillie's parameters
+pub struct XSLPRP;
+/// Quadratic Frobenius probable prime test with Selfridge's parameters
+pub struct Frobenius;
+
+/// Strong Fermat test to base S and the quadratic Frobenius test
+pub struct SFrobenius<const S: usize>;
+/// Baillie-PSW, Strong Fermat test to base 2 and the strong Lucas test with Selfridge's parameters
+pub struct BPSW;
+
 /// Prime First Base, Fermat test using the first S prime bases
 pub struct PFB<const S: usize>;
 
@@ -20,6 +20,11 @@ pub trait StrongFermat: EulerFermat + GenericFilter {
     fn sprp<T: Natural>(x: T) -> bool;
 }
 
+/// Trait implementing Lucas probable prime tests, the non-Fermat component of BPSW-style tests
+pub trait Lucas: GenericFilter {
+    fn lucas<T: Natural>(x: T) -> bool;
+}
+
 /// Trait implementing checks for integers coprime to some set of integers
 pub trait Coprime: GenericFilter {
     fn coprime<T: Natural>(x: T) -> bool;
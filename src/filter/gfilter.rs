@@ -58,6 +58,72 @@ impl GenericFilter for EPF {
         x.euler_p()
     }
 }
+
+impl GenericFilter for LPRP {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.lucas()
+    }
+}
+
+impl GenericFilter for SLPRP {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.strong_lucas()
+    }
+}
+
+impl GenericFilter for XSLPRP {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.extra_strong_lucas()
+    }
+}
+
+impl GenericFilter for Frobenius {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.frobenius()
+    }
+}
+
+impl<const S: usize> GenericFilter for SFrobenius<S> {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        if !x.sprp(T::from(S as u64)) {
+            return false;
+        }
+        x.frobenius()
+    }
+}
+
+impl GenericFilter for BPSW {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        if !x.sprp(T::from(2)) {
+            return false;
+        }
+        x.strong_lucas()
+    }
+}
+
+impl<const S: usize> GenericFilter for FBase<S> {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.fermat(T::from(S as u64))
+    }
+}
+
+impl<const S: usize> GenericFilter for EBase<S> {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.euler(T::from(S as u64))
+    }
+}
+
+impl<const S: usize> GenericFilter for EJBase<S> {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.euler_jacobi(T::from(S as u64))
+    }
+}
+
+impl<const S: usize> GenericFilter for EPBase<S> {
+    fn filter_check<T: Natural>(x: T) -> bool {
+        x.euler_plumb(T::from(S as u64))
+    }
+}
 // First quadratic residue fermat base selection
 //pub struct QR;
 
@@ -0,0 +1,27 @@
+use crate::filter::filtertype::*;
+use crate::filter::ftraits::Lucas;
+use crate::Natural;
+
+impl Lucas for LPRP {
+    fn lucas<T: Natural>(x: T) -> bool {
+        x.lucas()
+    }
+}
+
+impl Lucas for SLPRP {
+    fn lucas<T: Natural>(x: T) -> bool {
+        x.strong_lucas()
+    }
+}
+
+impl Lucas for XSLPRP {
+    fn lucas<T: Natural>(x: T) -> bool {
+        x.extra_strong_lucas()
+    }
+}
+
+impl Lucas for Frobenius {
+    fn lucas<T: Natural>(x: T) -> bool {
+        x.frobenius()
+    }
+}
@@ -0,0 +1,491 @@
+use crate::filter::FilterExpr;
+use crate::{FResult, HashTable, Natural, Persistent};
+
+/*
+   Textual filter expressions
+
+     expr    := and ('|' and)*
+     and     := unary ('&' unary)*
+     unary   := '!' unary | '(' expr ')' | NAME ['(' arg (',' arg)* ')']
+     arg     := STRING | sum
+     sum     := product (('+'|'-') product)*
+     product := power ('*' power)*
+     power   := NUMBER ['^' power] | '(' sum ')'
+
+   NUMBER is decimal with an optional exponent of 10 e.g 1e12, arithmetic is evaluated in 128 bits.
+   Whitespace is ignored and '#' comments out the remainder of a line so that expressions can be kept in files
+*/
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(String),
+    Name(String),
+    Str(String),
+    LParen,
+    RParen,
+    Comma,
+    And,
+    Or,
+    Not,
+    Plus,
+    Minus,
+    Star,
+    Caret,
+}
+
+fn tokenize(expr: &str) -> FResult<Vec<Token>> {
+    let mut res = vec![];
+    let mut chars = expr.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let tok = match c {
+            '#' => {
+                while chars.next_if(|x| *x != '\n').is_some() {}
+                continue;
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            // && and || are accepted as synonyms
+            '&' => {
+                chars.next_if_eq(&'&');
+                Token::And
+            }
+            '|' => {
+                chars.next_if_eq(&'|');
+                Token::Or
+            }
+            '!' => Token::Not,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '^' => Token::Caret,
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(x) => s.push(x),
+                        None => return FResult::Err("Unterminated string"),
+                    }
+                }
+                Token::Str(s)
+            }
+            x if x.is_ascii_digit() => {
+                let mut s = x.to_string();
+                while let Some(d) = chars.next_if(|d| d.is_ascii_alphanumeric() || *d == '_') {
+                    s.push(d);
+                }
+                Token::Num(s)
+            }
+            x if x.is_ascii_alphabetic() || x == '_' => {
+                let mut s = x.to_string();
+                while let Some(d) = chars.next_if(|d| d.is_ascii_alphanumeric() || *d == '_') {
+                    s.push(d);
+                }
+                Token::Name(s.to_ascii_lowercase())
+            }
+            x if x.is_whitespace() => continue,
+            _ => return FResult::Err("Unexpected character in filter expression"),
+        };
+        res.push(tok);
+    }
+    FResult::Value(res)
+}
+
+// Decimal literal with an optional exponent of 10, underscores are permitted as separators
+fn literal(x: &str) -> FResult<u128> {
+    let digits = x.replace('_', "");
+    let (mantissa, exp) = match digits.split_once(['e', 'E']) {
+        Some((m, e)) => (m.to_string(), e.to_string()),
+        None => (digits, "0".to_string()),
+    };
+    let m = match mantissa.parse::<u128>() {
+        Ok(v) => v,
+        Err(_) => return FResult::Err("Invalid integer"),
+    };
+    let e = match exp.parse::<u32>() {
+        Ok(v) => v,
+        Err(_) => return FResult::Err("Invalid integer"),
+    };
+    match 10u128.checked_pow(e).and_then(|p| m.checked_mul(p)) {
+        Some(v) => FResult::Value(v),
+        None => FResult::Err("Integer exceeds 128 bits"),
+    }
+}
+
+// Argument of a filter in the expression
+#[derive(Clone)]
+enum Arg {
+    Int(u128),
+    Str(String),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let res = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        res
+    }
+
+    fn eat(&mut self, tok: &Token) -> bool {
+        if self.peek() == Some(tok) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, tok: &Token, message: &'static str) -> FResult<()> {
+        if self.eat(tok) {
+            return FResult::Success;
+        }
+        FResult::Err(message)
+    }
+
+    fn or<T: Natural>(&mut self) -> FResult<FilterExpr<T>> {
+        let mut res = match self.and() {
+            FResult::Value(x) => x,
+            failure => return failure,
+        };
+        while self.eat(&Token::Or) {
+            match self.and() {
+                FResult::Value(x) => res = res | x,
+                failure => return failure,
+            }
+        }
+        FResult::Value(res)
+    }
+
+    fn and<T: Natural>(&mut self) -> FResult<FilterExpr<T>> {
+        let mut res = match self.unary() {
+            FResult::Value(x) => x,
+            failure => return failure,
+        };
+        while self.eat(&Token::And) {
+            match self.unary() {
+                FResult::Value(x) => res = res & x,
+                failure => return failure,
+            }
+        }
+        FResult::Value(res)
+    }
+
+    fn unary<T: Natural>(&mut self) -> FResult<FilterExpr<T>> {
+        match self.next() {
+            Some(Token::Not) => match self.unary() {
+                FResult::Value(x) => FResult::Value(!x),
+                failure => failure,
+            },
+            Some(Token::LParen) => {
+                let res = match self.or() {
+                    FResult::Value(x) => x,
+                    failure => return failure,
+                };
+                match self.expect(&Token::RParen, "Expected )") {
+                    FResult::Success => FResult::Value(res),
+                    failure => failure.recast(),
+                }
+            }
+            Some(Token::Name(name)) => {
+                let mut args = vec![];
+                if self.eat(&Token::LParen) {
+                    loop {
+                        match self.arg() {
+                            FResult::Value(x) => args.push(x),
+                            failure => return failure.recast(),
+                        }
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        if !self.eat(&Token::Comma) {
+                            return FResult::Err("Expected , or )");
+                        }
+                    }
+                }
+                named(&name, args)
+            }
+            _ => FResult::Err("Expected a filter"),
+        }
+    }
+
+    fn arg(&mut self) -> FResult<Arg> {
+        if let Some(Token::Str(s)) = self.peek() {
+            let res = Arg::Str(s.clone());
+            self.pos += 1;
+            return FResult::Value(res);
+        }
+        match self.sum() {
+            FResult::Value(x) => FResult::Value(Arg::Int(x)),
+            failure => failure.recast(),
+        }
+    }
+
+    fn sum(&mut self) -> FResult<u128> {
+        let mut res = match self.product() {
+            FResult::Value(x) => x,
+            failure => return failure,
+        };
+        loop {
+            let add = if self.eat(&Token::Plus) {
+                true
+            } else if self.eat(&Token::Minus) {
+                false
+            } else {
+                return FResult::Value(res);
+            };
+            let rhs = match self.product() {
+                FResult::Value(x) => x,
+                failure => return failure,
+            };
+            let val = if add { res.checked_add(rhs) } else { res.checked_sub(rhs) };
+            res = match val {
+                Some(x) => x,
+                None => return FResult::Err("Integer out of bounds"),
+            };
+        }
+    }
+
+    fn product(&mut self) -> FResult<u128> {
+        let mut res = match self.power() {
+            FResult::Value(x) => x,
+            failure => return failure,
+        };
+        while self.eat(&Token::Star) {
+            let rhs = match self.power() {
+                FResult::Value(x) => x,
+                failure => return failure,
+            };
+            res = match res.checked_mul(rhs) {
+                Some(x) => x,
+                None => return FResult::Err("Integer exceeds 128 bits"),
+            };
+        }
+        FResult::Value(res)
+    }
+
+    fn power(&mut self) -> FResult<u128> {
+        let base = match self.next() {
+            Some(Token::Num(x)) => match literal(&x) {
+                FResult::Value(v) => v,
+                failure => return failure,
+            },
+            Some(Token::LParen) => {
+                let res = match self.sum() {
+                    FResult::Value(x) => x,
+                    failure => return failure,
+                };
+                match self.expect(&Token::RParen, "Expected )") {
+                    FResult::Success => res,
+                    failure => return failure.recast(),
+                }
+            }
+            _ => return FResult::Err("Expected an integer"),
+        };
+        if !self.eat(&Token::Caret) {
+            return FResult::Value(base);
+        }
+        let exp = match self.power() {
+            FResult::Value(x) => x,
+            failure => return failure,
+        };
+        // 2^128 is permitted and saturates, so that range(0,2^128) covers every 128-bit integer
+        match u32::try_from(exp).ok().and_then(|e| base.checked_pow(e)) {
+            Some(x) => FResult::Value(x),
+            None if base == 2 && exp == 128 => FResult::Value(u128::MAX),
+            None => FResult::Err("Integer exceeds 128 bits"),
+        }
+    }
+}
+
+// Converts to the integer type, saturating if it exceeds the width of T
+fn to_natural<T: Natural>(x: u128) -> T {
+    match x.to_string().parse::<T>() {
+        Ok(v) => v,
+        Err(_) => T::from(u64::MAX),
+    }
+}
+
+fn integers(args: &[Arg]) -> FResult<Vec<u128>> {
+    let mut res = vec![];
+    for i in args {
+        match i {
+            Arg::Int(x) => res.push(*x),
+            Arg::Str(_) => return FResult::Err("Expected an integer argument"),
+        }
+    }
+    FResult::Value(res)
+}
+
+// Small arguments such as exponents and forms
+fn small(x: u128) -> FResult<u64> {
+    match u64::try_from(x) {
+        Ok(v) => FResult::Value(v),
+        Err(_) => FResult::Err("Argument exceeds 64 bits"),
+    }
+}
+
+fn named<T: Natural>(name: &str, args: Vec<Arg>) -> FResult<FilterExpr<T>> {
+    if let ("table" | "hashtable", [Arg::Str(locale)]) = (name, &args[..]) {
+        return match HashTable::from_persistent(locale) {
+            FResult::Value(ht) => FResult::Value(FilterExpr::Table(ht)),
+            failure => failure.recast(),
+        };
+    }
+
+    let ints = match integers(&args) {
+        FResult::Value(x) => x,
+        failure => return failure.recast(),
+    };
+    let bases = || ints.iter().map(|x| to_natural::<T>(*x)).collect::<Vec<T>>();
+
+    let res = match (name, ints.len()) {
+        ("true", 0) => FilterExpr::True,
+        ("false", 0) => FilterExpr::False,
+        ("prime", 0) => FilterExpr::Prime,
+        ("square", 0) => FilterExpr::Square,
+        ("sprp", 1) => FilterExpr::Sprp(bases()[0]),
+        ("sprp", l) if l > 1 => FilterExpr::Bases(bases()),
+        ("fermat", l) if l > 0 => FilterExpr::all(bases().into_iter().map(FilterExpr::Fermat).collect()),
+        ("ej" | "euler_jacobi", l) if l > 0 => {
+            FilterExpr::all(bases().into_iter().map(FilterExpr::EulerJacobi).collect())
+        }
+        ("coprime" | "gcd", 1) => FilterExpr::Coprime(bases()[0]),
+        ("trial", 1) => match small(ints[0]) {
+            FResult::Value(s) if (1..=128).contains(&s) => FilterExpr::Trial(s as usize),
+            FResult::Value(_) => return FResult::Err("Trial division is supported for the first 1 to 128 primes"),
+            failure => return failure.recast(),
+        },
+        ("power", 1) => match small(ints[0]) {
+            FResult::Value(k) => FilterExpr::Power(k as usize),
+            failure => return failure.recast(),
+        },
+        // spk(q) is the form (k+1)(qk+1)
+        ("spk" | "spkh", 1 | 2) => {
+            let mut form = vec![];
+            for i in ints.iter() {
+                match small(*i) {
+                    FResult::Value(x) => form.push(x),
+                    failure => return failure.recast(),
+                }
+            }
+            let (p, q) = if form.len() == 1 { (1, form[0]) } else { (form[0], form[1]) };
+            if name == "spk" {
+                FilterExpr::Spk(p, q)
+            } else {
+                FilterExpr::Spkh(p, q)
+            }
+        }
+        ("range", 2) => FilterExpr::Range(to_natural(ints[0]), to_natural(ints[1])),
+        (
+            "true" | "false" | "prime" | "square" | "sprp" | "fermat" | "ej" | "euler_jacobi" | "coprime" | "gcd"
+            | "trial" | "power" | "spk" | "spkh" | "range" | "table" | "hashtable",
+            _,
+        ) => return FResult::Err("Incorrect arguments to filter"),
+        _ => return FResult::Err("Unknown filter"),
+    };
+    FResult::Value(res)
+}
+
+impl<T: Natural> FilterExpr<T> {
+    /// Parses a textual filter expression e.g "sprp(2) & sprp(3) & !spk(2) & range(1e12, 2^64)"
+    ///
+    /// Filters are combined with & (and), | (or), ! (not) and parentheses, in increasing order of precedence.
+    /// Integer arguments may be written with exponents and arithmetic, e.g 1e12 or 2^64-1, integers exceeding the
+    /// width of T saturate. The filters are
+    /// - true, false
+    /// - prime, square, power(k)
+    /// - sprp(a,..), fermat(a,..), ej(a,..) strong fermat, fermat and Euler-Jacobi tests to each base
+    /// - coprime(a) coprime to a, and trial(s) coprime to the first s primes, 1 <= s <= 128
+    /// - spk(q), spk(p,q) semiprimes of the form (k+1)(qk+1) and (pk+1)(qk+1), spkh for the probabilistic check
+    /// - range(inf,sup) within (inf;sup)
+    /// - table("file") strong fermat test selected by a hashtable loaded from file
+    /// ```
+    /// use f_analysis::filter::FilterExpr;
+    /// use f_analysis::FResult;
+    ///
+    /// let expr = FilterExpr::<u64>::parse("sprp(2) & sprp(3) & !spk(2) & range(1e6, 2^64)").unwrap();
+    /// assert!(expr.check(25326001));
+    /// assert!(!expr.check(1373653));
+    /// assert!(matches!(FilterExpr::<u64>::parse("trial(0)"), FResult::Err(_)));
+    /// ```
+    pub fn parse(expr: &str) -> FResult<Self> {
+        let tokens = match tokenize(expr) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let mut parser = Parser { tokens, pos: 0 };
+        let res = match parser.or() {
+            FResult::Value(x) => x,
+            failure => return failure,
+        };
+        if parser.pos != parser.tokens.len() {
+            return FResult::Err("Unexpected trailing input in filter expression");
+        }
+        FResult::Value(res)
+    }
+}
+
+#[test]
+fn parse_precedence() {
+    let eval = |expr: &str| FilterExpr::<u64>::parse(expr).unwrap().check(0);
+    // & binds tighter than |, and ! tighter than &
+    assert!(eval("true | false & !true"));
+    assert!(eval("!true | true"));
+    assert!(!eval("!(true | true)"));
+    assert!(!eval("(true | false) & !true"));
+    assert!(eval("((false) | (true & !false))"));
+
+    match FilterExpr::<u64>::parse("sprp(2) | sprp(3) & !prime").unwrap() {
+        FilterExpr::Or(lhs, rhs) => match (*lhs, *rhs) {
+            (FilterExpr::Sprp(2), FilterExpr::And(lhs, rhs)) => {
+                assert!(matches!(*lhs, FilterExpr::Sprp(3)));
+                assert!(matches!(*rhs, FilterExpr::Not(x) if matches!(*x, FilterExpr::Prime)));
+            }
+            _ => panic!("Expected sprp(2) | (sprp(3) & !prime)"),
+        },
+        _ => panic!("Expected a disjunction"),
+    }
+    let expr = FilterExpr::<u64>::parse("sprp(2) & (sprp(3) | prime)").unwrap();
+    assert!(expr.check(1373653) && expr.check(97) && !expr.check(2047));
+}
+
+#[test]
+fn parse_errors() {
+    let err = |expr: &str| match FilterExpr::<u64>::parse(expr) {
+        FResult::Err(message) => message,
+        _ => panic!("{} was parsed", expr),
+    };
+    assert_eq!(err("foo(2)"), "Unknown filter");
+    assert_eq!(err("sprp()"), "Expected an integer");
+    assert_eq!(err("sprp"), "Incorrect arguments to filter");
+    assert_eq!(err("range(1)"), "Incorrect arguments to filter");
+    assert_eq!(err("prime(3)"), "Incorrect arguments to filter");
+    assert_eq!(err("true true"), "Unexpected trailing input in filter expression");
+    assert_eq!(err("sprp(2))"), "Unexpected trailing input in filter expression");
+    assert_eq!(err("table(\"file"), "Unterminated string");
+    assert_eq!(err("trial(0)"), "Trial division is supported for the first 1 to 128 primes");
+    assert_eq!(err("trial(129)"), "Trial division is supported for the first 1 to 128 primes");
+    assert!(matches!(FilterExpr::<u64>::parse("trial(128)"), FResult::Value(FilterExpr::Trial(128))));
+}
+
+#[test]
+fn parse_saturation() {
+    // Integers beyond the width of T saturate
+    assert!(matches!(FilterExpr::<u64>::parse("range(0, 2^64)"), FResult::Value(FilterExpr::Range(0, u64::MAX))));
+    assert!(matches!(FilterExpr::<u64>::parse("sprp(2^64+1)"), FResult::Value(FilterExpr::Sprp(u64::MAX))));
+    assert!(matches!(
+        FilterExpr::<u128>::parse("range(1e12, 2^128)"),
+        FResult::Value(FilterExpr::Range(1_000_000_000_000, u128::MAX))
+    ));
+    assert!(matches!(FilterExpr::<u64>::parse("range(0, 2^129)"), FResult::Err(_)));
+}
@@ -1,2 +1,3 @@
+pub mod header;
 pub mod read;
 pub mod write;
@@ -0,0 +1,321 @@
+use crate::Natural;
+#[cfg(test)]
+use crate::FResult;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/*
+   Versioned binary format for CompVector files
+
+   All fields little-endian
+
+   magic           4 bytes  "FAcv"
+   version         u16
+   byte_length     u16      Natural::byte_length of the elements
+   flags           u32      bit 0 set if the elements are in ascending order
+   count           u64      number of elements
+   checksum        u64      FNV-1a hash of the element bytes
+   metadata_len    u32
+   metadata        utf-8 description of how the elements were generated
+
+   Files that do not begin with the magic and version are read as legacy headerless files of raw integers. Files
+   that do are rejected if the lengths or metadata are inconsistent, e.g truncated or never finished by BinaryWriter
+*/
+
+pub(crate) const MAGIC: [u8; 4] = *b"FAcv";
+pub(crate) const VERSION: u16 = 1;
+// Length of the header excluding metadata
+pub(crate) const FIXED_LENGTH: usize = 32;
+
+const SORTED_FLAG: u32 = 1;
+pub(crate) const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Header of a binary CompVector file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileHeader {
+    /// Number of bytes used to store each element
+    pub byte_length: u16,
+    /// Number of elements stored
+    pub count: u64,
+    /// True if the elements are stored in ascending order
+    pub sorted: bool,
+    /// FNV-1a hash of the element bytes
+    pub checksum: u64,
+    /// Description of the generating base set, interval or other provenance
+    pub metadata: String,
+}
+
+impl std::fmt::Display for FileHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "version {} elements {} x {} bytes sorted {} checksum {:016x} {}",
+            VERSION, self.count, self.byte_length, self.sorted, self.checksum, self.metadata
+        )
+    }
+}
+
+pub(crate) fn checksum_update(hash: u64, bytes: &[u8]) -> u64 {
+    let mut h = hash;
+    for i in bytes {
+        h ^= *i as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    h
+}
+
+impl FileHeader {
+    pub(crate) fn new<T: Natural>(metadata: &str) -> Self {
+        Self {
+            byte_length: T::BYTE_LENGTH as u16,
+            count: 0,
+            sorted: true,
+            checksum: FNV_OFFSET,
+            metadata: metadata.to_string(),
+        }
+    }
+
+    /// Header describing the elements of a vector
+    pub(crate) fn from_elements<T: Natural>(x: &[T], metadata: &str) -> Self {
+        let mut res = Self::new::<T>(metadata);
+        for i in x.iter() {
+            res.checksum = checksum_update(res.checksum, &i.to_bytes()[..]);
+        }
+        res.count = x.len() as u64;
+        res.sorted = x.windows(2).all(|w| w[0] <= w[1]);
+        res
+    }
+
+    /// Number of bytes preceding the elements
+    pub fn length(&self) -> u64 {
+        (FIXED_LENGTH + self.metadata.len()) as u64
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(self.length() as usize);
+        let flags = if self.sorted { SORTED_FLAG } else { 0 };
+        res.extend_from_slice(&MAGIC[..]);
+        res.extend_from_slice(&VERSION.to_le_bytes()[..]);
+        res.extend_from_slice(&self.byte_length.to_le_bytes()[..]);
+        res.extend_from_slice(&flags.to_le_bytes()[..]);
+        res.extend_from_slice(&self.count.to_le_bytes()[..]);
+        res.extend_from_slice(&self.checksum.to_le_bytes()[..]);
+        res.extend_from_slice(&(self.metadata.len() as u32).to_le_bytes()[..]);
+        res.extend_from_slice(self.metadata.as_bytes());
+        res
+    }
+
+    /// Reads the header from the start of the file, leaving the cursor at the first element
+    ///
+    /// Returns None if the file is a legacy headerless file, the cursor is then at the start of the file
+    /// # InvalidData
+    /// The file begins with the magic and version but the lengths do not match the file or the metadata is not utf-8
+    pub(crate) fn read(file: &std::fs::File) -> std::io::Result<Option<Self>> {
+        let mut f = file;
+        let file_len = f.metadata()?.len();
+        f.seek(SeekFrom::Start(0))?;
+
+        let mut fixed = [0u8; FIXED_LENGTH];
+        let prefix = (file_len as usize).min(FIXED_LENGTH);
+        f.read_exact(&mut fixed[..prefix])?;
+
+        if prefix < 6 || fixed[..4] != MAGIC[..] || u16::from_le_bytes([fixed[4], fixed[5]]) != VERSION {
+            f.seek(SeekFrom::Start(0))?;
+            return Ok(None);
+        }
+
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string());
+
+        if prefix < FIXED_LENGTH {
+            return Err(invalid("Truncated header"));
+        }
+
+        let u16_at = |i: usize| u16::from_le_bytes([fixed[i], fixed[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes(fixed[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(fixed[i..i + 8].try_into().unwrap());
+
+        let meta_len = u32_at(28) as u64;
+        let byte_length = u16_at(6);
+        let count = u64_at(12);
+
+        let expected = (byte_length as u64)
+            .checked_mul(count)
+            .and_then(|x| x.checked_add(FIXED_LENGTH as u64 + meta_len));
+
+        if byte_length == 0 || expected != Some(file_len) {
+            return Err(invalid("Length of file does not match header"));
+        }
+
+        let mut meta = vec![0u8; meta_len as usize];
+        f.read_exact(&mut meta[..])?;
+
+        match String::from_utf8(meta) {
+            Ok(metadata) => Ok(Some(Self {
+                byte_length,
+                count,
+                sorted: u32_at(8) & SORTED_FLAG == SORTED_FLAG,
+                checksum: u64_at(20),
+                metadata,
+            })),
+            Err(_) => Err(invalid("Metadata is not utf-8")),
+        }
+    }
+
+    /// Computes the checksum of the elements following the header
+    pub(crate) fn verify(&self, file: &std::fs::File) -> std::io::Result<bool> {
+        let mut f = file;
+        f.seek(SeekFrom::Start(self.length()))?;
+
+        let mut r = std::io::BufReader::new(f);
+        let mut buffer = vec![0u8; 1 << 16];
+        let mut hash = FNV_OFFSET;
+
+        loop {
+            let totalbytes = r.read(&mut buffer[..])?;
+            if totalbytes == 0 {
+                break;
+            }
+            hash = checksum_update(hash, &buffer[..totalbytes]);
+        }
+        f.seek(SeekFrom::Start(self.length()))?;
+        Ok(hash == self.checksum)
+    }
+}
+
+/// Seeks to the first element of the file returning its offset, 0 for legacy headerless files
+pub(crate) fn seek_payload(file: &std::fs::File) -> std::io::Result<u64> {
+    match FileHeader::read(file)? {
+        Some(h) => Ok(h.length()),
+        None => Ok(0),
+    }
+}
+
+/// Streaming writer of the versioned binary format
+///
+/// The count, sorted flag and checksum are accumulated as elements are written and the header is rewritten by finish
+pub(crate) struct BinaryWriter<T: Natural> {
+    file: std::fs::File,
+    out: std::io::BufWriter<std::fs::File>,
+    header: FileHeader,
+    last: Option<T>,
+}
+
+impl<T: Natural> BinaryWriter<T> {
+    /// Creates (or truncates) the file, opened for both reading and writing
+    pub(crate) fn create(locale: &str, metadata: &str) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(locale)?;
+        Self::from_file(file, metadata)
+    }
+
+    /// Writes to an already open file from the start, truncating any previous content
+    pub(crate) fn from_file(file: std::fs::File, metadata: &str) -> std::io::Result<Self> {
+        let header = FileHeader::new::<T>(metadata);
+        let mut f = &file;
+        f.set_len(0)?;
+        f.seek(SeekFrom::Start(0))?;
+        f.write_all(&header.to_bytes()[..])?;
+
+        Ok(Self {
+            out: std::io::BufWriter::new(file.try_clone()?),
+            file,
+            header,
+            last: None,
+        })
+    }
+
+    pub(crate) fn write(&mut self, x: T) -> std::io::Result<()> {
+        let bytes = x.to_bytes();
+        self.out.write_all(&bytes[..])?;
+        self.header.checksum = checksum_update(self.header.checksum, &bytes[..]);
+        self.header.count += 1;
+
+        if let Some(prev) = self.last {
+            if prev > x {
+                self.header.sorted = false;
+            }
+        }
+        self.last = Some(x);
+        Ok(())
+    }
+
+    /// Flushes the elements and writes the completed header, returning the file
+    pub(crate) fn finish(mut self) -> std::io::Result<std::fs::File> {
+        self.out.flush()?;
+        let mut f = &self.file;
+        f.seek(SeekFrom::Start(0))?;
+        f.write_all(&self.header.to_bytes()[..])?;
+        f.flush()?;
+        f.seek(SeekFrom::Start(self.header.length()))?;
+        Ok(self.file)
+    }
+}
+
+#[test]
+fn header_read() {
+    use crate::CompVector;
+
+    let folder = std::env::temp_dir().join(format!("f-analysis-header-{}", std::process::id()));
+    let _ = std::fs::create_dir_all(&folder);
+    let locale = |name: &str| folder.join(name).to_str().unwrap().to_string();
+    let open = |name: &str| {
+        std::fs::OpenOptions::new().read(true).write(true).open(locale(name)).unwrap()
+    };
+    let elements = [341u64, 561, 645, 1105];
+
+    // Round trip
+    let mut out = BinaryWriter::<u64>::create(&locale("round"), "bases 2").unwrap();
+    for i in elements {
+        out.write(i).unwrap();
+    }
+    out.finish().unwrap();
+    let h = FileHeader::read(&open("round")).unwrap().unwrap();
+    assert_eq!(h, FileHeader::from_elements(&elements[..], "bases 2"));
+    assert!(h.verify(&open("round")).unwrap());
+    let ce = CompVector::<u64>::from_file(&locale("round")).unwrap();
+    assert_eq!(ce.load_to_memory().unwrap().to_vector(), elements.to_vec());
+
+    // Legacy headerless file, read from the start
+    let raw = elements.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<u8>>();
+    std::fs::write(locale("legacy"), &raw).unwrap();
+    let mut f = open("legacy");
+    assert_eq!(FileHeader::read(&f).unwrap(), None);
+    assert_eq!(f.stream_position().unwrap(), 0);
+    let ce = CompVector::<u64>::from_file(&locale("legacy")).unwrap();
+    assert_eq!(ce.load_to_memory().unwrap().to_vector(), elements.to_vec());
+
+    // Checksum mismatch
+    let mut bytes = std::fs::read(locale("round")).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    std::fs::write(locale("corrupt"), &bytes).unwrap();
+    assert!(!FileHeader::read(&open("corrupt")).unwrap().unwrap().verify(&open("corrupt")).unwrap());
+    assert!(matches!(CompVector::<u64>::from_file(&locale("corrupt")), FResult::Err(_)));
+
+    // Truncated elements, truncated header and invalid metadata
+    bytes[last] ^= 1;
+    for (name, content) in [
+        ("short", bytes[..bytes.len() - 8].to_vec()),
+        ("header", bytes[..FIXED_LENGTH - 4].to_vec()),
+        ("utf8", [&bytes[..FIXED_LENGTH], &[0xff, 0xfe][..], &bytes[FIXED_LENGTH + 2..]].concat()),
+    ] {
+        std::fs::write(locale(name), &content).unwrap();
+        let err = FileHeader::read(&open(name)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}", name);
+        assert!(matches!(CompVector::<u64>::from_file(&locale(name)), FResult::IOError(_)), "{}", name);
+    }
+
+    // Writer interrupted before finish
+    let mut out = BinaryWriter::<u64>::create(&locale("unfinished"), "bases 2").unwrap();
+    for i in elements {
+        out.write(i).unwrap();
+    }
+    out.out.flush().unwrap();
+    assert!(FileHeader::read(&open("unfinished")).is_err());
+
+    let _ = std::fs::remove_dir_all(&folder);
+}
@@ -31,13 +31,18 @@ pub(crate) mod stat;
 mod enums;
 pub mod filter;
 
-pub use crate::enums::{FResult, Pseudoprime};
+pub use crate::enums::{FResult, Pseudoprime, Search, SourceLang};
+pub use crate::io::header::FileHeader;
 pub use crate::natural::{Epz, Natural};
+pub use crate::search::{AnnealParam, EvoParam};
 pub use crate::stat::Stats;
-pub use crate::computation::{PsiEval,WieferichEval};
+pub use crate::computation::{
+    Checkpoint, CorrectorEval, FermatEval, HeuristicEval, Job, JobState, MRBoundEval, Progress, PsiEval,
+    WieferichEval,
+};
 pub use crate::structures::{
-    store::Persistent, BaseSeq, CompVector, Constructor, CounterExamples, DataVector, HashTable,
-    Interval, PCGenerator, Primes, SOSet, SmallOrd, WieferichPrime, ResidueClass,SolVector
+    store::Persistent, BaseSeq, BoundCertificate, BoundFamily, Carmichael, Certificate, CompVector, CompositeFamily, Constructor, CounterExamples, DataVector, FamilyCover, FamilyGenerator, HashTable,
+    Interval, PCGenerator, Primes, SOSet, SmallOrd, WieferichPrime, ResidueClass, SolVector, Witness,
 };
 
 /*
@@ -57,8 +62,6 @@ pub use crate::structures::{
 
    Things to investigate, do different bases take different numbers of steps?
 
-   Implement R.E Pinch's fermat algorithm, implement Sorenson & Websters algorithm
-
 
    Conjectures to test
 
@@ -2,6 +2,7 @@ pub(crate) mod eightbytes;
 pub(crate) mod extended;
 pub(crate) mod factor;
 pub(crate) mod ftrait;
+pub(crate) mod lucas;
 pub(crate) mod montcore;
 pub(crate) mod rand;
 pub(crate) mod sixteenbytes;
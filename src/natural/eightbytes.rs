@@ -1,5 +1,6 @@
 use crate::natural::{
     factor::{factorize, Factorization},
+    lucas::{lucas_test, LucasMode},
     montcore::NTCore,
     rand::{rand},
 };
@@ -201,8 +202,10 @@ impl Natural for u64 {
     fn gcd(&self, other: Self) -> Self {
         let mut a = *self;
         let mut b = other;
-        // Handle the case where a = 0 outside this function, most applications should have no use for it
-        debug_assert!(a !=0);
+        // Pollard-Brent takes the gcd of a product that may be 0 mod n
+        if a == 0 {
+            return b;
+        }
         if b == 0 {
             return a;
            }
@@ -345,6 +348,8 @@ impl Natural for u64 {
         }
 
         for i in 1..tzc {
+            // a^(p-1)/4 when evaluating a^(p-1)/2
+            let quarter = mbase;
             mbase = machine_prime::mont_prod(mbase, mbase, inv ,*self);
             if mbase == oneinv {
                 return Pseudoprime::Strong;
@@ -352,6 +357,10 @@ impl Natural for u64 {
             if i == tzc - 1 {
                 let sym = a.jacobi(*self);
                 if sym == 1 && mbase == one {
+                    // -1 would have been Strong
+                    if quarter == one {
+                        return Pseudoprime::EulerPlumb;
+                    }
                     return Pseudoprime::EulerJacobi;
                 }
                 if mbase == one {
@@ -403,11 +412,12 @@ impl Natural for u64 {
         unimplemented!()
     }
 
-    fn sqr_fermat(&self, base: Self) -> bool {
-        if *self > 1u64 << 32 {
-            return (*self as u128).p_sq_fermat(base as u128);
+    // p^2 overflows 64 bits beyond 2^32 so the 128-bit core is used
+    fn sqr_fermat(&self, p: Self) -> bool {
+        if p > 1u64 << 32 {
+            (*self as u128).p_sq_fermat(p as u128)
         } else {
-            return self.p_sq_fermat(base);
+            self.p_sq_fermat(p)
         }
     }
 
@@ -527,6 +537,14 @@ impl Natural for u64 {
 
     }
 
+    fn euler(&self, a: Self) -> bool {
+        if self & 1 == 0 {
+            return NTCore::fermat(self, a);
+        }
+        let res = a.exp_residue((*self - 1) / 2, *self);
+        res == 1 || res == *self - 1
+    }
+
     fn euler_jacobi(&self, a: Self) -> bool {
         let r = a.jacobi(*self);
         if r == -1 {
@@ -535,6 +553,24 @@ impl Natural for u64 {
         a.exp_residue((*self - 1) / 2, *self) == r as u64
     }
 
+    fn euler_plumb(&self, a: Self) -> bool {
+        if self & 1 == 0 {
+            return NTCore::fermat(self, a);
+        }
+        let pminus = *self - 1;
+        let r = a.jacobi(*self);
+        if r == 1 && *self & 3 == 1 {
+            let res = a.exp_residue(pminus >> 2, *self);
+            return res == 1 || res == pminus;
+        }
+        let res = a.exp_residue(pminus >> 1, *self);
+        match r {
+            1 => res == 1,
+            -1 => res == pminus,
+            _ => false,
+        }
+    }
+
     
     fn sprp(&self, a: Self) -> bool {
         if self & 1 == 0 {
@@ -551,6 +587,22 @@ impl Natural for u64 {
         machine_prime::is_prime(*self)
     }
 
+    fn lucas(&self) -> bool {
+        lucas_test(*self, LucasMode::Standard)
+    }
+
+    fn strong_lucas(&self) -> bool {
+        lucas_test(*self, LucasMode::Strong)
+    }
+
+    fn extra_strong_lucas(&self) -> bool {
+        lucas_test(*self, LucasMode::ExtraStrong)
+    }
+
+    fn frobenius(&self) -> bool {
+        lucas_test(*self, LucasMode::Frobenius)
+    }
+
     fn euler_p(&self) -> bool {
         let residue = *self & 7;
         let mut param = 0;
@@ -68,10 +68,9 @@ impl<const S: usize> Shr<u32> for Epz<S> {
           res.limbs[lastidx-offset-i]=self.limbs[lastidx-i];
         }
         
-        let carry = shr_slice(&mut res.limbs[..], shift, 0u64);
-        
-        debug_assert!(carry==0);
-        
+        // The bits shifted out of the least significant limb are discarded
+        shr_slice(&mut res.limbs[..], shift, 0u64);
+
         res
         //let mut clonus = self;
         //clonus >>= shift;
@@ -225,9 +224,16 @@ impl<const S: usize> Div for Epz<S> {
 impl<const S: usize> Rem for Epz<S> {
     type Output = Self;
     fn rem(self, other: Self) -> Self {
-        let mut k = self.clone();
-        rem_slice(&mut k.limbs.to_vec(), &other.limbs[..]);
-        k
+        // Division by a single limb, rem_slice requires at least two
+        if leading_idx(&other.limbs[..]) == 0 {
+            let d = other.limbs[0] as u128;
+            let mut r = 0u128;
+            for limb in self.limbs.iter().rev() {
+                r = ((r << 64) | *limb as u128) % d;
+            }
+            return Self::from(r as u64);
+        }
+        self.mul_mod(Self::ONE, other)
     }
 }
 
@@ -264,14 +270,18 @@ impl<const S: usize> Epz<S> {
         let leading_prod = leading_idx(&k);
         let leading_ring = leading_idx(&nc.limbs[..]);
         
-        if leading_prod <= leading_ring {
-            if cmp_slice(&k[..leading_prod], &nc.limbs[..leading_ring]) == std::cmp::Ordering::Less
-            {
-                for i in 0..S {
-                    zero.limbs[i] = k[i];
-                }
-                return zero;
-            }
+        // Single limb moduli are reduced by scalar division, the long division requires at least two limbs
+        if leading_ring == 0 {
+            zero.limbs[0] = mod_slice(&mut k[..], nc.limbs[0], 0);
+            return zero;
+        }
+        // The product is already reduced, the leading limbs are included in the comparison
+        if leading_prod < leading_ring
+            || (leading_prod == leading_ring
+                && cmp_slice(&k[..=leading_prod], &nc.limbs[..=leading_ring]) == std::cmp::Ordering::Less)
+        {
+            zero.limbs.copy_from_slice(&k[..S]);
+            return zero;
         }
 
         let shift = leading_digit(&nc.limbs[..]).leading_zeros();
@@ -1,5 +1,6 @@
-use crate::natural::extended::{sliceops::{div_slice, leading_idx, scale_slice},muldiv::quo_rem_slice};
+use crate::natural::extended::{sliceops::{div_slice, leading_idx, scale_slice, shl_slice, sub_slice},muldiv::mul_slice};
 use crate::natural::{factor::Factorization,rand::rand,finite::FiniteArith};
+use crate::natural::lucas::{lucas_eval, LucasMode};
 use crate::{Natural, Pseudoprime};
 
 /// Extended Precision Integer (Zahl)
@@ -100,7 +101,7 @@ impl<const S: usize> Natural for Epz<S> {
     /// Evaluates if integer is between inf and sup exclusive
     fn is_bounded_by(&self, inf: Self, sup: Self) -> bool {
     
-       if *self < inf && *self > sup{
+       if *self > inf && *self < sup{
           return true;
        }
        false
@@ -119,20 +120,53 @@ impl<const S: usize> Natural for Epz<S> {
         ((self.limbs[0] as u32).wrapping_mul(multiplier) as usize) >> shift
     }
 
+    /// Classifies the integer by successively weaker tests, slower than the Montgomery form of the machine types
     fn fast_classify(&self, a: Self) -> Pseudoprime {
-        unimplemented!()
+        // As with the machine types only the fermat test is defined for even integers
+        if self.is_even() {
+            if self.fermat(a) {
+                return Pseudoprime::Strong;
+            }
+            return Pseudoprime::Composite;
+        }
+        if self.sprp(a) {
+            return Pseudoprime::Strong;
+        }
+        if self.euler_plumb(a) {
+            return Pseudoprime::EulerPlumb;
+        }
+        if self.euler_jacobi(a) {
+            return Pseudoprime::EulerJacobi;
+        }
+        if self.euler(a) {
+            return Pseudoprime::Euler;
+        }
+        if self.fermat(a) {
+            return Pseudoprime::Fermat;
+        }
+        Pseudoprime::Composite
     }
-    /// Classifies the integer into the result of
+    /// Classifies the integer into the result of fast_classify, or Prime
     fn classify(&self, a: Self) -> Pseudoprime {
-        unimplemented!()
+        if *self == Self::from(2u64) {
+            return Pseudoprime::Prime;
+        }
+        let set = self.fast_classify(a);
+        if set == Pseudoprime::Strong && self.is_prime() {
+            return Pseudoprime::Prime;
+        }
+        set
     }
 
     fn is_spk(&self, p: u64, q: u64) -> bool {
-        unimplemented!()
+        match self.spk_components(p, q) {
+            Some((lhs, rhs)) => lhs.is_prime() && rhs.is_prime(),
+            None => false,
+        }
     }
-    
+
     fn is_spkh(&self, p: u64, q: u64) -> bool {
-        unimplemented!()
+        self.spk_components(p, q).is_some()
     }
 
     fn semi_k_complement(&self, k: usize) -> Self {
@@ -145,18 +179,42 @@ impl<const S: usize> Natural for Epz<S> {
     }
 
     fn overflowing_mul(&self, otra: Self) -> (Self, bool) {
-        unimplemented!()
+        let mut prod = vec![0u64; 2 * S];
+        mul_slice(&self.limbs[..], &otra.limbs[..], &mut prod[..]);
+        let mut res = Self::ZERO;
+        res.limbs.copy_from_slice(&prod[..S]);
+        (res, prod[S..].iter().any(|x| *x != 0))
     }
 
-    /// Evaluates if integer is coprime to all primes under the provided bound (exclusive)
+    /// Evaluates if integer is coprime to the first s primes, or is one of them
     fn trial_bound(&self, s: usize) -> bool {
-        unimplemented!()
+        if self.is_even() {
+            return false;
+        }
+        let mut p = 1u64;
+        for _ in 1..s {
+            p = (p + 2..).step_by(2).find(|x| x.is_prime()).unwrap();
+            if *self % Self::from(p) == Self::ZERO {
+                return *self == Self::from(p);
+            }
+        }
+        true
     }
 
+    /// Binary long division, the remainder is shifted in a bit at a time
     fn euclidean(&self, otra: Self) -> (Self, Self) {
        let mut quo = Self::ZERO;
-       let mut rem = self.clone();
-       quo_rem_slice(&mut rem.limbs.to_vec(), &otra.limbs[..], &mut quo.limbs[..]);
+       let mut rem = Self::ZERO;
+       for i in (0..self.msb()).rev() {
+          let bit = (self.limbs[i / 64] >> (i % 64)) & 1;
+          // The bit shifted out of the remainder means it exceeds the divisor
+          let carry = shl_slice(&mut rem.limbs[..], 1, bit);
+          shl_slice(&mut quo.limbs[..], 1, 0);
+          if carry == 1 || rem >= otra {
+             sub_slice(&mut rem.limbs[..], &otra.limbs[..], 0);
+             quo.limbs[0] |= 1;
+          }
+       }
        (quo,rem)
     }
 
@@ -367,7 +425,7 @@ impl<const S: usize> Natural for Epz<S> {
     // fn semi_sprp(&self, p: Self, q: Self) -> bool;
 
     fn sqr_fermat(&self, p: Self) -> bool {
-        self.exp_unit(*self-Self::ONE,*self* *self)
+        self.exp_unit(p-Self::ONE,p*p)
     }
 
     /// Jacobi symbol
@@ -389,7 +447,7 @@ impl<const S: usize> Natural for Epz<S> {
 
             std::mem::swap(&mut n, &mut p);
 
-            if n.limbs[0]&3==3 && presidue==3 {
+            if n.limbs[0]&3==3 && p.limbs[0]&3==3 {
                 t = -t;
             }
 
@@ -407,21 +465,66 @@ impl<const S: usize> Natural for Epz<S> {
     fn fermat(&self, a: Self) -> bool {
         a.exp_residue(*self-Self::ONE,*self)==Self::ONE
     }
-    /// a^p-1 mod p = jacobi(a,p)
+    /// a^(p-1)/2 mod p = ±1
+    fn euler(&self, a: Self) -> bool {
+        if self.is_even(){
+           return self.fermat(a);
+        }
+        let pminus = *self-Self::ONE;
+        let res = a.exp_residue(pminus>>1,*self);
+        res == Self::ONE || res == pminus
+    }
+
+    /// a^(p-1)/2 mod p = jacobi(a,p)
     fn euler_jacobi(&self, a: Self) -> bool {
-        //unimplemented!()
-        let e = self.jacobi(a);
-        let res = self.exp_residue(*self-Self::ONE,*self);
+        let e = a.jacobi(*self);
+        let res = a.exp_residue((*self-Self::ONE)>>1,*self);
         
         if e == -1{
            return res == *self-Self::ONE;
         }
-        res==Self::ONE
+        e == 1 && res==Self::ONE
     }
 
     /// Colin Plumb's variant of Fermat test
     fn euler_p(&self) -> bool {
-        unimplemented!()
+        self.euler_plumb(Self::from(2u64))
+    }
+
+    /// Colin Plumb's variant of the Euler-Jacobi test to any base
+    fn euler_plumb(&self, a: Self) -> bool {
+        if self.is_even(){
+           return self.fermat(a);
+        }
+        let pminus = *self-Self::ONE;
+        let e = a.jacobi(*self);
+        // p = 1 mod 4
+        if e == 1 && pminus.trailing_zeros() > 1{
+           let res = a.exp_residue(pminus>>2,*self);
+           return res == Self::ONE || res == pminus;
+        }
+        let res = a.exp_residue(pminus>>1,*self);
+        match e{
+          1 => res == Self::ONE,
+          -1 => res == pminus,
+          _=> false,
+        }
+    }
+
+    fn lucas(&self) -> bool {
+        lucas_eval(*self, LucasMode::Standard, |p, q| self.lucas_prp(p, q, LucasMode::Standard))
+    }
+
+    fn strong_lucas(&self) -> bool {
+        lucas_eval(*self, LucasMode::Strong, |p, q| self.lucas_prp(p, q, LucasMode::Strong))
+    }
+
+    fn extra_strong_lucas(&self) -> bool {
+        lucas_eval(*self, LucasMode::ExtraStrong, |p, q| self.lucas_prp(p, q, LucasMode::ExtraStrong))
+    }
+
+    fn frobenius(&self) -> bool {
+        lucas_eval(*self, LucasMode::Frobenius, |p, q| self.lucas_prp(p, q, LucasMode::Frobenius))
     }
 
     /// Strong Fermat
@@ -454,6 +557,11 @@ impl<const S: usize> Natural for Epz<S> {
 
     /// Probable prime
     fn is_prime(&self) -> bool {
+        // Integers of at most 128 bits are proven by the deterministic test
+        if self.limbs.iter().skip(2).all(|x| *x == 0) {
+            let hi = if S > 1 { self.limbs[1] as u128 } else { 0 };
+            return (hi << 64 | self.limbs[0] as u128).is_prime();
+        }
         if self.is_even(){
            return false;
         }
@@ -481,22 +589,16 @@ impl<const S: usize> Natural for Epz<S> {
     
     /// Integer sqrt
     fn isqrt(&self) -> Self {
-    
-       println!("{}",self);
-       
-       let mut est = self.clone()>>((self.msb() as u32 / 2) - 1);
-        println!("{:?} {}",est.limbs,self.msb());
-        loop {
-            let s = est;
-            println!("s:{} {:?}",s,(*self/s).limbs);
-            let t =s+(*self/s); 
-            est = t>>1;
-            println!("{} {}",t,est);
-            if est >= s{
-               return s;
-            }
-            
-            }
+       // Constructed from the most significant bit, the root has at most half the bits so squares do not overflow
+       let mut root = Self::ZERO;
+       for i in (0..self.msb().div_ceil(2)).rev() {
+          let mut est = root;
+          est.limbs[i / 64] |= 1 << (i % 64);
+          if est * est <= *self {
+             root = est;
+          }
+       }
+       root
     }
 
     /// Integer nth root
@@ -509,7 +611,8 @@ impl<const S: usize> Natural for Epz<S> {
     }
 
     fn is_square(&self) -> bool {
-        unimplemented!()
+        let root = self.isqrt();
+        root * root == *self
     }
 
     fn factor(&self) -> Option<Factorization<Self>> {
@@ -539,6 +642,123 @@ impl<const S: usize> Natural for Epz<S> {
     }
 }
 
+impl<const S: usize> Epz<S> {
+    // The factors (pk+1)(qk+1) if the integer is of that form
+    fn spk_components(&self, p: u64, q: u64) -> Option<(Self, Self)> {
+        let k = self.euclidean(Self::from(p * q)).0.isqrt();
+        let lhs = Self::from(p) * k + Self::ONE;
+        let rhs = Self::from(q) * k + Self::ONE;
+        if lhs * rhs == *self {
+            return Some((lhs, rhs));
+        }
+        None
+    }
+
+    // Lucas probable prime test with parameters P and Q, the counterpart of NTCore::lucas_prp in residue form
+    // n must be odd and coprime to P^2-4Q
+    pub(crate) fn lucas_prp(&self, p: i64, q: i64, mode: LucasMode) -> bool {
+        let n = *self;
+        let nplus = n + Self::ONE;
+        // n+1 is not representable, 2^(64S)-1 is composite
+        if nplus == Self::ZERO {
+            return false;
+        }
+        let zero = Self::ZERO;
+        let two = Self::from(2u64);
+
+        // Sums and differences of residues, without overflowing
+        let add = |x: Self, y: Self| -> Self {
+            let comp = n - y;
+            if x >= comp {
+                return x - comp;
+            }
+            x + y
+        };
+        let sub = |x: Self, y: Self| -> Self {
+            if x >= y {
+                return x - y;
+            }
+            x + (n - y)
+        };
+        // x/2 mod n, (x+n)/2 = x/2 + n/2 + 1 for x and n odd
+        let half = |x: Self| -> Self {
+            if x.is_even() {
+                return x >> 1;
+            }
+            (x >> 1) + (n >> 1) + Self::ONE
+        };
+        let signed = |x: i64| -> Self {
+            let m = Self::from(x.unsigned_abs()).euclidean(n).1;
+            if x < 0 {
+                return sub(zero, m);
+            }
+            m
+        };
+        let mp = signed(p);
+        let mq = signed(q);
+        let md = signed(p * p - 4 * q);
+
+        let s = nplus.trailing_zeros();
+        let d = match mode {
+            LucasMode::Standard | LucasMode::Frobenius => nplus,
+            _ => nplus >> s,
+        };
+        // U_d, V_d and Q^d from the most significant bit
+        let mut u = Self::ONE;
+        let mut v = mp;
+        let mut qk = mq;
+
+        for i in (0..(d.msb() - 1)).rev() {
+            u = u.mul_mod(v, n);
+            v = sub(v.mul_mod(v, n), add(qk, qk));
+            qk = qk.mul_mod(qk, n);
+
+            if (d.limbs[i / 64] >> (i % 64)) & 1 == 1 {
+                let pu = mp.mul_mod(u, n);
+                let du = md.mul_mod(u, n);
+                let pv = mp.mul_mod(v, n);
+                u = half(add(pu, v));
+                v = half(add(du, pv));
+                qk = qk.mul_mod(mq, n);
+            }
+        }
+
+        match mode {
+            LucasMode::Standard => u == zero,
+            LucasMode::Frobenius => u == zero && v == add(mq, mq),
+            LucasMode::Strong => {
+                if u == zero || v == zero {
+                    return true;
+                }
+                for _ in 1..s {
+                    v = sub(v.mul_mod(v, n), add(qk, qk));
+                    if v == zero {
+                        return true;
+                    }
+                    qk = qk.mul_mod(qk, n);
+                }
+                false
+            }
+            // Q = 1 so V_2k = V_k^2 - 2
+            LucasMode::ExtraStrong => {
+                if u == zero && (v == two || v == sub(zero, two)) {
+                    return true;
+                }
+                if v == zero {
+                    return true;
+                }
+                for _ in 1..s.saturating_sub(1) {
+                    v = sub(v.mul_mod(v, n), two);
+                    if v == zero {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
 /*
   Algorithm
 
@@ -643,3 +863,67 @@ impl<const S: usize> std::fmt::Display for Epz<S> {
         write!(f, "{}", last)
     }
 }
+
+#[test]
+fn classify_epz() {
+    // Multiplication modulo integers of more than one limb
+    let n = 36895018421479871953u128;
+    assert_eq!(Epz::<2>::from(n).classify(Epz::<2>::from(3u64)), Pseudoprime::Fermat);
+
+    for n in (1u128 << 65..(1u128 << 65) + 4000).step_by(2).chain((3u128..4000).step_by(2)) {
+        for a in [2u64, 3, 5, 7] {
+            assert_eq!(Epz::<2>::from(n).classify(Epz::<2>::from(a)), n.classify(a as u128), "{} {}", n, a);
+        }
+    }
+    assert!(Pseudoprime::EulerJacobi < Pseudoprime::EulerPlumb && Pseudoprime::EulerPlumb < Pseudoprime::Strong);
+}
+
+#[test]
+fn lucas_epz() {
+    let ranges = [(3u128, 20000u128), (1 << 64, (1 << 64) + 4000), ((1 << 126) + 1, (1 << 126) + 400)];
+    for (inf, sup) in ranges {
+        for n in inf..sup {
+            let x = Epz::<2>::from(n);
+            let (quo, rem) = x.euclidean(Epz::<2>::from(4u64));
+            assert_eq!((quo, rem), (Epz::<2>::from(n / 4), Epz::<2>::from(n % 4)), "{}", n);
+            assert_eq!(x.isqrt(), Epz::<2>::from(n.isqrt()), "{}", n);
+            assert_eq!(x.is_square(), n.is_square(), "{}", n);
+            assert_eq!(x.lucas(), n.lucas(), "{}", n);
+            assert_eq!(x.strong_lucas(), n.strong_lucas(), "{}", n);
+            assert_eq!(x.extra_strong_lucas(), n.extra_strong_lucas(), "{}", n);
+            assert_eq!(x.frobenius(), n.frobenius(), "{}", n);
+        }
+    }
+    // Strong Lucas pseudoprimes
+    for n in [5459u64, 5777, 10877, 16109, 18971] {
+        assert!(Epz::<2>::from(n).strong_lucas() && !Epz::<2>::from(n).is_prime());
+    }
+    // Squares beyond 128 bits
+    let root = Epz::<4>::from(u128::MAX - 158);
+    assert_eq!((root * root).isqrt(), root);
+    assert!(!(root * root).lucas() && !(root * root).extra_strong_lucas());
+    // 2^127-1 and 2^107-1 are prime
+    assert!(Epz::<3>::from(u128::MAX >> 1).strong_lucas() && Epz::<3>::from(u128::MAX >> 21).extra_strong_lucas());
+    assert!(Epz::<3>::from(u128::MAX >> 1).frobenius() && !Epz::<3>::from(u128::MAX >> 2).frobenius());
+}
+
+#[test]
+fn spk_epz() {
+    use crate::filter::FilterExpr;
+
+    for n in (1u128 << 64..(1u128 << 64) + 2000).chain(3..2000) {
+        let x = Epz::<2>::from(n);
+        for (p, q) in [(1, 2), (2, 4), (1, 3)] {
+            assert_eq!(x.is_spk(p, q), n.is_spk(p, q), "{}", n);
+            assert_eq!(x.is_spkh(p, q), n.is_spkh(p, q), "{}", n);
+        }
+        for s in [1, 2, 10, 128] {
+            assert_eq!(x.trial_bound(s), n.trial_bound(s), "{} {}", n, s);
+        }
+    }
+    // (2k+1)(4k+1) for k = 2^40+57
+    let k = (1u128 << 40) + 57;
+    let expr = FilterExpr::Spk(2, 4) & FilterExpr::Trial(128);
+    assert_eq!(expr.check(Epz::<2>::from((2 * k + 1) * (4 * k + 1))), ((2 * k + 1) * (4 * k + 1)).is_spk(2, 4));
+    assert!(!FilterExpr::Trial(0).check(Epz::<2>::from(101u64)) && !FilterExpr::Trial(129).check(101u128));
+}
@@ -142,12 +142,29 @@ pub trait Natural:
     fn classify(&self, a: Self) -> Pseudoprime;
     /// a^p-1 mod p = 1
     fn fermat(&self, a: Self) -> bool;
+    /// a^(p-1)/2 mod p = ±1
+    fn euler(&self, a: Self) -> bool;
     /// a^p-1 mod p = jacobi(a,p)
     fn euler_jacobi(&self, a: Self) -> bool;
     /// Colin Plumb's variant of Fermat test
     fn euler_p(&self) -> bool;
+    /// Colin Plumb's variant of the Euler-Jacobi test to any base. a^(p-1)/4 mod p = ±1 if p = 1 mod 4 and
+    /// jacobi(a,p) = 1, otherwise a^(p-1)/2 mod p = jacobi(a,p)
+    ///
+    /// Strictly stronger than Euler-Jacobi and weaker than Strong Fermat
+    fn euler_plumb(&self, a: Self) -> bool;
     /// Strong Fermat
     fn sprp(&self, a: Self) -> bool;
+    /// Lucas probable prime test with Selfridge's parameters, U_(n+1) = 0 mod n
+    fn lucas(&self) -> bool;
+    /// Strong Lucas probable prime test with Selfridge's parameters, the Lucas component of BPSW
+    fn strong_lucas(&self) -> bool;
+    /// Extra strong Lucas probable prime test with Baillie's parameters Q = 1 and the least P >= 3 such that
+    /// jacobi(P^2-4,n) = -1
+    fn extra_strong_lucas(&self) -> bool;
+    /// Quadratic Frobenius probable prime test (Grantham) with respect to x^2-Px+Q with Selfridge's parameters,
+    /// U_(n+1) = 0 and V_(n+1) = 2Q mod n
+    fn frobenius(&self) -> bool;
     // Performs a strong fermat test to a certain power, Analog to exp_unit
     // N must be \in 2Z+1
     fn special_sf(&self, p: Self,n: Self) -> bool;
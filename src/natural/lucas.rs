@@ -0,0 +1,99 @@
+use crate::natural::montcore::NTCore;
+use crate::Natural;
+
+/*
+   Lucas probable prime tests
+
+   Parameters are selected generically, the Lucas sequences are evaluated in Montgomery form by NTCore
+   or in residue form by Epz
+*/
+
+/// Variant of the Lucas probable prime test
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum LucasMode {
+    /// U_(n+1) = 0
+    Standard,
+    /// U_d = 0 or V_(d*2^r) = 0 for some 0 <= r < s, where n+1 = d*2^s
+    Strong,
+    /// U_d = 0 and V_d = ±2, or V_(d*2^r) = 0 for some 0 <= r < s-1
+    ExtraStrong,
+    /// U_(n+1) = 0 and V_(n+1) = 2Q, the quadratic Frobenius test with respect to x^2-Px+Q
+    Frobenius,
+}
+
+// Jacobi symbol of a signed integer
+fn signed_jacobi<T: Natural>(d: i64, n: T) -> i8 {
+    let j = T::from(d.unsigned_abs()).jacobi(n);
+    // jacobi(-1,n) = -1 for n = 3 mod 4
+    if d < 0 && n.euclidean(T::from(4)).1 == T::from(3) {
+        return -j;
+    }
+    j
+}
+
+// Perfect squares have no parameter with jacobi symbol -1 so they are checked after a few candidates
+const SQUARE_CHECK: usize = 8;
+
+/// Selfridge's method A. The first D in 5,-7,9,-11.. such that jacobi(D,n) = -1, returning P = 1 and Q = (1-D)/4
+///
+/// None if n is a perfect square or has a factor in common with some D, and so is composite
+pub(crate) fn selfridge<T: Natural>(n: T) -> Option<(i64, i64)> {
+    let mut d = 5i64;
+    let mut count = 0usize;
+    loop {
+        match signed_jacobi(d, n) {
+            -1 => return Some((1, (1 - d) / 4)),
+            0 if T::from(d.unsigned_abs()) != n => return None,
+            _ => (),
+        }
+        count += 1;
+        if count == SQUARE_CHECK && n.is_square() {
+            return None;
+        }
+        d = if d < 0 { 2 - d } else { -2 - d };
+    }
+}
+
+/// Baillie's parameters for the extra strong test. The first P in 3,4,5.. such that jacobi(P^2-4,n) = -1, Q = 1
+///
+/// None if n is a perfect square or has a factor in common with some P^2-4, and so is composite
+pub(crate) fn baillie<T: Natural>(n: T) -> Option<i64> {
+    let mut p = 3i64;
+    loop {
+        match signed_jacobi(p * p - 4, n) {
+            -1 => return Some(p),
+            // P^2-4 = (P-2)(P+2), the prime P+2 is skipped
+            0 if T::from((p + 2) as u64) != n => return None,
+            _ => (),
+        }
+        if p as usize - 2 == SQUARE_CHECK && n.is_square() {
+            return None;
+        }
+        p += 1;
+    }
+}
+
+/// Lucas probable prime test of the variant, 2 is the only even probable prime
+pub(crate) fn lucas_test<T: Natural + NTCore>(n: T, mode: LucasMode) -> bool {
+    lucas_eval(n, mode, |p, q| n.lucas_prp(p, q, mode))
+}
+
+/// Lucas probable prime test of the variant with the sequences evaluated by prp(P,Q), for types without NTCore
+pub(crate) fn lucas_eval<T: Natural>(n: T, mode: LucasMode, prp: impl Fn(i64, i64) -> bool) -> bool {
+    if n.is_even() {
+        return n == T::from(2);
+    }
+    if n == T::ONE {
+        return false;
+    }
+    let param = match mode {
+        LucasMode::ExtraStrong => baillie(n).map(|p| (p, 1)),
+        _ => selfridge(n),
+    };
+    match param {
+        // The Frobenius test also requires Q to be coprime to n
+        Some((_, q)) if mode == LucasMode::Frobenius && n.gcd(T::from(q.unsigned_abs())) != T::ONE => false,
+        Some((p, q)) => prp(p, q),
+        None => false,
+    }
+}
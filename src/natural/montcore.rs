@@ -1,3 +1,5 @@
+use crate::natural::lucas::LucasMode;
+
 pub(crate) trait NTCore : PartialEq + Sized {
     fn mont_sub(&self, y: Self, n: Self) -> Self;
 
@@ -52,13 +54,116 @@ pub(crate) trait NTCore : PartialEq + Sized {
     fn exp_one(&self, p: Self, n: Self) -> bool;
     
     fn special_sf(&self, p: Self, n: Self) -> bool;
+
+    // x/2 mod n, n must be odd
+    fn mont_half(&self, n: Self) -> Self;
+
+    // Lucas probable prime test of the variant with parameters P,Q. n must be odd and jacobi(P^2-4Q,n) = -1
+    fn lucas_prp(&self, p: i64, q: i64, mode: LucasMode) -> bool;
+}
+
+// Lucas tests over the Montgomery arithmetic of NTCore, identical for u64 and u128
+macro_rules! lucas_impl {
+    () => {
+        fn mont_half(&self, n: Self) -> Self {
+            if *self & 1 == 0 {
+                return *self >> 1;
+            }
+            // (x+n)/2 without overflow, both are odd
+            (*self >> 1) + (n >> 1) + 1
+        }
+
+        fn lucas_prp(&self, p: i64, q: i64, mode: LucasMode) -> bool {
+            let n = *self;
+            // n+1 is not representable, 2^BITS-1 is composite
+            if n == Self::MAX {
+                return false;
+            }
+            let one = n.n_identity();
+            let inv = n.inv_2();
+            let zero = 0;
+            let two = n.two_identity(one);
+
+            let signed = |x: i64| -> Self {
+                let m = ((x.unsigned_abs() as Self) % n).to_mont(n);
+                if x < 0 {
+                    return zero.mont_sub(m, n);
+                }
+                m
+            };
+            let mp = signed(p);
+            let mq = signed(q);
+            let md = signed(p * p - 4 * q);
+
+            let nplus = n + 1;
+            let s = nplus.trailing_zeros();
+            let d = match mode {
+                LucasMode::Standard | LucasMode::Frobenius => nplus,
+                _ => nplus >> s,
+            };
+
+            // U_d, V_d and Q^d from the most significant bit
+            let mut u = one;
+            let mut v = mp;
+            let mut qk = mq;
+
+            for i in (0..(Self::BITS - d.leading_zeros() - 1)).rev() {
+                u = u.mont_prod(v, inv, n);
+                v = v.mont_sqr(inv, n).mont_sub(qk.mont_add(qk, n), n);
+                qk = qk.mont_sqr(inv, n);
+
+                if (d >> i) & 1 == 1 {
+                    let pu = mp.mont_prod(u, inv, n);
+                    let du = md.mont_prod(u, inv, n);
+                    let pv = mp.mont_prod(v, inv, n);
+                    u = pu.mont_add(v, n).mont_half(n);
+                    v = du.mont_add(pv, n).mont_half(n);
+                    qk = qk.mont_prod(mq, inv, n);
+                }
+            }
+
+            match mode {
+                LucasMode::Standard => u == zero,
+                LucasMode::Frobenius => u == zero && v == mq.mont_add(mq, n),
+                LucasMode::Strong => {
+                    if u == zero || v == zero {
+                        return true;
+                    }
+                    for _ in 1..s {
+                        v = v.mont_sqr(inv, n).mont_sub(qk.mont_add(qk, n), n);
+                        if v == zero {
+                            return true;
+                        }
+                        qk = qk.mont_sqr(inv, n);
+                    }
+                    false
+                }
+                // Q = 1 so V_2k = V_k^2 - 2
+                LucasMode::ExtraStrong => {
+                    if u == zero && (v == two || v == zero.mont_sub(two, n)) {
+                        return true;
+                    }
+                    if v == zero {
+                        return true;
+                    }
+                    for _ in 1..s.saturating_sub(1) {
+                        v = v.mont_sqr(inv, n).mont_sub(two, n);
+                        if v == zero {
+                            return true;
+                        }
+                    }
+                    false
+                }
+            }
+        }
+    };
 }
 
 impl NTCore for u64 {
     fn mont_add(&self, x: Self, n: Self) -> Self {
-        let res = self.wrapping_add(x);
+        let (res, carry) = self.overflowing_add(x);
 
-        if res > n {
+        if carry || res >= n {
             return res.wrapping_sub(n);
         }
         res
@@ -312,6 +417,7 @@ impl NTCore for u64 {
         
     }
 
+    lucas_impl!();
 }
 
 #[inline(always)]
@@ -322,9 +428,9 @@ const fn split_to_u128(x: u128) -> (u128, u128) {
 
 impl NTCore for u128 {
     fn mont_add(&self, x: Self, n: Self) -> Self {
-        let res = self.wrapping_add(x);
+        let (res, carry) = self.overflowing_add(x);
 
-        if res > n {
+        if carry || res >= n {
             return res.wrapping_sub(n);
         }
         res
@@ -356,7 +462,8 @@ impl NTCore for u128 {
     }
 
     fn to_mont(&self, n: Self) -> Self {
-        machine_prime::to_mont_128(*self, n)
+        // Unlike the 64-bit conversion, to_mont_128 requires the integer to be less than n
+        machine_prime::to_mont_128(*self % n, n)
     }
 
     fn mont_prod(&self, y: Self, inv: Self, n: Self) -> Self {
@@ -570,6 +677,7 @@ impl NTCore for u128 {
         
     }
 
+    lucas_impl!();
 }
 
 #[test]
@@ -41,6 +41,26 @@ pub(crate) fn rand() -> u64 {
     }
 }
 
+/// Seedable xorshift generator for reproducible searches, the increment prevents the fixed point at zero
+pub(crate) struct Xorshift(pub(crate) u64);
+
+impl Xorshift {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = drbg(self.0.wrapping_add(0x9E3779B97F4A7C15));
+        self.0
+    }
+
+    // Uniform in [0;sup), with negligible bias for small sup
+    pub(crate) fn below(&mut self, sup: u64) -> u64 {
+        self.next() % sup
+    }
+
+    // Uniform in [0;1)
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
 pub fn gen_k(k: u64) -> Option<u64> {
     if k > 64 {
         return None;
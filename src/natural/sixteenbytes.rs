@@ -1,8 +1,9 @@
 use crate::natural::factor::{factorize_128, Factorization};
+use crate::natural::lucas::{lucas_test, LucasMode};
 use crate::natural::montcore::NTCore;
 use crate::primes::{SMALL_PRIMES};
 use crate::data::SQRTINV;
-use crate::{Natural, Pseudoprime};
+use crate::{Epz, Natural, Pseudoprime};
 use machine_prime::{is_prime_128,PRIME_TABLE_128};
 
 /*
@@ -193,7 +194,9 @@ impl Natural for u128 {
     fn gcd(&self, other: Self) -> Self {
         let mut a = *self;
         let mut b = other;
-        debug_assert!(a != 0);
+        if a == 0 {
+            return b;
+        }
         if b == 0 {
             return a;
         }
@@ -320,11 +323,66 @@ impl Natural for u128 {
     }
 
     fn fast_classify(&self, a: Self) -> Pseudoprime {
-        unimplemented!()
+        if *self & 1 == 0 {
+            if Natural::fermat(self, a) {
+                return Pseudoprime::Strong;
+            } else {
+                return Pseudoprime::Composite;
+            }
+        }
+        let a = a % *self;
+        let inv = self.inv_2();
+        let pminus = *self - 1;
+        let tzc = pminus.trailing_zeros();
+        let d = pminus >> tzc;
+        let one = self.n_identity();
+        let oneinv = self.one_inverse_n(one);
+        let b = a.to_mont(*self);
+
+        let mut mbase = b.mont_pow(one, d, inv, *self);
+
+        if mbase == one || mbase == oneinv {
+            return Pseudoprime::Strong;
+        }
+
+        for i in 1..tzc {
+            // a^(p-1)/4 when evaluating a^(p-1)/2
+            let quarter = mbase;
+            mbase = mbase.mont_prod(mbase, inv, *self);
+            if mbase == oneinv {
+                return Pseudoprime::Strong;
+            }
+            if i == tzc - 1 {
+                let sym = a.jacobi(*self);
+                if sym == 1 && mbase == one {
+                    // -1 would have been Strong
+                    if quarter == one {
+                        return Pseudoprime::EulerPlumb;
+                    }
+                    return Pseudoprime::EulerJacobi;
+                }
+                if mbase == one {
+                    return Pseudoprime::Euler;
+                }
+            }
+        }
+        let mbase = mbase.mont_prod(mbase, inv, *self);
+
+        if mbase == one {
+            return Pseudoprime::Fermat;
+        }
+        Pseudoprime::Composite
     }
 
     fn classify(&self, a: Self) -> Pseudoprime {
-        unimplemented!()
+        if *self == 2 {
+            return Pseudoprime::Prime;
+        }
+        let set = self.fast_classify(a);
+        if set == Pseudoprime::Strong && self.is_prime() {
+            return Pseudoprime::Prime;
+        }
+        set
     }
 
     fn fermat(&self, a: Self) -> bool {
@@ -340,11 +398,17 @@ impl Natural for u128 {
        NTCore::special_sf(self,p,n)
     }
     
-    fn sqr_fermat(&self, base: Self) -> bool {
-        if *self > 1u128 << 32 {
-            return (*self as u128).p_sq_fermat(base as u128);
+    // The base is reduced as 128-bit Montgomery form requires it to be less than p^2, which is only defined
+    // for p less than 2^64. Greater p are evaluated in 256-bit arithmetic
+    fn sqr_fermat(&self, p: Self) -> bool {
+        if p >= 1u128 << 64 {
+            return Epz::<4>::from(*self).sqr_fermat(Epz::<4>::from(p));
+        }
+        let n = p * p;
+        if p > 1u128 << 32 {
+            (*self % n).p_sq_fermat(p)
         } else {
-            return self.p_sq_fermat(base);
+            ((*self % n) as u64).p_sq_fermat(p as u64)
         }
     }
 
@@ -467,6 +531,14 @@ impl Natural for u128 {
         (fermatprod as f64/phifloat,strongprod as f64/phifloat)
     }
 
+    fn euler(&self, a: Self) -> bool {
+        if *self & 1 == 0 {
+            return NTCore::fermat(self, a);
+        }
+        let res = a.exp_residue((*self - 1) / 2, *self);
+        res == 1 || res == *self - 1
+    }
+
     fn euler_jacobi(&self, a: Self) -> bool {
         let r = a.jacobi(*self);
         if r == -1 {
@@ -475,6 +547,24 @@ impl Natural for u128 {
         a.exp_residue((*self - 1) / 2, *self) == (Self::from(r as u64))
     }
 
+    fn euler_plumb(&self, a: Self) -> bool {
+        if *self & 1 == 0 {
+            return NTCore::fermat(self, a);
+        }
+        let pminus = *self - 1;
+        let r = a.jacobi(*self);
+        if r == 1 && *self & 3 == 1 {
+            let res = a.exp_residue(pminus >> 2, *self);
+            return res == 1 || res == pminus;
+        }
+        let res = a.exp_residue(pminus >> 1, *self);
+        match r {
+            1 => res == 1,
+            -1 => res == pminus,
+            _ => false,
+        }
+    }
+
     fn sprp(&self, a: Self) -> bool {
         if *self & 1 == 0 {
             return NTCore::fermat(self, a);
@@ -486,6 +576,22 @@ impl Natural for u128 {
         machine_prime::is_prime_128(*self)
     }
 
+    fn lucas(&self) -> bool {
+        lucas_test(*self, LucasMode::Standard)
+    }
+
+    fn strong_lucas(&self) -> bool {
+        lucas_test(*self, LucasMode::Strong)
+    }
+
+    fn extra_strong_lucas(&self) -> bool {
+        lucas_test(*self, LucasMode::ExtraStrong)
+    }
+
+    fn frobenius(&self) -> bool {
+        lucas_test(*self, LucasMode::Frobenius)
+    }
+
     fn euler_p(&self) -> bool {
         let residue = *self & 7;
         let mut param = 0;
@@ -713,3 +819,27 @@ impl Natural for u128 {
         Some((totalord, sig))
     }
 }
+
+#[test]
+fn sqr_fermat_128() {
+    // 1093 and 3511 are the Wieferich primes to base 2, the 256-bit evaluation must agree with 128-bit arithmetic
+    assert!(2u128.sqr_fermat(1093) && 2u128.sqr_fermat(3511) && !2u128.sqr_fermat(1097));
+    for p in [(1u128 << 64) - 59, (1u128 << 61) - 1, 4294967311] {
+        for b in [2u128, 3, 5, 1 << 70, u128::MAX] {
+            let wide = Epz::<4>::from(b).sqr_fermat(Epz::<4>::from(p));
+            assert_eq!(b.sqr_fermat(p), wide);
+        }
+    }
+    // p^2 exceeds 2^128
+    let p = (1u128 << 64) + 13;
+    assert!(!2u128.sqr_fermat(p) && 1u128.sqr_fermat(p));
+}
+
+#[test]
+fn gcd_zero() {
+    assert_eq!(0u64.gcd(12), 12);
+    assert_eq!(12u64.gcd(0), 12);
+    assert_eq!(0u128.gcd(1 << 100), 1 << 100);
+    assert_eq!((3u128 << 100).gcd(0), 3 << 100);
+    assert_eq!((6u128 << 70).gcd(9 << 66), 3 << 66);
+}
@@ -2,15 +2,21 @@
 
     Search algorithms applied to vectors, this simply compartmentalises the algorithms used for CompVector
 
-    split into Parallel, Single-thread, running over hashbuckets, and searching for hash multipliers
+    split into Parallel, Single-thread, running over hashbuckets, searching for hash multipliers, evolutionary search and annealing of hashtables
 
 */
 
+pub(crate) mod anneal;
+pub(crate) mod evolution;
 pub(crate) mod hash;
 pub(crate) mod parallel;
 pub(crate) mod sectored;
 pub(crate) mod single;
 
+pub(crate) use anneal::anneal_par;
+pub use anneal::AnnealParam;
+pub(crate) use evolution::evo_base_par;
+pub use evolution::EvoParam;
 pub(crate) use hash::hash_search;
 pub(crate) use parallel::*;
 pub(crate) use sectored::*;
@@ -0,0 +1,373 @@
+use crate::natural::rand::{rand, Xorshift};
+use crate::search::thread_count;
+use crate::{FResult, HashTable, Natural};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/*
+   Simulated annealing of hashtables
+
+   The state is the multiplier and the base of each bucket, the energy is the number of composites that pass the
+   base of their bucket. Moves either replace the base of a failing bucket, evaluating only the composites of that
+   bucket, or flip a bit of the multiplier, which rehashes every composite while keeping the bases of each bucket.
+   Independent restarts run concurrently until the restarts or the time budget are exhausted
+*/
+
+/// Parameters of the local search for hashtables
+#[derive(Clone, Debug)]
+pub struct AnnealParam {
+    dimen: usize,
+    bound: u64,
+    restarts: usize,
+    iterations: usize,
+    temperature: f64,
+    multiplier_rate: f64,
+    time: Option<Duration>,
+    seed: Option<u64>,
+}
+
+impl AnnealParam {
+    /// Table of dimension dimen with bases less than 65536, 2^20 iterations for each of thread count restarts, an
+    /// initial temperature of 2 and multiplier moves for 1 in 1000 iterations. There is no time budget
+    ///
+    /// The dimension must be a power of two from 2 to 2^32, otherwise the search returns NotSupported
+    pub fn new(dimen: usize) -> Self {
+        Self {
+            dimen,
+            bound: 65535,
+            restarts: thread_count(),
+            iterations: 1 << 20,
+            temperature: 2.0,
+            multiplier_rate: 0.001,
+            time: None,
+            seed: None,
+        }
+    }
+
+    /// Bases are selected from [2;bound]
+    pub fn set_bound(&mut self, bound: u64) {
+        self.bound = bound.max(2);
+    }
+
+    pub fn set_restarts(&mut self, restarts: usize) {
+        self.restarts = restarts.max(1);
+    }
+
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations;
+    }
+
+    /// Initial temperature, decaying geometrically to a thousandth of it over the iterations
+    pub fn set_temperature(&mut self, temperature: f64) {
+        self.temperature = temperature.max(0.0);
+    }
+
+    /// Probability that a move changes the multiplier rather than a base
+    pub fn set_multiplier_rate(&mut self, rate: f64) {
+        self.multiplier_rate = rate.clamp(0.0, 1.0);
+    }
+
+    /// Stops all restarts once the duration has elapsed, returning the best table found
+    pub fn set_time(&mut self, time: Duration) {
+        self.time = Some(time);
+    }
+
+    /// Fixes the seed, restart i is seeded by seed+i. Without a time budget the result is then identical for any number
+    /// of threads, runs with a time budget may differ
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+}
+
+// Number of random bases sampled when a bucket is initialised
+const SAMPLE: usize = 16;
+
+struct State<T: Natural> {
+    shift: usize,
+    multiplier: u32,
+    buckets: Vec<Vec<T>>,
+    table: Vec<u64>,
+    fails: Vec<u64>,
+    total: u64,
+}
+
+impl<T: Natural> State<T> {
+    fn new(pseudos: &[T], dimen: usize, multiplier: u32, table: Vec<u64>) -> Self {
+        let mut res = Self {
+            shift: (32 - dimen.trailing_zeros()) as usize,
+            multiplier,
+            buckets: vec![],
+            table,
+            fails: vec![],
+            total: 0,
+        };
+        res.rehash(pseudos);
+        res
+    }
+
+    fn rehash(&mut self, pseudos: &[T]) {
+        let mut buckets = vec![vec![]; self.table.len()];
+        for i in pseudos {
+            buckets[i.hash_shift(self.shift, self.multiplier)].push(*i);
+        }
+        self.buckets = buckets;
+        self.fails = (0..self.table.len()).map(|idx| self.failures(idx, self.table[idx])).collect();
+        self.total = self.fails.iter().sum();
+    }
+
+    fn failures(&self, idx: usize, base: u64) -> u64 {
+        if !self.valid(idx, base) {
+            // Invalid bases pass every composite of the bucket, and are replaced as the worst buckets
+            return self.buckets[idx].len() as u64 + 1;
+        }
+        self.buckets[idx].iter().filter(|x| x.sprp(T::from(base))).count() as u64
+    }
+
+    // Bases must be coprime to the primes hashed to their bucket, otherwise those primes would fail
+    fn valid(&self, idx: usize, base: u64) -> bool {
+        if base < 2 {
+            return false;
+        }
+        let mut b = base;
+        let mut p = 2u64;
+        while p * p <= b {
+            if b.is_multiple_of(p) {
+                if p.hash_shift(self.shift, self.multiplier) == idx {
+                    return false;
+                }
+                while b.is_multiple_of(p) {
+                    b /= p;
+                }
+            }
+            p += 1;
+        }
+        b < 2 || b.hash_shift(self.shift, self.multiplier) != idx
+    }
+
+    // Best of several random bases for the bucket
+    fn sample(&self, idx: usize, rng: &mut Xorshift, bound: u64) -> (u64, u64) {
+        let mut best = (self.table[idx], self.fails[idx]);
+        for _ in 0..SAMPLE {
+            if best.1 == 0 {
+                break;
+            }
+            let b = 2 + rng.below(bound - 1);
+            let f = self.failures(idx, b);
+            if f < best.1 {
+                best = (b, f);
+            }
+        }
+        best
+    }
+
+    fn set(&mut self, idx: usize, base: u64, fails: u64) {
+        self.total = self.total - self.fails[idx] + fails;
+        self.table[idx] = base;
+        self.fails[idx] = fails;
+    }
+
+    fn to_hashtable(&self) -> HashTable {
+        HashTable::new(self.table.clone(), self.table.len(), self.multiplier)
+    }
+}
+
+// Metropolis criterion, improvements are always accepted
+fn accept(delta: i64, temperature: f64, rng: &mut Xorshift) -> bool {
+    delta <= 0 || (temperature > 0.0 && rng.unit() < (-(delta as f64) / temperature).exp())
+}
+
+fn anneal_st<T: Natural>(
+    pseudos: &[T],
+    param: &AnnealParam,
+    initial: Option<&HashTable>,
+    rng: &mut Xorshift,
+    stop: &dyn Fn() -> bool,
+) -> (HashTable, u64) {
+    let mut state = match initial {
+        Some(ht) => {
+            let (dimen, multiplier, table) = ht.values();
+            State::new(pseudos, dimen, multiplier, table)
+        }
+        None => State::new(pseudos, param.dimen, rng.next() as u32, vec![0; param.dimen]),
+    };
+    let dimen = state.table.len();
+    if initial.is_none() {
+        for idx in 0..dimen {
+            let (b, f) = state.sample(idx, rng, param.bound);
+            state.set(idx, b, f);
+        }
+    }
+
+    let mut best = (state.to_hashtable(), state.total);
+    let cooling = 0.001f64.powf(1.0 / param.iterations.max(1) as f64);
+    let mut temperature = param.temperature;
+
+    for iteration in 0..param.iterations {
+        if best.1 == 0 || (iteration & 1023 == 0 && stop()) {
+            break;
+        }
+        temperature *= cooling;
+
+        if rng.unit() < param.multiplier_rate {
+            // The previous hashing is restored rather than recomputed if the move is rejected
+            let multiplier = state.multiplier;
+            let buckets = std::mem::take(&mut state.buckets);
+            let fails = std::mem::take(&mut state.fails);
+            let total = state.total;
+
+            state.multiplier ^= 1 << rng.below(32);
+            state.rehash(pseudos);
+            if !accept(state.total as i64 - total as i64, temperature, rng) {
+                state.multiplier = multiplier;
+                state.buckets = buckets;
+                state.fails = fails;
+                state.total = total;
+            }
+        } else {
+            // Select a failing bucket, the first failing bucket after a random index
+            let start = rng.below(dimen as u64) as usize;
+            let idx = match (0..dimen).map(|i| (start + i) % dimen).find(|i| state.fails[*i] > 0) {
+                Some(x) => x,
+                None => break,
+            };
+            let b = 2 + rng.below(param.bound - 1);
+            let f = state.failures(idx, b);
+            if accept(f as i64 - state.fails[idx] as i64, temperature, rng) {
+                state.set(idx, b, f);
+            }
+        }
+
+        if state.total < best.1 {
+            best = (state.to_hashtable(), state.total);
+        }
+    }
+    best
+}
+
+/*
+   In: A vector of composites, search parameters and optionally a table to start from
+   Out: The table with the fewest composites passing it, and the number that pass
+
+   Restarts are distributed over the threads. Once a restart finds a table passing no composite, only restarts of a
+   lesser index continue. Ties are broken by the least restart index, so the result does not depend on the order in
+   which the threads complete
+*/
+pub(crate) fn anneal_par<T: Natural>(
+    pseudos: Vec<T>,
+    param: &AnnealParam,
+    initial: Option<HashTable>,
+) -> FResult<(HashTable, u64)> {
+    anneal_threads(pseudos, param, initial, thread_count())
+}
+
+fn anneal_threads<T: Natural>(
+    pseudos: Vec<T>,
+    param: &AnnealParam,
+    initial: Option<HashTable>,
+    tc: usize,
+) -> FResult<(HashTable, u64)> {
+    // Only single-level tables of a single base per bucket are annealed
+    let dimen = match &initial {
+        Some(ht) if ht.stride() != 1 || ht.levels() != 1 => return FResult::NotSupported,
+        Some(ht) => ht.values().0,
+        None => param.dimen,
+    };
+    // A single bucket would shift the hash by the full 32 bits
+    if dimen < 2 || !dimen.is_power_of_two() || dimen > (1 << 32) {
+        return FResult::NotSupported;
+    }
+
+    let seed = param.seed.unwrap_or_else(rand);
+    let start = Instant::now();
+    let pseudos = Arc::new(pseudos);
+    let initial = Arc::new(initial);
+    let restart = Arc::new(AtomicUsize::new(0));
+    // Least restart that found a table passing no composite
+    let zero = Arc::new(AtomicUsize::new(usize::MAX));
+    let best: Arc<Mutex<Option<(HashTable, u64, usize)>>> = Arc::new(Mutex::new(None));
+
+    let mut threads = vec![];
+    for _ in 0..tc.min(param.restarts) {
+        let p_i = pseudos.clone();
+        let init_i = initial.clone();
+        let r_i = restart.clone();
+        let z_i = zero.clone();
+        let b_i = best.clone();
+        let param_i = param.clone();
+
+        threads.push(std::thread::spawn(move || {
+            let elapsed = || param_i.time.is_some_and(|t| start.elapsed() >= t);
+            loop {
+                let r = r_i.fetch_add(1, Ordering::SeqCst);
+                // At least one restart is evaluated regardless of the time budget
+                if r >= param_i.restarts || r > z_i.load(Ordering::SeqCst) || (r > 0 && elapsed()) {
+                    break;
+                }
+                // Restarts after one passing no composite cannot be selected, so may stop at any point
+                let stop = || r > z_i.load(Ordering::SeqCst) || elapsed();
+                let mut rng = Xorshift(seed.wrapping_add(r as u64));
+                let (ht, count) = anneal_st(&p_i[..], &param_i, (*init_i).as_ref(), &mut rng, &stop);
+
+                if count == 0 {
+                    z_i.fetch_min(r, Ordering::SeqCst);
+                }
+                let mut b = b_i.lock().unwrap();
+                if b.as_ref().is_none_or(|x| (count, r) < (x.1, x.2)) {
+                    *b = Some((ht, count, r));
+                }
+            }
+        }));
+    }
+
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let res = best.lock().unwrap().take();
+    match res {
+        Some((ht, count, _)) => FResult::Value((ht, count)),
+        None => FResult::Failure,
+    }
+}
+
+#[test]
+fn anneal_dimension() {
+    let pseudos = vec![2047u64, 3277, 4033, 4681, 8321, 15841, 29341, 42799, 49141, 52633];
+    for dimen in [0, 1, 3] {
+        assert!(matches!(anneal_par(pseudos.clone(), &AnnealParam::new(dimen), None), FResult::NotSupported));
+    }
+    let single = HashTable::new(vec![2], 1, 1);
+    assert!(matches!(anneal_par(pseudos.clone(), &AnnealParam::new(2), Some(single)), FResult::NotSupported));
+    let mut param = AnnealParam::new(2);
+    param.set_iterations(1 << 12);
+    param.set_seed(1);
+    match anneal_par(pseudos.clone(), &param, None) {
+        FResult::Value((ht, count)) => {
+            assert_eq!(pseudos.iter().filter(|x| ht.primality(**x)).count() as u64, count)
+        }
+        _ => panic!("Annealing a table of two buckets failed"),
+    }
+}
+
+#[test]
+fn anneal_reproducible() {
+    let pseudos = (3..1u64 << 20).step_by(2).filter(|x| !x.is_prime() && x.sprp(2)).collect::<Vec<u64>>();
+    // Restarts tied by base 2 alone, and restarts that stop once one reaches zero
+    for (bound, zero) in [(2, false), (65535, true)] {
+        let mut param = AnnealParam::new(16);
+        param.set_bound(bound);
+        param.set_iterations(1 << 11);
+        param.set_restarts(6);
+        param.set_seed(7);
+        let run = |tc: usize| match anneal_threads(pseudos.clone(), &param, None, tc) {
+            FResult::Value((ht, count)) => (ht.values(), count),
+            _ => panic!("Annealing failed"),
+        };
+        let single = run(1);
+        assert_eq!(single.1 == 0, zero);
+        for tc in [1, 2, 3, 6] {
+            assert_eq!(run(tc), single, "{} threads", tc);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::natural::rand::{rand, Xorshift};
+use crate::search::thread_count;
+use crate::structures::{BaseSeq, CounterExamples};
+use crate::Natural;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/*
+   Evolutionary search for sets of strong fermat bases
+
+   The fitness of a set is the number of composites that pass every base, the intersection of the counterexample
+   bitvectors of each base. Bitvectors are computed in parallel for the bases new to each generation and cached for
+   the bases that remain in the population
+*/
+
+/// Parameters of the evolutionary search for k bases
+#[derive(Clone, Debug)]
+pub struct EvoParam {
+    k: usize,
+    population: usize,
+    generations: usize,
+    mutation: f64,
+    inf: u64,
+    sup: u64,
+    seed: Option<u64>,
+}
+
+impl EvoParam {
+    /// Searches for k bases, with a population of 64 over 200 generations, a mutation probability of 0.1 per base
+    /// and bases in the interval [2;65536)
+    pub fn new(k: usize) -> Self {
+        Self {
+            k: k.max(1),
+            population: 64,
+            generations: 200,
+            mutation: 0.1,
+            inf: 2,
+            sup: 65536,
+            seed: None,
+        }
+    }
+
+    pub fn set_population(&mut self, population: usize) {
+        self.population = population.max(4);
+    }
+
+    pub fn set_generations(&mut self, generations: usize) {
+        self.generations = generations;
+    }
+
+    /// Probability that each base of a child is replaced by a random base
+    pub fn set_mutation(&mut self, mutation: f64) {
+        self.mutation = mutation.clamp(0.0, 1.0);
+    }
+
+    /// Bases are selected from the interval [inf;sup)
+    pub fn set_interval(&mut self, inf: u64, sup: u64) {
+        let (min, max) = inf.min_max(sup);
+        self.inf = min.max(2);
+        self.sup = max.max(self.inf + 1);
+    }
+
+    /// Fixes the seed so that the search is reproducible, otherwise it is seeded by the system
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+}
+
+// Random base within the interval that is not already in the set
+fn fresh_base<T: Natural>(rng: &mut Xorshift, param: &EvoParam, set: &[T]) -> T {
+    let width = param.sup - param.inf;
+    loop {
+        let b = T::from(param.inf + rng.below(width));
+        // Intervals narrower than the set necessarily repeat bases
+        if !set.contains(&b) || (width as usize) <= set.len() {
+            return b;
+        }
+    }
+}
+
+// Counterexample bitvectors of each base, evaluated in parallel over the bases
+fn counterexamples_par<T: Natural>(pseudos: Arc<Vec<T>>, bases: Vec<T>) -> Vec<(T, CounterExamples)> {
+    let tc = thread_count().min(bases.len()).max(1);
+    let stride = bases.len().div_ceil(tc);
+    let mut threads = vec![];
+
+    for chunk in bases.chunks(stride.max(1)) {
+        let p_i = pseudos.clone();
+        let b_i = chunk.to_vec();
+        threads.push(std::thread::spawn(move || {
+            let mut res = vec![];
+            for b in b_i {
+                let mut ce = CounterExamples::new(p_i.len());
+                for (idx, el) in p_i.iter().enumerate() {
+                    if el.sprp(b) {
+                        ce.set(idx);
+                    }
+                }
+                res.push((b, ce));
+            }
+            res
+        }));
+    }
+
+    threads.into_iter().flat_map(|t| t.join().unwrap()).collect()
+}
+
+// Number of composites passing every base of the set
+fn fitness<T: Natural>(set: &BaseSeq<T>, cache: &HashMap<T, CounterExamples>) -> u64 {
+    let mut iter = set.iter();
+    let mut res = match iter.next() {
+        Some(b) => cache[b].clone(),
+        None => return u64::MAX,
+    };
+    for b in iter {
+        res.shared(&cache[b]);
+    }
+    res.count()
+}
+
+/*
+   In: A vector of composites, search parameters
+   Out: The fittest set of bases found and the number of composites that pass it
+
+   Each generation the fittest quarter is retained, the remainder are children of parents selected by tournament,
+   with uniform crossover and mutation by swapping in random bases. Terminates early if a set passes no composite
+*/
+pub(crate) fn evo_base_par<T: Natural>(pseudos: Vec<T>, param: &EvoParam) -> (BaseSeq<T>, u64) {
+    let mut rng = Xorshift(param.seed.unwrap_or_else(rand));
+    let pseudos = Arc::new(pseudos);
+    let mut cache = HashMap::<T, CounterExamples>::new();
+
+    let mut population = vec![];
+    for _ in 0..param.population {
+        let mut set = vec![];
+        for _ in 0..param.k {
+            let b = fresh_base(&mut rng, param, &set);
+            set.push(b);
+        }
+        population.push(BaseSeq::new(set));
+    }
+
+    let elite = (param.population / 4).max(1);
+    let mut generation = 0usize;
+
+    loop {
+        // Evaluate the bases not already cached, and discard those no longer in the population
+        let mut uncached = vec![];
+        for set in population.iter() {
+            for b in set.iter() {
+                if !cache.contains_key(b) && !uncached.contains(b) {
+                    uncached.push(*b);
+                }
+            }
+        }
+        cache.retain(|b, _| population.iter().any(|set| set.iter().any(|x| x == b)));
+        cache.extend(counterexamples_par(pseudos.clone(), uncached));
+
+        let mut scored = population
+            .into_iter()
+            .map(|set| {
+                let f = fitness(&set, &cache);
+                (f, set)
+            })
+            .collect::<Vec<(u64, BaseSeq<T>)>>();
+        scored.sort_by_key(|x| x.0);
+
+        if scored[0].0 == 0 || generation == param.generations {
+            return (scored[0].1.clone(), scored[0].0);
+        }
+        generation += 1;
+
+        population = scored[..elite].iter().map(|x| x.1.clone()).collect();
+
+        while population.len() < param.population {
+            // Tournament selection, the lower index is the fitter
+            let lhs = rng.below(scored.len() as u64).min(rng.below(scored.len() as u64)) as usize;
+            let rhs = rng.below(scored.len() as u64).min(rng.below(scored.len() as u64)) as usize;
+
+            let mut child = scored[lhs].1.clone();
+            for idx in 0..param.k {
+                let b = scored[rhs].1[idx];
+                let current = child.iter().copied().collect::<Vec<T>>();
+                if rng.below(2) == 1 && !current.contains(&b) {
+                    child.swap(b, idx);
+                }
+            }
+
+            for idx in 0..param.k {
+                if rng.unit() < param.mutation {
+                    let current = child.iter().copied().collect::<Vec<T>>();
+                    let b = fresh_base(&mut rng, param, &current);
+                    child.swap(b, idx);
+                }
+            }
+            population.push(child);
+        }
+    }
+}
+
+#[test]
+fn evo_reproducible() {
+    // Strong pseudoprimes to base 2, which two bases eliminate
+    let pseudos = (3..1u64 << 20).step_by(2).filter(|x| !x.is_prime() && x.sprp(2)).collect::<Vec<u64>>();
+    let mut param = EvoParam::new(2);
+    param.set_seed(11);
+    let run = || {
+        let (bases, count) = evo_base_par(pseudos.clone(), &param);
+        (bases.iter().copied().collect::<Vec<u64>>(), count)
+    };
+    let (bases, count) = run();
+    assert_eq!(run(), (bases.clone(), count));
+    assert_eq!(count, 0);
+    assert!(pseudos.iter().all(|x| !bases.iter().all(|b| x.sprp(*b))));
+}
@@ -244,3 +244,136 @@ fn array_bs_unary<T: Natural, const S: usize>(ce: &[T], primes: &[u64], bound: u
     // Return base array
     base_array
 }
+
+// Number of bases evaluated when selecting the strongest base of a bucket
+const STRONGEST_SEARCH: u64 = 256;
+
+/*
+    In : Composites of a bucket, the primes hashed to it, the number of bases, the bound and whether the bases must
+         eliminate every composite
+    Out: Bases that jointly eliminate all the composites, None if the bound is insufficient. If not complete the
+         strongest bases are returned regardless of the composites that remain
+
+    The strongest of the first bases is selected successively, until a single base eliminates the remainder
+*/
+fn bucket_bases<T: Natural>(
+    ce: &[T],
+    primes: &[u64],
+    stride: usize,
+    bound: u64,
+    complete: bool,
+) -> Option<Vec<u64>> {
+    let mut rem = ce.to_vec();
+    let mut res: Vec<u64> = vec![];
+
+    for pos in 0..stride {
+        if rem.is_empty() {
+            // Repeating a base is harmless, an empty bucket takes the first coprime base
+            let filler = match res.last() {
+                Some(b) => *b,
+                None => (2..=bound).find(|b| !gcd_check(*b, primes))?,
+            };
+            res.push(filler);
+            continue;
+        }
+
+        let full = bs_unary(&rem[..], primes, bound);
+        if full != 0 {
+            res.push(full);
+            rem.clear();
+            continue;
+        }
+        if pos == stride - 1 && complete {
+            return None;
+        }
+
+        let mut strongest = (0u64, usize::MAX);
+        for b in (2..=bound).filter(|b| !gcd_check(*b, primes)).take(STRONGEST_SEARCH as usize) {
+            let count = rem.iter().filter(|x| x.sprp(T::from(b))).count();
+            if count < strongest.1 {
+                strongest = (b, count);
+            }
+        }
+        if strongest.0 == 0 {
+            return None;
+        }
+        rem.retain(|x| x.sprp(T::from(strongest.0)));
+        res.push(strongest.0);
+    }
+    Some(res)
+}
+
+/*
+    In : Composites, dimension and multiplier of the hash, number of bases per bucket, the bound of the bases and
+         whether the table must eliminate every composite
+    Out: Table of stride bases per bucket, or InsufficientCandidates with the index of the bucket that failed
+*/
+pub(crate) fn multi_ht_par<T: Natural>(
+    ce: Vec<T>,
+    dimen: usize,
+    stride: usize,
+    multiplier: u32,
+    bound: u64,
+    complete: bool,
+) -> FResult<Vec<u64>> {
+    if !dimen.is_power_of_two() || stride == 0 {
+        return FResult::NotSupported;
+    }
+    let divisor = (32 - dimen.trailing_zeros()) as usize;
+
+    let mut prime_list = Primes::init(bound as usize).to_vector();
+    prime_list.push(2);
+    let mut primes = vec![vec![]; dimen];
+    for i in prime_list {
+        primes[i.hash_shift(divisor, multiplier)].push(i);
+    }
+
+    let mut buckets = vec![vec![]; dimen];
+    for i in ce {
+        buckets[i.hash_shift(divisor, multiplier)].push(i);
+    }
+
+    let output = Arc::new((0..dimen * stride).map(|_| AtomicU64::new(0)).collect::<Vec<AtomicU64>>());
+    let b_vec = Arc::new(buckets);
+    let p_vec = Arc::new(primes);
+    let idx = Arc::new(AtomicUsize::new(0));
+    let flag = Arc::new(AtomicBool::new(false));
+    let f_indx = Arc::new(AtomicUsize::new(0));
+
+    let mut thread_vec = vec![];
+    for _ in 0..thread_count() {
+        let o_i = Arc::clone(&output);
+        let b_i = Arc::clone(&b_vec);
+        let p_i = Arc::clone(&p_vec);
+        let idx_i = Arc::clone(&idx);
+        let f_i = Arc::clone(&flag);
+        let findx_i = Arc::clone(&f_indx);
+
+        thread_vec.push(std::thread::spawn(move || loop {
+            let c_idx = idx_i.fetch_add(1, Ordering::SeqCst);
+            if c_idx >= dimen || f_i.load(Ordering::SeqCst) {
+                break;
+            }
+            match bucket_bases(&b_i[c_idx][..], &p_i[c_idx][..], stride, bound, complete) {
+                Some(bases) => {
+                    for (j, b) in bases.iter().enumerate() {
+                        o_i[c_idx * stride + j].store(*b, Ordering::SeqCst);
+                    }
+                }
+                None => {
+                    f_i.store(true, Ordering::SeqCst);
+                    findx_i.store(c_idx, Ordering::SeqCst);
+                }
+            }
+        }));
+    }
+
+    for handle in thread_vec {
+        handle.join().unwrap();
+    }
+
+    if flag.load(Ordering::SeqCst) {
+        return FResult::InsufficientCandidates(f_indx.load(Ordering::SeqCst));
+    }
+    FResult::Value(output.iter().map(|q| q.load(Ordering::SeqCst)).collect())
+}
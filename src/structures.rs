@@ -1,9 +1,13 @@
 pub(crate) mod base;
+pub(crate) mod carmichael;
+pub(crate) mod certificate;
 pub(crate) mod ce;
 pub(crate) mod composite;
+pub(crate) mod family;
 pub(crate) mod fdata;
 pub(crate) mod hashtable;
 pub(crate) mod interval;
+pub(crate) mod jaeschke;
 pub(crate) mod pcg;
 pub(crate) mod pord;
 pub(crate) mod prime;
@@ -15,12 +19,16 @@ pub(crate) mod solvector;
 
 
 pub use base::BaseSeq;
+pub use carmichael::Carmichael;
+pub use certificate::{Certificate, Witness};
 pub use ce::CounterExamples;
 pub use composite::CompVector;
 pub use composite::Constructor;
+pub use family::{CompositeFamily, FamilyGenerator};
 pub use fdata::{DataVector, Point};
 pub use hashtable::HashTable;
 pub use interval::Interval;
+pub use jaeschke::{BoundCertificate, BoundFamily, FamilyCover};
 pub use pcg::PCGenerator;
 pub use pord::{SOSet, SmallOrd};
 pub use prime::Primes;
@@ -1,7 +1,8 @@
 use crate::car::MRC_18;
 use crate::enums::{Search, AUTO_FLAG, MEMORY_MAX, UTF8_FLAG};
+use crate::io::header::BinaryWriter;
 use crate::structures::store::Persistent;
-use crate::structures::{Primes,residue::ResidueClass,monier::MonierSemiprime};
+use crate::structures::{FamilyGenerator,Primes,residue::ResidueClass,monier::MonierSemiprime,carmichael::prime_factors,pord::SIEVE_MAX};
 use crate::{Natural,FResult,Epz};
 use crate::primes::{PARTIAL_WHEEL,WHEEL};
 use crate::{CompVector, HashTable};
@@ -12,6 +13,20 @@ use std::sync::{atomic::{Ordering,AtomicU64,AtomicBool},Arc};
 //use num_bigint::BigUint;
 
 
+// Candidates and extent of the Sorenson-Webster enumeration, see BaseSeq::sw_cover
+#[derive(Clone, Default)]
+pub(crate) struct SWCover {
+    // Candidates whose prime factors are all at most the square root of the bound
+    pub(crate) small: Vec<u128>,
+    // Candidates with a prime factor greater than the square root
+    pub(crate) large: Vec<u128>,
+    // Number of signatures and of primes sharing them
+    pub(crate) groups: u64,
+    pub(crate) primes: u64,
+    // Lambda values evaluated for the large prime factor
+    pub(crate) lambdas: u64,
+}
+
 /// Vector of Fermat bases to be evaluated sequentially as a full primality test
 #[derive(Clone, Debug)]
 pub struct BaseSeq<T: Natural> {
@@ -156,6 +171,18 @@ impl<T: Natural> BaseSeq<T> {
         self.mode = Search::Deterministic;
     }
 
+    pub fn set_mr_heuristic(&mut self) {
+        self.mode = Search::MRHeuristic;
+    }
+
+    pub fn set_spk_heuristic(&mut self, k: usize) {
+        self.mode = Search::SPKHeuristic(k);
+    }
+
+    pub fn set_spka_heuristic(&mut self, a: usize, b: usize) {
+        self.mode = Search::SPKAHeuristic(a, b);
+    }
+
     pub fn iter(&self) -> std::slice::Iter<T> {
         self.bases.iter()
     }
@@ -189,136 +216,24 @@ impl<T: Natural> BaseSeq<T> {
         return true;
     }
 
+    /// Pseudoprimes to the bases within the open interval (inf;sup) among the composites of the heuristic, see
+    /// FamilyGenerator::from_base_search. The weak heuristic evaluates the semiprimes (2k+1)(ak+1) for a in {3,4,6}
+    /// and the tabulated Carmichael numbers, the strong heuristic adds (k+1)(ak+1) for a in 2..64
+    /// # NotSupported
+    /// Deterministic search
     pub fn generate_pseudoprimes(
         &self,
         inf: T,
         sup: T,
         locale: Option<&str>,
     ) -> FResult<CompVector<T>> {
-        if self.mode == Search::Deterministic {
-            return FResult::NotSupported;
-        }
-
-        let p_bound = sup.isqrt().to_u64() as usize;
-        let plist = Primes::generate_or_restore(p_bound);
-
-        match locale {
-            // Write all composites to file
-            Some(x) => {
-                let mut outfile = File::create(x).unwrap();
-                let mut out = std::io::BufWriter::new(outfile.try_clone().unwrap());
-                // Monier-Rabin Heuristic
-                for i in plist.iter() {
-                    let lhs = T::from(i);
-
-                    for j in [3, 4, 6].iter() {
-                        let rhs = lhs.even_complement(T::from(*j));
-
-                        if rhs.is_prime() {
-                            let (prod, flag) = lhs.overflowing_mul(rhs);
-                            // If multiplication overflowed or the prod exceeds the bound break loop
-                            if flag || !prod.is_bounded_by(T::ZERO, sup) {
-                                break;
-                            }
-
-                            if prod.is_bounded_by(inf, sup) && self.primality(prod) {
-                                out.write(&prod.to_bytes()[..]).unwrap();
-                            }
-                        }
-                    }
-                }
-
-                for i in MRC_18 {
-                    let carmichael = T::from(i);
-                    if carmichael.is_bounded_by(inf, sup) && self.primality(carmichael) {
-                        out.write(&carmichael.to_bytes()[..]).unwrap();
-                    }
-                }
-                out.flush().unwrap();
-
-                if self.mode == Search::StrongHeuristic {
-                    for i in plist.iter() {
-                        let lhs = T::from(i);
-
-                        for j in 2..64 {
-                            let rhs = lhs.semi_k_complement(j);
-
-                            if rhs.is_prime() {
-                                let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                                if flag || !prod.is_bounded_by(T::ZERO, sup) {
-                                    break;
-                                }
-
-                                if prod.is_bounded_by(inf, sup) && self.primality(prod) {
-                                    out.write(&prod.to_bytes()[..]).unwrap();
-                                }
-                            }
-                        }
-                    }
-                    out.flush().unwrap();
-                }
-                return FResult::Value(CompVector::from_file_internal(
-                    outfile.try_clone().unwrap(),
-                    MEMORY_MAX,
-                    UTF8_FLAG,
-                    AUTO_FLAG,
-                ));
-            } // End file write
-
-            // Write all composites to volatile memory (i.e vector)
-            None => {
-                let mut ce = Vec::<T>::new();
-
-                for i in plist.iter() {
-                    let lhs = T::from(i);
-                    for j in [3, 4, 6].iter() {
-                        let rhs = lhs.even_complement(T::from(*j));
-
-                        if rhs.is_prime() {
-                            let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                            if flag || !prod.is_bounded_by(T::ZERO, sup) {
-                                break;
-                            }
-
-                            if prod.is_bounded_by(inf, sup) & self.primality(prod) {
-                                ce.push(prod);
-                            }
-                        }
-                    }
-                }
-
-                for i in MRC_18 {
-                    if T::from(i).is_bounded_by(inf, sup) & self.primality(T::from(i)) {
-                        ce.push(T::from(i))
-                    }
-                }
-
-                if self.mode == Search::StrongHeuristic {
-                    for i in plist.iter() {
-                        let lhs = T::from(i);
-
-                        for j in 2..64 {
-                            let rhs = lhs.semi_k_complement(j);
-
-                            if rhs.is_prime() {
-                                let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                                if flag || !prod.is_bounded_by(T::ZERO, sup) {
-                                    break;
-                                }
-
-                                if prod.is_bounded_by(inf, sup) && !flag && self.primality(prod) {
-                                    ce.push(prod);
-                                }
-                            }
-                        }
-                    }
-                }
-                return FResult::Value(CompVector::from_vector(ce));
-            }
-        } // end match
+        let gen = match FamilyGenerator::from_base_search(inf, sup, self.mode) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let metadata = format!("bases {} interval {},{} {:?}", self, inf, sup, self.mode);
+        let bases = self.clone();
+        gen.collect(locale, &metadata, Arc::new(move |x| bases.primality(x)))
     }
 }
 
@@ -1095,12 +1010,408 @@ impl BaseSeq<u64> {
         return FResult::Value(ce);
     }
 
+    // Strong pseudoprime check in 128-bit arithmetic, see sprp_reduced
+    pub(crate) fn sprp_128(&self, n: u128) -> bool {
+        self.bases.iter().all(|b| sprp_reduced(n, *b))
+    }
+
+    // Residue classes r mod R such that every base b with 4b | R is a quadratic residue of primes p = r mod R
+    // If the order of every base to p is odd then every base is a quadratic residue, which restricts p to 2^-k of the units
+    fn qr_wheel(&self) -> ResidueClass {
+        let mut ring = 8u64;
+        let mut included = vec![];
+
+        for b in self.bases.iter() {
+            if let Some(x) = ring.lcm(4 * *b) {
+                if x <= 1 << 17 {
+                    ring = x;
+                    included.push(*b);
+                }
+            }
+        }
+
+        let mut res = vec![];
+
+        for r in (1..ring).step_by(2) {
+            if r.gcd(ring) == 1 && included.iter().all(|b| b.jacobi(r) == 1) {
+                res.push(r);
+            }
+        }
+        ResidueClass::new(res, ring)
+    }
+
+    // Primes p in (inf;sup] such that lcm(ord_b(p)) over all bases is exactly lambda
+    fn lambda_primes(&self, lambda: u64, inf: u128, sup: u128, wheel: &ResidueClass) -> Vec<u128> {
+        let b128 = self.bases.iter().map(|x| *x as u128).collect::<Vec<u128>>();
+        let mut res = vec![];
+
+        let is_lambda = |p: u128| -> bool {
+            match p.signature_v(&b128[..]) {
+                Some((ord, _)) => ord == lambda as u128,
+                None => false,
+            }
+        };
+
+        // Lambda-small, p divides gcd(b^lambda - 1) over all bases
+        if lambda < 128 && (sup - inf) / (lambda as u128) >= SIEVE_MAX as u128 {
+            if let Some(x) = (self.bases[0] as u128).checked_pow(lambda as u32) {
+                let mut g = x - 1;
+
+                for b in b128[1..].iter() {
+                    if g == 1 {
+                        break;
+                    }
+                    let r = b.exp_residue(lambda as u128, g);
+                    g = g.gcd((r + g - 1) % g);
+                }
+
+                let mut fctr = vec![];
+                prime_factors(g, &mut fctr);
+
+                for (p, _) in fctr {
+                    if p > inf && p <= sup && is_lambda(p) {
+                        res.push(p);
+                    }
+                }
+                return res;
+            }
+        }
+
+        // Lambda-large, sieve the residue classes 1 mod lambda
+        let mut classes = vec![];
+        // If p = 1 mod 2*lambda then all orders have a smaller power of 2 than p-1 and the bases are quadratic residues
+        let ring = (lambda as u128) * 2;
+
+        if ring * (wheel.ring as u128) < 1u128 << 64 {
+            classes.push(wheel.promote(1, ring as u64));
+        } else {
+            classes.push(ResidueClass::new(vec![1], ring as u64));
+        }
+
+        if lambda & 1 == 0 {
+            classes.push(ResidueClass::new(vec![lambda + 1], ring as u64));
+        }
+
+        for class in classes {
+            let stride = class.ring as u128;
+
+            for r in class.iter() {
+                let mut p = (inf / stride) * stride + *r as u128;
+
+                while p <= sup {
+                    if p > inf {
+                        let flag = if p < 1u128 << 64 {
+                            self.bases[0].exp_unit(lambda, p as u64)
+                        } else {
+                            b128[0].exp_unit(lambda as u128, p)
+                        };
+
+                        if flag && b128.iter().all(|b| b.exp_unit(lambda as u128, p)) && p.is_prime() && is_lambda(p) {
+                            res.push(p);
+                        }
+                    }
+                    p += stride;
+                }
+            }
+        }
+        res
+    }
+
+    // Extends the product m of primes that share a signature by smaller primes of the same signature
+    // Every strong pseudoprime divisible by m is of the form mk where k = m^-1 mod lambda, so if the arithmetic progression
+    // is shorter than the remaining primes it is evaluated directly
+    fn sw_descend(&self, group: &[(u64, u64)], idx: usize, m: u128, lambda: u128, sup: u128, out: &mut Vec<u128>) {
+        if lambda >= sup {
+            return;
+        }
+
+        if (m - 1).is_multiple_of(lambda) {
+            out.push(m);
+        }
+
+        let k_max = (sup - 1) / m;
+
+        if idx == 0 || k_max < 2 {
+            return;
+        }
+
+        if k_max / lambda <= idx as u128 {
+            let mut k = if lambda == 1 {
+                1
+            } else {
+                (m % lambda).mul_inverse(lambda).unwrap() % lambda
+            };
+
+            while k < 2 {
+                k += lambda;
+            }
+
+            while k <= k_max {
+                let n = m * k;
+                if self.sprp_128(n) {
+                    out.push(n);
+                }
+                k += lambda;
+            }
+            return;
+        }
+
+        for (jdx, (q, qlambda)) in group[..idx].iter().enumerate() {
+            let (q, qlambda) = (*q as u128, *qlambda as u128);
+
+            if q > k_max {
+                break;
+            }
+            // q | lambda or gcd(lambda(q),m) != 1 contradict m*q | n and lambda | n-1
+            if lambda.is_multiple_of(q) || qlambda.gcd(m) != 1 {
+                continue;
+            }
+
+            let n_lambda = match lambda.lcm(qlambda) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let mut qpow = q;
+            // Higher powers are only possible for primes that are Wieferich to every base
+            loop {
+                self.sw_descend(group, jdx, m * qpow, n_lambda, sup, out);
+
+                match qpow.checked_mul(q) {
+                    Some(x) => {
+                        if x > k_max || !self.bases.iter().all(|b| (*b as u128).exp_residue(qlambda, x) == 1) {
+                            break;
+                        }
+                        qpow = x;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Enumerates all strong pseudoprimes to the bases less than sup, using the algorithm of Sorenson and Webster
+    ///
+    /// Pseudoprimes are split by their largest prime factor p, with lambda(p) the lcm of the orders of the bases modulo p.
+    /// If p > sqrt(sup) then lambda(p) < sqrt(sup) and p is either a factor of gcd(b^lambda - 1) (lambda-small) or is found by
+    /// sieving 1 mod lambda (lambda-large). Otherwise all prime factors are at most sqrt(sup) and share the signature of p
+    /// # Usage
+    /// Intended to be used with the first k primes as in the computation of Jaeschke's psi function, but applies to any bases
+    ///
+    /// It is not a unit of PsiEval, which only searches the Monier-Rabin semiprimes to bounds of 2^128 and beyond where
+    /// enumerating every strong pseudoprime is infeasible. Instead `jaeschke_bound` proves that a semiprime candidate is the
+    /// least strong pseudoprime by enumerating every strong pseudoprime below it
+    pub fn sorenson_webster(&self, sup: u128) -> FResult<CompVector<u128>> {
+        let cover = match self.sw_cover(sup) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+
+        let mut values = cover.small;
+        values.extend_from_slice(&cover.large[..]);
+        values.sort();
+        values.dedup();
+        values.retain(|x| !x.is_prime() && self.sprp_128(*x));
+
+        FResult::Value(CompVector::from_vector(values))
+    }
+
+    // Candidates of the Sorenson-Webster enumeration below sup, split by whether the largest prime factor exceeds sqrt(sup)
+    // Candidates are not necessarily composite nor strong pseudoprimes, see sorenson_webster
+    pub(crate) fn sw_cover(&self, sup: u128) -> FResult<SWCover> {
+        if self.bases.is_empty() {
+            return FResult::Err("Empty base sequence");
+        }
+
+        if self.bases.iter().any(|b| *b < 2) {
+            return FResult::Err("Bases must be greater than 1");
+        }
+
+        if sup < 5 {
+            return FResult::Value(SWCover::default());
+        }
+
+        let root = sup.isqrt();
+
+        if root >= 1u128 << 64 {
+            return FResult::Err("Bound exceeds 2^128");
+        }
+
+        let tc = thread_count();
+
+        // Signatures of the small primes
+        let plist = Arc::new(
+            Primes::generate_or_restore((root as usize).max(16))
+                .iter()
+                .take_while(|p| (*p as u128) <= root)
+                .collect::<Vec<u64>>(),
+        );
+        let sig_idx = Arc::new(AtomicU64::new(0));
+        let mut thread_vec = Vec::new();
+
+        for _ in 0..tc {
+            let p_i = Arc::clone(&plist);
+            let idx_i = Arc::clone(&sig_idx);
+            let bases = self.bases.clone();
+
+            thread_vec.push(std::thread::spawn(move || {
+                let mut res = vec![];
+                loop {
+                    let c_idx = idx_i.fetch_add(1, Ordering::SeqCst) as usize;
+
+                    if c_idx >= p_i.len() {
+                        break;
+                    }
+
+                    let p = p_i[c_idx];
+
+                    if let Some((lambda, sig)) = p.signature_v(&bases[..]) {
+                        res.push((sig, p, lambda));
+                    }
+                }
+                res
+            }));
+        }
+
+        // Primes grouped by signature, 2 is excluded as strong pseudoprimes are odd
+        let mut groups: std::collections::HashMap<Vec<u32>, Vec<(u64, u64)>> = std::collections::HashMap::new();
+
+        for handle in thread_vec {
+            for (sig, p, lambda) in handle.join().unwrap() {
+                groups.entry(sig).or_default().push((p, lambda));
+            }
+        }
+
+        let group_count = groups.len() as u64;
+        let prime_count = groups.values().map(|g| g.len() as u64).sum::<u64>();
+        let mut tasks = vec![];
+
+        for group in groups.into_values() {
+            let mut group = group;
+            group.sort();
+            let group = Arc::new(group);
+
+            for idx in 0..group.len() {
+                tasks.push((Arc::clone(&group), idx));
+            }
+        }
+
+        // Strong pseudoprimes whose largest prime factor is at most sqrt(sup)
+        let tasks = Arc::new(tasks);
+        let task_idx = Arc::new(AtomicU64::new(0));
+        let mut thread_vec: Vec<std::thread::JoinHandle<Vec<u128>>> = Vec::new();
+
+        for _ in 0..tc {
+            let t_i = Arc::clone(&tasks);
+            let idx_i = Arc::clone(&task_idx);
+            let witness = self.clone();
+
+            thread_vec.push(std::thread::spawn(move || {
+                let mut res = vec![];
+                loop {
+                    let c_idx = idx_i.fetch_add(1, Ordering::SeqCst) as usize;
+
+                    if c_idx >= t_i.len() {
+                        break;
+                    }
+
+                    let (group, idx) = &t_i[c_idx];
+                    let (p, lambda) = (group[*idx].0 as u128, group[*idx].1 as u128);
+
+                    let mut ppow = p;
+
+                    loop {
+                        witness.sw_descend(&group[..], *idx, ppow, lambda, sup, &mut res);
+
+                        match ppow.checked_mul(p) {
+                            Some(x) => {
+                                if x >= sup || !witness.bases.iter().all(|b| (*b as u128).exp_residue(lambda, x) == 1) {
+                                    break;
+                                }
+                                ppow = x;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                res
+            }));
+        }
+
+        let mut small = vec![];
+
+        for handle in thread_vec {
+            small.extend_from_slice(&handle.join().unwrap()[..]);
+        }
+
+        // Strong pseudoprimes whose largest prime factor exceeds sqrt(sup), these have lambda < sqrt(sup)
+        let wheel = Arc::new(self.qr_wheel());
+        let lambda_idx = Arc::new(AtomicU64::new(1));
+        let mut thread_vec: Vec<std::thread::JoinHandle<Vec<u128>>> = Vec::new();
+
+        for _ in 0..tc {
+            let w_i = Arc::clone(&wheel);
+            let idx_i = Arc::clone(&lambda_idx);
+            let witness = self.clone();
+
+            thread_vec.push(std::thread::spawn(move || {
+                let mut res = vec![];
+                loop {
+                    let lambda = idx_i.fetch_add(1, Ordering::SeqCst);
+                    // Largest prime such that a cofactor of 1 + lambda exists
+                    let hi = (sup - 1) / (lambda as u128 + 1);
+
+                    if hi <= root {
+                        break;
+                    }
+
+                    for p in witness.lambda_primes(lambda, root, hi, &w_i) {
+                        let k_max = (sup - 1) / p;
+                        let mut k = 1 + lambda as u128;
+
+                        while k <= k_max {
+                            let n = p * k;
+                            if witness.sprp_128(n) {
+                                res.push(n);
+                            }
+                            k += lambda as u128;
+                        }
+                    }
+                }
+                res
+            }));
+        }
+
+        let mut large = vec![];
+
+        for handle in thread_vec {
+            large.extend_from_slice(&handle.join().unwrap()[..]);
+        }
+
+        // Every thread stops at the first lambda exceeding the bound, so the last lambda evaluated is one less
+        let lambdas = lambda_idx.load(Ordering::SeqCst) - tc as u64 - 1;
+
+        FResult::Value(SWCover {
+            small,
+            large,
+            groups: group_count,
+            primes: prime_count,
+            lambdas,
+        })
+    }
+
     // If Strong heuristic
     // If Deterministic block
 
     // generate_pseudoprimes(&self,inf: T, sup: T ) -> CompVector<T>
 }
 
+/// Strong fermat test of n to the base reduced modulo n. Even n and bases divisible by n do not pass
+pub(crate) fn sprp_reduced(n: u128, b: u64) -> bool {
+    let b = b as u128 % n;
+    n & 1 == 1 && b != 0 && n.sprp(b)
+}
+
 impl<T: Natural> std::fmt::Display for BaseSeq<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let zepto = self
@@ -1112,3 +1423,50 @@ impl<T: Natural> std::fmt::Display for BaseSeq<T> {
         write!(f, "{}", quokka)
     }
 }
+
+// Strong fermat test of odd n > 1 to the base b mod n by square and multiply, independent of the library arithmetic
+#[cfg(test)]
+pub(crate) fn naive_sprp(n: u64, b: u64) -> bool {
+    let n128 = n as u128;
+    let b = b as u128 % n128;
+    if n & 1 == 0 || b == 0 {
+        return false;
+    }
+    let pow = |mut base: u128, mut e: u64| -> u128 {
+        let mut acc = 1u128;
+        while e > 0 {
+            if e & 1 == 1 {
+                acc = acc * base % n128;
+            }
+            base = base * base % n128;
+            e >>= 1;
+        }
+        acc
+    };
+    let tzc = (n - 1).trailing_zeros();
+    let mut x = pow(b, (n - 1) >> tzc);
+    if x == 1 || x == n128 - 1 {
+        return true;
+    }
+    for _ in 1..tzc {
+        x = x * x % n128;
+        if x == n128 - 1 {
+            return true;
+        }
+    }
+    false
+}
+
+#[test]
+fn sorenson_webster() {
+    let sup = 2_000_000u64;
+    let sets: [&[u64]; 10] = [&[2], &[3], &[15], &[2, 3], &[3, 5], &[6], &[10, 21], &[3, 5, 7], &[38], &[60, 63]];
+    for bases in sets {
+        let sw = BaseSeq::new(bases.to_vec()).sorenson_webster(sup as u128).unwrap().to_vector();
+        let brute = (3..sup)
+            .filter(|n| !n.is_prime() && bases.iter().all(|b| naive_sprp(*n, *b)))
+            .map(|n| n as u128)
+            .collect::<Vec<u128>>();
+        assert_eq!(sw, brute, "bases {:?}", bases);
+    }
+}
@@ -0,0 +1,352 @@
+use crate::car::MRC_18;
+use crate::search::thread_count;
+use crate::structures::Primes;
+use crate::{FResult, Natural};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/*
+   Carmichael number generation
+
+   Korselt's criterion: n is a Carmichael number iff n is squarefree and p-1 | n-1 for all primes p | n
+
+   Let n = P*q*r where P is the product of the k-2 smallest prime factors and q < r the two largest.
+
+     r-1 | n-1 implies r-1 | Pq-1, set h = (Pq-1)/(r-1), then 1 < h < P + (P-1)/(q-1)
+
+     q-1 | n-1 implies q-1 | h(Pr-1) = P(Pq-1) + h(P-1) which is congruent to (P-1)(P+h) mod q-1
+
+   So for each prefix P and each h the candidate q are the divisors of (P-1)(P+h) plus one, and r is determined by q and h.
+   This is R.G.E Pinch's construction for three factors generalised to a prefix of k-2 primes.
+
+   Each Carmichael number is produced exactly once, as the prefix, h, and q are uniquely determined by the factorisation.
+*/
+
+// Bound of the tabulated Carmichael numbers MRC_18
+const MRC_BOUND: u128 = 1_000_000_000_000_000_000;
+
+/// Generator of Carmichael numbers with a fixed number of prime factors
+///
+/// Enumerates all Carmichael numbers with exactly k prime factors within a bound, optionally restricting all prime factors to a
+/// single residue class. Products are evaluated in 128-bit arithmetic so the maximum bound is 2^128
+#[derive(Clone, Debug)]
+pub struct Carmichael {
+    k: usize,
+    residue: Option<(u64, u64)>,
+}
+
+// Prime factorisation of x with all factors guaranteed to be prime, appended to the accumulator
+pub(crate) fn prime_factors(x: u128, acc: &mut Vec<(u128, u32)>) {
+    if x < 2 {
+        return;
+    }
+    let fctr = x.factor().unwrap();
+    for (p, e) in fctr.pair_iter() {
+        if *p == 1 {
+            continue;
+        }
+        // Pollard-Brent may return a composite factor, so it is decomposed again
+        if !p.is_prime() {
+            let mut interim = vec![];
+            prime_factors(*p, &mut interim);
+            for (q, f) in interim {
+                acc.push((q, f * e));
+            }
+            continue;
+        }
+        acc.push((*p, *e));
+    }
+}
+
+// Merges the prime factorisations of x and y into the factorisation of x*y
+fn product_factors(x: &[(u128, u32)], y: u128) -> Vec<(u128, u32)> {
+    let mut res = x.to_vec();
+    let mut interim = vec![];
+    prime_factors(y, &mut interim);
+
+    for (p, e) in interim {
+        match res.iter_mut().find(|(q, _)| *q == p) {
+            Some(el) => el.1 += e,
+            None => res.push((p, e)),
+        }
+    }
+    res
+}
+
+// All divisors of the factorisation
+fn divisors(fctr: &[(u128, u32)]) -> Vec<u128> {
+    let mut res = vec![1u128];
+
+    for (p, e) in fctr {
+        let len = res.len();
+        let mut pow = 1u128;
+        for _ in 0..*e {
+            pow *= *p;
+            for idx in 0..len {
+                res.push(res[idx] * pow);
+            }
+        }
+    }
+    res
+}
+
+impl Carmichael {
+    /// Carmichael numbers with k prime factors, k must be at least 3
+    pub fn new(k: usize) -> Self {
+        Self { k, residue: None }
+    }
+
+    /// Carmichael numbers of the form pqr where p,q,r are all 3 mod 4. This is the set MRC_18 is drawn from
+    pub fn mrc() -> Self {
+        Self {
+            k: 3,
+            residue: Some((3, 4)),
+        }
+    }
+
+    /// Restricts all prime factors to residue mod ring
+    pub fn set_residue(&mut self, residue: u64, ring: u64) {
+        self.residue = Some((residue % ring, ring));
+    }
+
+    /// Removes the residue class restriction
+    pub fn set_unrestricted(&mut self) {
+        self.residue = None;
+    }
+
+    /// Number of prime factors
+    pub fn factor_count(&self) -> usize {
+        self.k
+    }
+
+    fn in_class(&self, p: u128) -> bool {
+        match self.residue {
+            Some((res, ring)) => p % (ring as u128) == res as u128,
+            None => true,
+        }
+    }
+
+    // Evaluates all Carmichael numbers of the form P*q*r where P is the prefix product, L = lcm(p-1) for p | P,
+    // and last is the largest prime dividing P
+    fn complete(&self, prefix: u128, lambda: u128, last: u128, inf: u128, sup: u128, out: &mut Vec<u128>) {
+        let mut pminus = vec![];
+        prime_factors(prefix - 1, &mut pminus);
+
+        // n > Pq(Pq-1)/h > P*last*(P*last-1)/h so h > P*last*(P*last-1)/sup
+        let a = prefix * last;
+        let hmin = match a.checked_mul(a - 1) {
+            Some(prod) => prod / sup,
+            None => a / (sup / (a - 1) + 1),
+        }
+        .max(2);
+        // r > q implies h < P + (P-1)/(q-1)
+        let hmax = prefix + (prefix - 1) / last;
+
+        for h in hmin..=hmax {
+            let fctr = product_factors(&pminus, prefix + h);
+
+            for d in divisors(&fctr) {
+                if d < last {
+                    continue;
+                }
+                let q = d + 1;
+
+                let pq = match prefix.checked_mul(q) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                if (pq - 1) % h != 0 {
+                    continue;
+                }
+
+                let r = (pq - 1) / h + 1;
+
+                if r <= q || !self.in_class(q) || !self.in_class(r) {
+                    continue;
+                }
+
+                let n = match pq.checked_mul(r) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                if n >= sup || n <= inf {
+                    continue;
+                }
+                // Korselt's criterion for the prefix and q, r-1 | n-1 by construction
+                if (n - 1) % lambda != 0 || (n - 1) % d != 0 {
+                    continue;
+                }
+
+                if q.is_prime() && r.is_prime() {
+                    out.push(n);
+                }
+            }
+        }
+    }
+
+    // Extends the prefix by the prime primes[idx], returns false if the prime and all greater primes exceed the bound
+    fn extend(
+        &self,
+        primes: &[u64],
+        idx: usize,
+        depth: usize,
+        (prefix, lambda): (u128, u128),
+        (inf, sup): (u128, u128),
+        out: &mut Vec<u128>,
+    ) -> bool {
+        let p = primes[idx] as u128;
+        let remaining = (self.k - depth) as u32;
+        // All remaining factors are greater than or equal to p
+        match p.checked_pow(remaining).and_then(|x| x.checked_mul(prefix)) {
+            Some(x) => {
+                if x >= sup {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+
+        if !self.in_class(p) {
+            return true;
+        }
+        // Korselt's criterion requires gcd(p,L) = 1 and gcd(p-1,P) = 1
+        if lambda.is_multiple_of(p) || (p - 1).gcd(prefix) != 1 {
+            return true;
+        }
+
+        let n_prefix = prefix * p;
+        let n_lambda = lambda.lcm(p - 1).unwrap();
+
+        if depth + 1 == self.k - 2 {
+            self.complete(n_prefix, n_lambda, p, inf, sup, out);
+        } else {
+            for jdx in idx + 1..primes.len() {
+                if !self.extend(primes, jdx, depth + 1, (n_prefix, n_lambda), (inf, sup), out) {
+                    break;
+                }
+            }
+        }
+        true
+    }
+
+    /// Enumerates all Carmichael numbers with k prime factors in the open interval (inf;sup), in ascending order
+    pub fn enumerate(&self, inf: u128, sup: u128) -> FResult<Vec<u128>> {
+        if self.k < 3 {
+            return FResult::Err("Carmichael numbers have at least 3 prime factors");
+        }
+
+        if let Some((_, ring)) = self.residue {
+            if ring == 0 {
+                return FResult::Err("Residue class of zero ring");
+            }
+        }
+
+        let (inf, sup) = inf.min_max(sup);
+        // All prefix primes are less than the cube root of the bound
+        let p_bound = (sup.nth_root(3) + 2) as usize;
+
+        if p_bound < 5 {
+            return FResult::Value(vec![]);
+        }
+
+        let plist: Arc<Vec<u64>> = Arc::new(Primes::generate_or_restore(p_bound).to_vector());
+        let idx = Arc::new(AtomicUsize::new(0usize));
+        let tc = thread_count();
+
+        let mut thread_vec: Vec<std::thread::JoinHandle<Vec<u128>>> = Vec::new();
+
+        // Threads take the smallest prime factor from a shared index
+        for _ in 0..tc {
+            let p_i = Arc::clone(&plist);
+            let idx_i = Arc::clone(&idx);
+            let generator = self.clone();
+
+            thread_vec.push(std::thread::spawn(move || {
+                let mut res = vec![];
+                loop {
+                    let c_idx = idx_i.fetch_add(1, Ordering::SeqCst);
+
+                    if c_idx >= p_i.len() {
+                        break;
+                    }
+                    if !generator.extend(&p_i[..], c_idx, 0, (1, 1), (inf, sup), &mut res) {
+                        break;
+                    }
+                }
+                res
+            }));
+        }
+
+        let mut total = vec![];
+
+        for handle in thread_vec {
+            match handle.join() {
+                Ok(x) => total.extend_from_slice(&x[..]),
+                Err(_) => return FResult::Critical,
+            }
+        }
+        total.sort();
+        FResult::Value(total)
+    }
+
+    /// Checks the tabulated Carmichael numbers pqr with p, q and r all 3 mod 4 (MRC_18) against the generator, below sup
+    ///
+    /// Returns true if the generator reproduces the table. The table extends to 10^18, regenerating all of it is a long computation
+    pub fn check_tabulated(sup: u128) -> FResult<bool> {
+        let sup = sup.min(MRC_BOUND);
+        let generated = match Carmichael::mrc().enumerate(0, sup) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let tabulated = MRC_18.iter().map(|x| *x as u128).take_while(|x| *x < sup);
+        FResult::Value(generated.into_iter().eq(tabulated))
+    }
+}
+
+// Carmichael numbers with k prime factors below sup by Korselt's criterion
+#[cfg(test)]
+fn korselt_count(k: usize, sup: usize) -> usize {
+    let mut spf = vec![0usize; sup];
+    for i in 2..sup {
+        if spf[i] == 0 {
+            for j in (i..sup).step_by(i) {
+                if spf[j] == 0 {
+                    spf[j] = i;
+                }
+            }
+        }
+    }
+    (3..sup)
+        .step_by(2)
+        .filter(|n| {
+            let mut x = *n;
+            let mut factors = 0;
+            while x > 1 {
+                let p = spf[x];
+                x /= p;
+                if x % p == 0 || (n - 1) % (p - 1) != 0 || p == *n {
+                    return false;
+                }
+                factors += 1;
+            }
+            factors == k
+        })
+        .count()
+}
+
+#[test]
+fn carmichael_korselt() {
+    for k in 3..6 {
+        let generated = Carmichael::new(k).enumerate(0, 1_000_000).unwrap();
+        assert_eq!(generated.len(), korselt_count(k, 1_000_000));
+    }
+    let counts = (3..6).map(|k| Carmichael::new(k).enumerate(0, 100_000_000).unwrap().len()).collect::<Vec<usize>>();
+    assert_eq!(counts, [84, 144, 27]);
+}
+
+#[test]
+fn carmichael_tabulated() {
+    assert!(Carmichael::check_tabulated(100_000_000_000).unwrap());
+}
@@ -4,6 +4,7 @@ use crate::io::write::write_binary;
 /// A bitvector that stores information on counterexamples to some reference set
 ///
 /// Primarily for internal use.
+#[derive(Clone)]
 pub struct CounterExamples {
     idx: Vec<u64>,
 }
@@ -0,0 +1,499 @@
+use crate::io::header::{checksum_update, seek_payload, FileHeader, FNV_OFFSET};
+use crate::structures::hashtable::parse_lines;
+use crate::structures::store::Persistent;
+use crate::{CompVector, FResult, HashTable, Natural};
+use std::io::{Read, Seek, SeekFrom};
+
+/*
+   Certificates that a base set or hashtable eliminates every composite of a reference file below a bound
+
+   The certificate records the file by its path, checksum and element count, the bound, the witness and the
+   composites below the bound that pass it. It is created with the library primality tests and verified by
+   reading the raw elements of the file and repeating the strong fermat tests with an independent modular
+   arithmetic, so that an error in either is detected as a mismatch.
+
+   Format, one field per line
+
+   certificate: deterministic | failures
+   source: path of the binary file of composites
+   checksum: FNV-1a hash of the element bytes, in hexadecimal
+   elements: number of elements
+   width: bytes per element
+   provenance: metadata of the file header, empty for legacy files. Backslashes and line breaks are escaped
+   bound: exclusive bound of the composites evaluated
+   evaluated: number of elements less than the bound
+   passing: number of composites passing, followed by a line of them comma-separated if any
+   witness: bases a,b,.. | hashtable, followed by the table
+*/
+
+/// Base set or hashtable certified by a Certificate
+#[derive(Clone)]
+pub enum Witness {
+    /// Every base is evaluated
+    Bases(Vec<u64>),
+    /// Bases selected by hashing, see HashTable::primality
+    Table(HashTable),
+}
+
+impl Witness {
+    /// Evaluates the witness using the library primality tests
+    pub fn check<T: Natural>(&self, x: T) -> bool {
+        match self {
+            Witness::Bases(b) => b.iter().all(|a| x.sprp(T::from(*a))),
+            Witness::Table(ht) => ht.primality(x),
+        }
+    }
+}
+
+/// Record that a base set or hashtable eliminates the composites of a reference file below a bound
+///
+/// The witness is deterministic below the bound if the file contains every composite that could pass it, which the
+/// checksum and provenance identify but the certificate cannot prove
+#[derive(Clone)]
+pub struct Certificate {
+    source: String,
+    checksum: u64,
+    elements: u64,
+    width: usize,
+    provenance: String,
+    bound: u128,
+    evaluated: u64,
+    witness: Witness,
+    passing: Vec<u128>,
+}
+
+fn widen<T: Natural>(x: T) -> u128 {
+    x.to_bytes().iter().rev().fold(0u128, |acc, b| (acc << 8) | *b as u128)
+}
+
+impl Certificate {
+    /// Evaluates the witness against the composites of the binary file less than bound
+    /// # NotSupported
+    /// T is wider than 128 bits
+    /// # Err
+    /// The file does not match the element length of T or its checksum
+    pub fn new<T: Natural>(source: &str, witness: Witness, bound: T) -> FResult<Self> {
+        if T::BYTE_LENGTH > 16 {
+            return FResult::NotSupported;
+        }
+        let cvec = match CompVector::<T>::from_file(source) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let provenance = match cvec.header() {
+            FResult::Value(h) => h.metadata,
+            _ => String::new(),
+        };
+
+        let mut checksum = FNV_OFFSET;
+        let mut elements = 0u64;
+        let mut evaluated = 0u64;
+        let res = cvec.for_each_chunk(&mut |chunk: Vec<T>| {
+            for i in chunk {
+                checksum = checksum_update(checksum, &i.to_bytes()[..]);
+                elements += 1;
+                if i < bound {
+                    evaluated += 1;
+                }
+            }
+            Ok(())
+        });
+        if let FResult::IOError(message) = res {
+            return FResult::IOError(message);
+        }
+
+        let w = witness.clone();
+        let passing = match cvec.filter_streaming(move |x: T| x < bound && w.check(x), None) {
+            FResult::Value(x) => x.to_vector().into_iter().map(widen).collect(),
+            failure => return failure.recast(),
+        };
+
+        FResult::Value(Self {
+            source: source.to_string(),
+            checksum,
+            elements,
+            width: T::BYTE_LENGTH,
+            provenance,
+            bound: widen(bound),
+            evaluated,
+            witness,
+            passing,
+        })
+    }
+
+    /// No composite of the file less than the bound passes the witness
+    pub fn is_deterministic(&self) -> bool {
+        self.passing.is_empty()
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// FNV-1a hash of the element bytes, equal to the checksum of the file header
+    pub fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    pub fn provenance(&self) -> &str {
+        &self.provenance
+    }
+
+    pub fn bound(&self) -> u128 {
+        self.bound
+    }
+
+    /// Number of composites of the file less than the bound
+    pub fn evaluated(&self) -> u64 {
+        self.evaluated
+    }
+
+    pub fn witness(&self) -> &Witness {
+        &self.witness
+    }
+
+    /// Composites less than the bound that pass the witness, in file order
+    pub fn passing(&self) -> &[u128] {
+        &self.passing[..]
+    }
+
+    /// Re-reads the raw elements of the source and repeats every test without the library primality tests
+    ///
+    /// Evaluation is sequential
+    /// # Err
+    /// The source differs from the one certified, or the composites passing the witness differ
+    pub fn verify(&self) -> FResult<()> {
+        if self.width != 8 && self.width != 16 {
+            return FResult::Err("Element width of certificate must be 8 or 16 bytes");
+        }
+        let mut file = match std::fs::File::open(&self.source) {
+            Ok(x) => x,
+            Err(message) => return FResult::IOError(message),
+        };
+        match FileHeader::read(&file) {
+            Ok(Some(h)) if h.byte_length as usize != self.width => {
+                return FResult::Err("Element width of source does not match certificate")
+            }
+            Ok(_) => (),
+            Err(message) => return FResult::IOError(message),
+        }
+        let mut payload = match seek_payload(&file).and_then(|offset| file.seek(SeekFrom::Start(offset))) {
+            Ok(_) => std::io::BufReader::new(file),
+            Err(message) => return FResult::IOError(message),
+        };
+
+        let mut checksum = FNV_OFFSET;
+        let mut elements = 0u64;
+        let mut evaluated = 0u64;
+        let mut passing = vec![];
+
+        loop {
+            let mut buffer = [0u8; 16];
+            let el = &mut buffer[..self.width];
+            match read_element(&mut payload, el) {
+                Ok(0) => break,
+                Ok(len) if len < self.width => return FResult::Err("Source is not a whole number of elements"),
+                Ok(_) => (),
+                Err(message) => return FResult::IOError(message),
+            }
+            checksum = checksum_update(checksum, el);
+            elements += 1;
+
+            let n = u128::from_le_bytes(buffer);
+            if n < self.bound {
+                evaluated += 1;
+                if self.verify_witness(n) {
+                    passing.push(n);
+                }
+            }
+        }
+
+        if checksum != self.checksum || elements != self.elements {
+            return FResult::Err("Checksum of source does not match certificate");
+        }
+        if evaluated != self.evaluated || passing != self.passing {
+            return FResult::Err("Composites passing the witness do not match certificate");
+        }
+        FResult::Success
+    }
+
+    fn verify_witness(&self, n: u128) -> bool {
+        match &self.witness {
+            Witness::Bases(b) => b.iter().all(|a| strong_fermat(n, *a)),
+            Witness::Table(ht) => {
+                let mut level = Some(ht);
+                while let Some(l) = level {
+                    let (dimen, multiplier, table) = l.values();
+                    let stride = table.len() / dimen;
+                    let shift = 32 - dimen.trailing_zeros();
+                    let idx = ((n as u32).wrapping_mul(multiplier).checked_shr(shift).unwrap_or(0)) as usize * stride;
+                    if !l.fixed().iter().chain(table[idx..idx + stride].iter()).all(|a| strong_fermat(n, *a)) {
+                        return false;
+                    }
+                    level = l.next();
+                }
+                true
+            }
+        }
+    }
+}
+
+// Fills the buffer unless the end of the file is reached, returning the number of bytes read
+fn read_element<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..])? {
+            0 => break,
+            k => len += k,
+        }
+    }
+    Ok(len)
+}
+
+/*
+   Modular arithmetic of the verifier, independent of the Montgomery arithmetic of Natural. Products of integers less
+   than 2^64 are computed directly, larger by doubling
+*/
+
+fn add_mod(a: u128, b: u128, n: u128) -> u128 {
+    let (sum, overflow) = a.overflowing_add(b);
+    if overflow || sum >= n {
+        sum.wrapping_sub(n)
+    } else {
+        sum
+    }
+}
+
+fn mul_mod(a: u128, b: u128, n: u128) -> u128 {
+    if n <= 1 << 64 {
+        return (a * b) % n;
+    }
+    let mut res = 0;
+    let mut a = a;
+    let mut b = b;
+    while b > 0 {
+        if b & 1 == 1 {
+            res = add_mod(res, a, n);
+        }
+        a = add_mod(a, a, n);
+        b >>= 1;
+    }
+    res
+}
+
+fn pow_mod(base: u128, exp: u128, n: u128) -> u128 {
+    let mut res = 1 % n;
+    let mut base = base % n;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            res = mul_mod(res, base, n);
+        }
+        base = mul_mod(base, base, n);
+        exp >>= 1;
+    }
+    res
+}
+
+// Strong fermat test, and the fermat test for even integers as by Natural::sprp
+fn strong_fermat(n: u128, base: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if n & 1 == 0 {
+        return pow_mod(base as u128, n - 1, n) == 1;
+    }
+    let twofactor = (n - 1).trailing_zeros();
+    let mut x = pow_mod(base as u128, (n - 1) >> twofactor, n);
+    if x == 1 || x == n - 1 {
+        return true;
+    }
+    for _ in 1..twofactor {
+        x = mul_mod(x, x, n);
+        if x == n - 1 {
+            return true;
+        }
+    }
+    false
+}
+
+// Keeps the provenance on a single line
+fn escape(x: &str) -> String {
+    x.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(x: &str) -> Option<String> {
+    let mut res = String::new();
+    let mut chars = x.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => res.push('\\'),
+            'n' => res.push('\n'),
+            'r' => res.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(res)
+}
+
+impl std::fmt::Display for Certificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |x: &mut dyn Iterator<Item = String>| x.collect::<Vec<String>>().join(",");
+        let result = if self.is_deterministic() { "deterministic" } else { "failures" };
+        writeln!(f, "certificate: {}", result)?;
+        writeln!(f, "source: {}", self.source)?;
+        writeln!(f, "checksum: {:016x}", self.checksum)?;
+        writeln!(f, "elements: {}", self.elements)?;
+        writeln!(f, "width: {}", self.width)?;
+        writeln!(f, "provenance: {}", escape(&self.provenance))?;
+        writeln!(f, "bound: {}", self.bound)?;
+        writeln!(f, "evaluated: {}", self.evaluated)?;
+        writeln!(f, "passing: {}", self.passing.len())?;
+        if !self.passing.is_empty() {
+            writeln!(f, "{}", join(&mut self.passing.iter().map(|x| x.to_string())))?;
+        }
+        match &self.witness {
+            Witness::Bases(b) => writeln!(f, "witness: bases {}", join(&mut b.iter().map(|x| x.to_string()))),
+            Witness::Table(ht) => write!(f, "witness: hashtable\n{}", ht),
+        }
+    }
+}
+
+impl Persistent for Certificate {
+    fn to_persistent(&self, locale: &str) -> FResult<()> {
+        match std::fs::write(locale, self.to_string()) {
+            Ok(_) => FResult::Success,
+            Err(message) => FResult::IOError(message),
+        }
+    }
+
+    /// Fails with an error if a field is missing or malformed
+    fn from_persistent(locale: &str) -> FResult<Self> {
+        let lines = match std::fs::read_to_string(locale) {
+            Ok(x) => x.lines().map(|l| l.to_string()).collect::<Vec<String>>(),
+            Err(message) => return FResult::IOError(message),
+        };
+        parse_certificate(&lines[..])
+    }
+}
+
+fn parse_certificate(lines: &[String]) -> FResult<Certificate> {
+    const MALFORMED: &str = "Malformed certificate";
+
+    let field = |idx: usize, key: &str| -> Option<&str> {
+        lines.get(idx)?.strip_prefix(key)?.strip_prefix(':').map(|x| x.trim())
+    };
+    let number = |idx: usize, key: &str| -> Option<u128> { field(idx, key)?.parse::<u128>().ok() };
+    let list = |x: &str| x.split(',').map(|v| v.trim().parse::<u128>().ok()).collect::<Option<Vec<u128>>>();
+
+    let parsed = (|| {
+        field(0, "certificate")?;
+        let source = field(1, "source")?.to_string();
+        let checksum = u64::from_str_radix(field(2, "checksum")?, 16).ok()?;
+        let elements = number(3, "elements")? as u64;
+        let width = number(4, "width")? as usize;
+        let provenance = unescape(field(5, "provenance")?)?;
+        let bound = number(6, "bound")?;
+        let evaluated = number(7, "evaluated")? as u64;
+        let count = number(8, "passing")? as usize;
+        let (passing, next) = if count == 0 {
+            (vec![], 9)
+        } else {
+            (list(lines.get(9)?)?, 10)
+        };
+        if passing.len() != count {
+            return None;
+        }
+        Some((source, checksum, elements, width, provenance, bound, evaluated, passing, next))
+    })();
+    let (source, checksum, elements, width, provenance, bound, evaluated, passing, next) = match parsed {
+        Some(x) => x,
+        None => return FResult::Err(MALFORMED),
+    };
+
+    let witness = match field(next, "witness") {
+        Some("hashtable") => match parse_lines(&lines[next + 1..]) {
+            FResult::Value(ht) => Witness::Table(ht),
+            failure => return failure.recast(),
+        },
+        Some(x) => match x.strip_prefix("bases").map(list) {
+            Some(Some(b)) if b.iter().all(|a| *a <= u64::MAX as u128) => {
+                Witness::Bases(b.into_iter().map(|a| a as u64).collect())
+            }
+            _ => return FResult::Err(MALFORMED),
+        },
+        None => return FResult::Err(MALFORMED),
+    };
+
+    FResult::Value(Certificate {
+        source,
+        checksum,
+        elements,
+        width,
+        provenance,
+        bound,
+        evaluated,
+        witness,
+        passing,
+    })
+}
+
+#[test]
+fn certificate_verify() {
+    use crate::io::header::BinaryWriter;
+
+    let folder = std::env::temp_dir().join(format!("f-analysis-certificate-{}", std::process::id()));
+    let _ = std::fs::create_dir_all(&folder);
+    let locale = |name: &str| folder.join(name).to_str().unwrap().to_string();
+
+    // Odd composites below 2^16, from a header with a multi-line provenance
+    let composites = (9u64..1 << 16).step_by(2).filter(|x| !x.is_prime()).collect::<Vec<u64>>();
+    let metadata = "odd composites\\below 2^16\r\nfrom trial division";
+    let mut out = BinaryWriter::<u64>::create(&locale("composites"), metadata).unwrap();
+    for i in composites.iter() {
+        out.write(*i).unwrap();
+    }
+    out.finish().unwrap();
+
+    let reload = |cert: &Certificate, name: &str| {
+        assert!(matches!(cert.to_persistent(&locale(name)), FResult::Success));
+        Certificate::from_persistent(&locale(name)).unwrap()
+    };
+
+    let cert = Certificate::new(&locale("composites"), Witness::Bases(vec![2]), 1u64 << 16).unwrap();
+    assert_eq!(cert.passing(), &[2047, 3277, 4033, 4681, 8321, 15841, 29341, 42799, 49141, 52633, 65281]);
+    let restored = reload(&cert, "bases");
+    assert_eq!(restored.provenance(), metadata);
+    assert!(matches!(restored.verify(), FResult::Success));
+
+    // Edited passing entry
+    let edited = std::fs::read_to_string(locale("bases")).unwrap().replace("2047,", "2049,");
+    std::fs::write(locale("edited"), edited).unwrap();
+    let edited = Certificate::from_persistent(&locale("edited")).unwrap();
+    assert!(matches!(edited.verify(), FResult::Err("Composites passing the witness do not match certificate")));
+
+    // Multi-level table with a fixed base and a stride of 2, verified by the independent arithmetic
+    let mut ht = HashTable::new((0..16).map(|i| 3 + i * 2).collect(), 8, 1234567);
+    ht.set_fixed(vec![2]);
+    ht.set_next(HashTable::new(vec![5, 7, 11, 13], 4, 7654321));
+    let witness = Witness::Table(ht.clone());
+    let cert = Certificate::new(&locale("composites"), witness, 1u64 << 16).unwrap();
+    let wide = [(1u128 << 89) - 1, 3825123056546413051, 318665857834031151167461];
+    for n in composites.iter().map(|x| *x as u128).chain(wide) {
+        assert_eq!(cert.verify_witness(n), ht.primality(n), "{}", n);
+    }
+    assert!(matches!(reload(&cert, "table").verify(), FResult::Success));
+
+    // Flipped payload byte
+    let mut bytes = std::fs::read(locale("composites")).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    std::fs::write(locale("composites"), bytes).unwrap();
+    assert!(matches!(restored.verify(), FResult::Err("Checksum of source does not match certificate")));
+
+    let _ = std::fs::remove_dir_all(&folder);
+}
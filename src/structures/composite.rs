@@ -1,7 +1,7 @@
 pub(crate) mod compstruct;
 pub(crate) mod constructor;
 mod corefunc;
-mod file;
+pub(crate) mod file;
 mod search;
 mod vector;
 
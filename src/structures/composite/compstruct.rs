@@ -47,6 +47,12 @@ use crate::Natural;
 ///    the new calculated CompVectors
 ///
 /// 8. Some functions, particularly the filter_generic functions are able to map to CFile directly without loading the entire CFile to memory.
+///    The file is streamed in chunks of at most memory_max/2 bytes, each evaluated in parallel, so a CFile larger than RAM can be
+///    filtered into another file. See filter_streaming, filter_bvector_stream and filter_hashtable_stream.
+///
+/// 9. Binary files are written with a header (see FileHeader) recording the element length, count, whether the elements are sorted,
+///    how they were generated and a checksum. from_file rejects files written for a different integer type or whose checksum fails,
+///    legacy headerless files are read as raw little-endian integers.
 pub struct CompVector<T: Natural> {
     // File if used
     pub(crate) file: Option<std::fs::File>,
@@ -4,6 +4,7 @@ use crate::Natural;
 
 use crate::enums::{Search, AUTO_FLAG, MEMORY_MAX, UTF8_FLAG};
 use crate::filter::{Coprime, GenericFilter, StrongFermat};
+use crate::io::header::{seek_payload, BinaryWriter, FileHeader};
 use crate::io::write::format_block;
 use crate::search::{
     binary_det_iter_st, binary_evo_par, binary_evo_st, binary_evo_st_rand_partial, exhaustive_par,
@@ -181,11 +182,30 @@ impl<T: Natural> CompVector<T> {
     // FIXME only supports binary files
     pub fn len(&self) -> usize {
         match &self.file {
-            Some(x) => (x.metadata().unwrap().len() as usize) / T::BYTE_LENGTH,
+            Some(x) => match FileHeader::read(x) {
+                Ok(Some(h)) => h.count as usize,
+                _ => (x.metadata().unwrap().len() as usize) / T::BYTE_LENGTH,
+            },
             None => self.elements.len(),
         }
     }
 
+    /// Header of the binary file
+    /// # FileDNE
+    /// CompVector is not stored in a file
+    /// # NotSupported
+    /// File is utf-8 or a legacy headerless binary file
+    pub fn header(&self) -> FResult<FileHeader> {
+        match &self.file {
+            Some(x) => match FileHeader::read(x) {
+                Ok(Some(h)) => FResult::Value(h),
+                Ok(None) => FResult::NotSupported,
+                Err(message) => FResult::IOError(message),
+            },
+            None => FResult::FileDNE,
+        }
+    }
+
     pub fn push(&mut self, el: T) {
         self.elements.push(el);
     }
@@ -223,19 +243,40 @@ impl<T: Natural> CompVector<T> {
     }
 
     /// Initialises from file
+    ///
+    /// Binary files with a header are checked against the element length of T and the checksum,
+    /// legacy headerless files are accepted without verification
+    /// # Err
+    /// Element length of the file does not match T, or the checksum does not match the elements
     pub fn from_file(filename: &str) -> FResult<Self> {
         match std::fs::OpenOptions::new()
             .read(true)
             .write(true)
             .open(filename)
         {
-            Ok(x) => FResult::Value(Self {
-                file: Some(x),
-                elements: Vec::<T>::new(),
-                memory_max: MEMORY_MAX,
-                utf8_flag: UTF8_FLAG,
-                auto_flag: AUTO_FLAG,
-            }),
+            Ok(x) => {
+                match FileHeader::read(&x) {
+                    Ok(Some(h)) => {
+                        if h.byte_length as usize != T::BYTE_LENGTH {
+                            return FResult::Err("Element length of file does not match type");
+                        }
+                        match h.verify(&x) {
+                            Ok(true) => (),
+                            Ok(false) => return FResult::Err("Checksum does not match file contents"),
+                            Err(message) => return FResult::IOError(message),
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(message) => return FResult::IOError(message),
+                }
+                FResult::Value(Self {
+                    file: Some(x),
+                    elements: Vec::<T>::new(),
+                    memory_max: MEMORY_MAX,
+                    utf8_flag: UTF8_FLAG,
+                    auto_flag: AUTO_FLAG,
+                })
+            }
             Err(file_error) => FResult::IOError(file_error),
         }
     }
@@ -282,27 +323,50 @@ impl<T: Natural> CompVector<T> {
     /// Writes to file returning a CompVector handling files   
     // FIXME Map file to file
     pub fn to_file(&self, filename: &str) -> FResult<Self> {
-        match std::fs::File::create(filename) {
+        self.to_file_with_metadata(filename, "")
+    }
+
+    /// Writes to file, recording metadata (e.g the generating base set or interval) in the header of binary files.
+    /// utf-8 files have no header and the metadata is discarded
+    pub fn to_file_with_metadata(&self, filename: &str, metadata: &str) -> FResult<Self> {
+        if !self.utf8_flag {
+            let mut wrtr = match BinaryWriter::<T>::create(filename, metadata) {
+                Ok(w) => w,
+                Err(message) => return FResult::IOError(message),
+            };
+            for i in self.elements.iter() {
+                match wrtr.write(*i) {
+                    Ok(_) => (),
+                    Err(message) => return FResult::IOError(message),
+                }
+            }
+            return match wrtr.finish() {
+                Ok(x) => FResult::Value(Self::from_file_internal(
+                    x,
+                    self.memory_max,
+                    self.utf8_flag,
+                    self.auto_flag,
+                )),
+                Err(message) => FResult::IOError(message),
+            };
+        }
+
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(filename)
+        {
             Ok(x) => {
                 let mut wrtr = std::io::BufWriter::new(x.try_clone().unwrap());
 
-                if self.utf8_flag == true {
-                    for i in self.elements.iter() {
-                        let out_str = i.to_string() + "\n";
-
-                        match wrtr.write(out_str.as_bytes()) {
-                            Ok(_) => (),
-                            Err(message) => return FResult::IOError(message),
-                        }
-                    }
-                }
+                for i in self.elements.iter() {
+                    let out_str = i.to_string() + "\n";
 
-                if self.utf8_flag == false {
-                    for i in self.elements.iter() {
-                        match wrtr.write(&i.to_bytes()[..]) {
-                            Ok(_) => (),
-                            Err(message) => return FResult::IOError(message),
-                        }
+                    match wrtr.write_all(out_str.as_bytes()) {
+                        Ok(_) => (),
+                        Err(message) => return FResult::IOError(message),
                     }
                 }
 
@@ -334,6 +398,9 @@ impl<T: Natural> CompVector<T> {
                     FResult::MemoryExceeded(mem) => return FResult::MemoryExceeded(mem),
                     _ => (),
                 }
+                if let Err(message) = seek_payload(filey) {
+                    return FResult::IOError(message);
+                }
                 // FIXME handle unwrapping
                 let mut r = std::io::BufReader::new(filey.try_clone().unwrap());
 
@@ -401,10 +468,10 @@ impl<T: Natural> CompVector<T> {
         use std::io::prelude::*;
         use std::io::SeekFrom;
 
-        output.seek(SeekFrom::Start(0));
-        let mut wrtr = std::io::BufWriter::new(output);
-
         if self.utf8_flag {
+            output.rewind();
+            let mut wrtr = std::io::BufWriter::new(output);
+
             for i in self.elements.iter() {
                 let x = i.to_string() + "\n";
 
@@ -412,10 +479,16 @@ impl<T: Natural> CompVector<T> {
             }
             wrtr.flush();
         } else if self.utf8_flag == false {
+            // Preserve the provenance of the file, the remaining fields are recomputed
+            let metadata = match FileHeader::read(output) {
+                Ok(Some(h)) => h.metadata,
+                _ => String::new(),
+            };
+            let mut wrtr = BinaryWriter::<T>::from_file(output.try_clone().unwrap(), &metadata).unwrap();
             for i in self.elements.iter() {
-                wrtr.write(&i.to_bytes()[..]).unwrap();
+                wrtr.write(*i).unwrap();
             }
-            wrtr.flush();
+            wrtr.finish().unwrap();
         }
     }
 
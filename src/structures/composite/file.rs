@@ -1,100 +1,62 @@
 use crate::filter::*;
-use crate::Natural;
+use crate::io::header::seek_payload;
+use crate::search::thread_count;
+use crate::{FResult, Natural};
 use std::io::BufRead;
 use std::io::Read;
-use std::io::Write;
+use std::sync::Arc;
 
-pub(crate) fn filter_generic<T: Natural, F: GenericFilter>(
-    file: std::fs::File,
-    utf8_flag: bool,
-    filter_flag: bool,
-) -> Vec<T> {
-
-
-    let mut file_in = std::io::BufReader::new(file.try_clone().unwrap());
-    let mut k = Vec::<T>::new();
-     // FIXME propagate error
-    if utf8_flag {
-        for i in file_in.lines() {
-          match i{
-            Ok(validline) => {
-               match T::from_str(&validline){
-                 Ok(val) => {
-                  if F::filter_check(val) == filter_flag{
-                    k.push(val);
-                    }
-                 }
-                 Err(_) => (),
-               }
-            } 
-            Err(_) => (),
-          }
-        }
-    } else {
-        let mut interim = vec![0u8; T::BYTE_LENGTH];
-
-        loop {
-            let totalbytes = file_in.read(&mut interim[..]).unwrap();
-
-            if totalbytes == 0usize {
-                break;
-            }
-
-            let val = T::from_bytes(&interim);
-
-            if F::filter_check(val) == filter_flag {
-                k.push(val);
-            }
-        }
-    }
-    k
-}
+// Predicate evaluated over each element when streaming
+pub(crate) type Predicate<T> = Arc<dyn Fn(T) -> bool + Send + Sync>;
 
 // Parallelised file read and filter for non-UTF-8 values
 pub(crate) fn filter_generic_par<T: Natural, F: GenericFilter>(
     file: std::fs::File,
     filter_flag: bool,
-) -> Vec<T> {
-    let read_eval = |mut f: std::fs::File, start: u64, stride: u64, flag: bool| -> Vec<T> {
+) -> FResult<Vec<T>> {
+    let offset = match seek_payload(&file) {
+        Ok(x) => x,
+        Err(message) => return FResult::IOError(message),
+    };
+
+    let read_eval = move |mut f: std::fs::File, start: u64, stride: u64, flag: bool| -> std::io::Result<Vec<T>> {
         use std::io::Seek;
-        let point = std::io::SeekFrom::Start(start * (T::BYTE_LENGTH as u64));
-        f.seek(point);
-        println!("Sought to {}", start * (T::BYTE_LENGTH as u64));
+        f.seek(std::io::SeekFrom::Start(offset + start * (T::BYTE_LENGTH as u64)))?;
         let mut res = vec![];
 
         let mut interim = vec![0u8; T::BYTE_LENGTH];
 
-        for i in 0..stride {
-            let totalbytes = f.read(&mut interim[..]).unwrap();
+        for _ in 0..stride {
+            let totalbytes = f.read(&mut interim[..])?;
 
             if totalbytes == 0usize {
                 break; // possibly return Error as you should never get this
             }
-            //             println!("{:?} ", interim);
 
             let val = T::from_bytes(&interim);
-            //              println!("{}",val);
             if F::filter_check(val) == flag {
                 res.push(val);
             }
         }
-        res
+        Ok(res)
     };
 
-    let el_count = ((file.metadata().unwrap().len() as usize) / T::BYTE_LENGTH) as u64;
+    let el_count = match file.metadata() {
+        Ok(x) => (x.len() - offset) / (T::BYTE_LENGTH as u64),
+        Err(message) => return FResult::IOError(message),
+    };
     let tc = crate::search::thread_count() as u64;
-    let stride = (el_count / tc);
+    let stride = el_count / tc;
 
-    println!("elements {} threads {} stride {} ", el_count, tc, stride);
-    let mut thread_vec: Vec<std::thread::JoinHandle<Vec<T>>> = Vec::new();
+    let mut thread_vec: Vec<std::thread::JoinHandle<std::io::Result<Vec<T>>>> = Vec::new();
 
     for i in 0..tc - 1 {
         let start = i * stride;
-        let f = file.try_clone().unwrap();
-        let inner_flag = filter_flag.clone();
-        thread_vec.push(std::thread::spawn(move || {
-            read_eval(f, start, stride.clone(), inner_flag)
-        }));
+        let f = match file.try_clone() {
+            Ok(x) => x,
+            Err(message) => return FResult::IOError(message),
+        };
+        thread_vec.push(std::thread::spawn(move || read_eval(f, start, stride, filter_flag)));
     }
 
     thread_vec.push(std::thread::spawn(move || {
@@ -109,97 +71,117 @@ pub(crate) fn filter_generic_par<T: Natural, F: GenericFilter>(
     let mut total = vec![];
 
     for handle in thread_vec {
-        total.extend_from_slice(&handle.join().unwrap()[..]);
+        match handle.join() {
+            Ok(Ok(x)) => total.extend_from_slice(&x[..]),
+            Ok(Err(message)) => return FResult::IOError(message),
+            Err(_) => return FResult::Critical,
+        }
     }
-    total
+    FResult::Value(total)
 }
 
-pub(crate) fn filter_generic_file<T: Natural, F: GenericFilter>(
-    file: std::fs::File,
-    out: std::fs::File,
-    utf8_flag: bool,
-    filter_flag: bool,
-) {
-    let mut file_in = std::io::BufReader::new(file.try_clone().unwrap());
-    let mut file_out = std::io::BufWriter::new(file.try_clone().unwrap());
-
-   if utf8_flag {
-        for i in file_in.lines() {
-          match i{
-            Ok(validline) => {
-               match T::from_str(&validline){
-                 Ok(val) => {
-                  if F::filter_check(val) == filter_flag{
-                   file_out.write(&val.to_bytes()[..]).unwrap();
-                    }
-                 }
-                 Err(_) => (),
-               }
-            } 
-            Err(_) => (),
-          }
-        }
-    } else {
-        let mut interim = vec![0u8; T::BYTE_LENGTH];
+/*
+   In: A chunk of elements and a predicate
+   Out: Elements that satisfy the predicate, in the same order
+*/
+pub(crate) fn filter_chunk_par<T: Natural>(chunk: Vec<T>, pred: &Predicate<T>) -> Vec<T> {
+    let tc = thread_count();
+    let len = chunk.len();
 
-        loop {
-            let totalbytes = file_in.read(&mut interim[..]).unwrap();
+    if tc == 1 || len < 1000 {
+        return chunk.into_iter().filter(|x| pred(*x)).collect::<Vec<T>>();
+    }
 
-            if totalbytes == 0usize {
-                break;
+    let stride = len.div_ceil(tc);
+    let c_arc = Arc::new(chunk);
+    let mut threads = Vec::new();
+
+    for i in 0..tc {
+        let start = (i * stride).min(len);
+        let stop = ((i + 1) * stride).min(len);
+        let ce_i = Arc::clone(&c_arc);
+        let p_i = Arc::clone(pred);
+
+        threads.push(std::thread::spawn(move || {
+            let mut res = vec![];
+            for j in ce_i[start..stop].iter() {
+                if p_i(*j) {
+                    res.push(*j)
+                }
             }
+            res
+        }));
+    }
 
-            let val = T::from_bytes(&interim);
-
-            if F::filter_check(val) == filter_flag {
-                file_out.write(&val.to_bytes()[..]).unwrap();
-            }
-        }
+    let mut total = vec![];
+    for handle in threads {
+        total.extend_from_slice(&handle.join().unwrap()[..]);
     }
-    //k
+    total
 }
 
-pub(crate) fn filter_strong<T: Natural>(
-    file: std::fs::File,
+// Reads at most chunk_len elements, returning an empty vector at the end of the file
+fn read_chunk<T: Natural, R: BufRead>(
+    r: &mut R,
     utf8_flag: bool,
-    filter_flag: bool,
-    base: T,
-) -> Vec<T> {
-    let mut file_in = std::io::BufReader::new(file.try_clone().unwrap());
-    let mut k = Vec::<T>::new();
-      if utf8_flag {
-        for i in file_in.lines() {
-          match i{
-            Ok(validline) => {
-               match T::from_str(&validline){
-                 Ok(val) => {
-                  if val.sprp(base) == filter_flag{
-                    k.push(val);
-                    }
-                 }
-                 Err(_) => (),
-               }
-            } 
-            Err(_) => (),
-          }
-        }
+    chunk_len: usize,
+) -> std::io::Result<Vec<T>> {
+    let mut res = Vec::new();
 
+    if utf8_flag {
+        let mut line = String::new();
+        while res.len() < chunk_len {
+            line.clear();
+            if r.read_line(&mut line)? == 0 {
+                break;
+            }
+            // Lines that are not integers are skipped, as in load_to_memory
+            if let Ok(val) = T::from_str(line.trim()) {
+                res.push(val);
+            }
+        }
     } else {
         let mut interim = vec![0u8; T::BYTE_LENGTH];
-
-        loop {
-            let totalbytes = file_in.read(&mut interim[..]).unwrap();
-
-            if totalbytes == 0usize {
-                break;
+        while res.len() < chunk_len {
+            match r.read_exact(&mut interim[..]) {
+                Ok(()) => res.push(T::from_bytes(&interim)),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
             }
+        }
+    }
+    Ok(res)
+}
 
-            let val = T::from_bytes(&interim);
-
-            if val.sprp(base) == filter_flag {
-                k.push(val);
-            }
+/// Streams the file in chunks of at most chunk_len elements, passing each chunk to sink in file order
+pub(crate) fn read_chunked<T: Natural>(
+    file: std::fs::File,
+    utf8_flag: bool,
+    chunk_len: usize,
+    sink: &mut dyn FnMut(Vec<T>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    // Skip the header, if any
+    seek_payload(&file)?;
+    let mut file_in = std::io::BufReader::with_capacity(1 << 20, file);
+
+    loop {
+        let chunk = read_chunk::<T, _>(&mut file_in, utf8_flag, chunk_len)?;
+        if chunk.is_empty() {
+            break;
         }
+        sink(chunk)?;
     }
-    k
+    Ok(())
+}
+
+/// Streams the file in chunks of at most chunk_len elements, each chunk is evaluated in parallel and the elements
+/// satisfying the predicate are passed to sink in file order. Only one chunk and its result are held in memory at a time
+pub(crate) fn filter_chunked<T: Natural>(
+    file: std::fs::File,
+    utf8_flag: bool,
+    chunk_len: usize,
+    pred: &Predicate<T>,
+    sink: &mut dyn FnMut(Vec<T>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    read_chunked(file, utf8_flag, chunk_len, &mut |chunk: Vec<T>| sink(filter_chunk_par(chunk, pred)))
 }
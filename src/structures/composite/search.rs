@@ -4,76 +4,169 @@ use crate::structures::{BaseSeq, CompVector, Point, Primes};
 use crate::Natural;
 
 use crate::enums::{Search, AUTO_FLAG, MEMORY_MAX, UTF8_FLAG};
-use crate::filter::{Coprime, GenericFilter, StrongFermat};
+use crate::filter::{Coprime, FilterExpr, GenericFilter, Lucas, StrongFermat};
+use crate::io::header::BinaryWriter;
+use std::sync::Arc;
 use crate::search::{
-    binary_det_iter_st, binary_evo_par, binary_evo_st, binary_evo_st_rand_partial,
-    exhaustive_list_par, exhaustive_list_st, exhaustive_par, exhaustive_rand_par, hash_search,
+    anneal_par, binary_det_iter_st, binary_evo_par, evo_base_par, binary_evo_st, binary_evo_st_rand_partial,
+    exhaustive_list_par, exhaustive_list_st, exhaustive_par, exhaustive_rand_par, hash_search, multi_ht_par,
     strip_pseudo_par, strip_pseudo_st, unary_ht_par, unary_strongest_par, unary_strongest_rand_par,
     unary_strongest_st,
 };
 use crate::structures::composite::{file::*, vector::*};
-use crate::FResult;
+use crate::{AnnealParam, EvoParam, FResult};
 use crate::HashTable;
 use std::io::{BufRead, Read, Write};
 
 impl<T: Natural> CompVector<T> {
-    //
-    pub(crate) fn filter_generic_internal<F: GenericFilter>(
+    // Number of elements read per chunk when streaming a file, so that a chunk and its result fit within memory_max
+    pub(crate) fn chunk_length(&self) -> usize {
+        ((self.memory_max / 2) as usize / T::BYTE_LENGTH).max(1)
+    }
+
+    // Applies the predicate, streaming files in chunks and writing to fileout if provided
+    pub(crate) fn filter_streaming_internal(
         &self,
+        pred: Predicate<T>,
         fileout: Option<&str>,
-        filter_flag: bool,
     ) -> FResult<Self> {
-        let mut pseudos: Vec<T> = Vec::new();
-
         match fileout {
             Some(x) => {
-                match std::fs::File::create(x) {
-                    Ok(mut output) => {
-                        if self.elements.len() > 0 {
-                            let pseudos = filter_generic_v::<T, F>(&self.elements, filter_flag);
-                            // FIXME catch write error
-                            for i in pseudos {
-                                output.write(&i.to_bytes()[..]).unwrap();
-                            }
-                        } else {
-                            filter_generic_file::<T, F>(
-                                self.file.as_ref().unwrap().try_clone().unwrap(),
-                                output.try_clone().unwrap(),
-                                self.utf8_flag,
-                                true,
-                            );
-                        }
-                        return FResult::Value(Self::from_file_internal(
-                            output.try_clone().unwrap(),
-                            self.memory_max,
-                            self.utf8_flag,
-                            self.auto_flag,
-                        ));
+                // The result inherits the provenance of the original
+                let metadata = match self.header() {
+                    FResult::Value(h) => h.metadata,
+                    _ => String::new(),
+                };
+
+                let mut wrtr = match BinaryWriter::<T>::create(x, &metadata) {
+                    Ok(w) => w,
+                    Err(message) => return FResult::IOError(message),
+                };
+
+                let mut sink = |chunk: Vec<T>| -> std::io::Result<()> {
+                    for i in chunk {
+                        wrtr.write(i)?;
                     }
+                    Ok(())
+                };
+
+                let res = match &self.file {
+                    Some(f) => match f.try_clone() {
+                        Ok(f) => filter_chunked(f, self.utf8_flag, self.chunk_length(), &pred, &mut sink),
+                        Err(message) => Err(message),
+                    },
+                    None => sink(filter_chunk_par(self.elements.clone(), &pred)),
+                };
+
+                if let Err(message) = res {
+                    return FResult::IOError(message);
+                }
 
-                    Err(file_error) => FResult::IOError(file_error),
+                match wrtr.finish() {
+                    // Output is always binary
+                    Ok(output) => FResult::Value(Self::from_file_internal(
+                        output,
+                        self.memory_max,
+                        false,
+                        self.auto_flag,
+                    )),
+                    Err(message) => FResult::IOError(message),
                 }
             }
             None => {
-                let mut pseudos: Vec<T> = Vec::new();
-                if self.elements.len() > 0 {
-                    pseudos = filter_generic_v::<T, F>(&self.elements, filter_flag);
-                } else {
-                    let infile = self.file.as_ref().unwrap().try_clone().unwrap();
-                    pseudos = filter_generic::<T, F>(infile, self.utf8_flag, true);
-                    //       pseudos = filter_generic_par::<T,F>(infile, true);
-                    //      println!("Parallel branch");
-                }
-                return FResult::Value(Self::from_vector_internal(
+                let pseudos = match &self.file {
+                    Some(f) => {
+                        let mut pseudos: Vec<T> = Vec::new();
+                        // The chunk being evaluated is counted against memory_max along with the results
+                        let bound = (self.memory_max as usize / T::BYTE_LENGTH).saturating_sub(self.chunk_length());
+
+                        let mut sink = |chunk: Vec<T>| -> std::io::Result<()> {
+                            pseudos.extend_from_slice(&chunk[..]);
+                            if pseudos.len() > bound {
+                                return Err(std::io::Error::from(std::io::ErrorKind::OutOfMemory));
+                            }
+                            Ok(())
+                        };
+
+                        let f = match f.try_clone() {
+                            Ok(x) => x,
+                            Err(message) => return FResult::IOError(message),
+                        };
+                        match filter_chunked(
+                            f,
+                            self.utf8_flag,
+                            self.chunk_length(),
+                            &pred,
+                            &mut sink,
+                        ) {
+                            Ok(()) => (),
+                            Err(message) if message.kind() == std::io::ErrorKind::OutOfMemory => {
+                                return FResult::MemoryExceeded(pseudos.len() * T::BYTE_LENGTH)
+                            }
+                            Err(message) => return FResult::IOError(message),
+                        }
+                        pseudos
+                    }
+                    None => filter_chunk_par(self.elements.clone(), &pred),
+                };
+
+                FResult::Value(Self::from_vector_internal(
                     pseudos,
                     self.memory_max,
                     self.utf8_flag,
                     self.auto_flag,
-                ));
+                ))
             }
         }
     }
 
+    // Passes the elements to sink in order, files are streamed in chunks as by filter_streaming
+    pub(crate) fn for_each_chunk(&self, sink: &mut dyn FnMut(Vec<T>) -> std::io::Result<()>) -> FResult<()> {
+        let res = match &self.file {
+            Some(f) => match f.try_clone() {
+                Ok(f) => read_chunked(f, self.utf8_flag, self.chunk_length(), sink),
+                Err(message) => Err(message),
+            },
+            None => sink(self.elements.clone()),
+        };
+        match res {
+            Ok(()) => FResult::Success,
+            Err(message) => FResult::IOError(message),
+        }
+    }
+
+    pub(crate) fn filter_generic_internal<F: GenericFilter>(
+        &self,
+        fileout: Option<&str>,
+        filter_flag: bool,
+    ) -> FResult<Self> {
+        if self.file.is_none() && fileout.is_none() {
+            let pseudos = filter_generic_v::<T, F>(&self.elements, filter_flag);
+            return FResult::Value(Self::from_vector_internal(
+                pseudos,
+                self.memory_max,
+                self.utf8_flag,
+                self.auto_flag,
+            ));
+        }
+        let check: fn(T) -> bool = F::filter_check::<T>;
+        self.filter_streaming_internal(Arc::new(move |x: T| check(x) == filter_flag), fileout)
+    }
+
+    /// Filters by an arbitrary predicate, keeping the composites for which it is true.
+    ///
+    /// Files are processed in chunks of at most memory_max/2 bytes evaluated in parallel, so files larger than memory
+    /// can be filtered if the result is written to a file.
+    /// # MemoryExceeded
+    /// filename is None and the result does not fit within the half of memory_max not used by the chunk
+    pub fn filter_streaming<P: Fn(T) -> bool + Send + Sync + 'static>(
+        &self,
+        pred: P,
+        filename: Option<&str>,
+    ) -> FResult<Self> {
+        self.filter_streaming_internal(Arc::new(pred), filename)
+    }
+
     /// Applies a filter that implements the GenericFilter trait
     pub fn filter_generic<F: GenericFilter>(&self, filename: Option<&str>) -> FResult<Self> {
         self.filter_generic_internal::<F>(filename, true)
@@ -92,6 +185,15 @@ impl<T: Natural> CompVector<T> {
         self.filter_generic_internal::<F>(filename, false)
     }
 
+    /// Filters all composites that fail a Lucas probable prime test
+    pub fn filter_lucas<F: Lucas>(&self, filename: Option<&str>) -> FResult<Self> {
+        self.filter_generic_internal::<F>(filename, true)
+    }
+
+    pub fn nfilter_lucas<F: Lucas>(&self, filename: Option<&str>) -> FResult<Self> {
+        self.filter_generic_internal::<F>(filename, false)
+    }
+
     pub fn filter_coprime<F: Coprime>(&self, filename: Option<&str>) -> FResult<Self> {
         self.filter_generic_internal::<F>(filename, true)
     }
@@ -100,6 +202,19 @@ impl<T: Natural> CompVector<T> {
         self.filter_generic_internal::<F>(filename, false)
     }
 
+    /// Filters all composites that fail a filter expression constructed at runtime
+    /// # Usage
+    /// This is the run-time equivalent to filter_generic
+    pub fn filter_expr(&self, expr: &FilterExpr<T>, filename: Option<&str>) -> FResult<Self> {
+        let fexpr = expr.clone();
+        self.filter_streaming(move |x: T| fexpr.check(x), filename)
+    }
+
+    pub fn nfilter_expr(&self, expr: &FilterExpr<T>, filename: Option<&str>) -> FResult<Self> {
+        let fexpr = expr.clone();
+        self.filter_streaming(move |x: T| !fexpr.check(x), filename)
+    }
+
     pub fn filter_range(&self, inf: T, sup: T) -> FResult<Self> {
         match &self.file {
             &None => {
@@ -136,7 +251,8 @@ impl<T: Natural> CompVector<T> {
         let dim = if let Some(dm) = dimen {
             dm
         } else {
-            (self.elements.len() / 150).next_power_of_two()
+            // The hash shift requires at least two buckets
+            (self.elements.len() / 150).next_power_of_two().max(2)
         };
 
         // If multiplier defined use it, otherwise calculate it
@@ -160,6 +276,100 @@ impl<T: Natural> CompVector<T> {
         }
     }
 
+    /// Constructs a hashtable testing each of the fixed bases and then stride bases from the bucket, such as is
+    /// required for 128-bit integers. The composites passing the fixed bases are distributed over the buckets, and
+    /// the bases of each bucket are selected successively from the strongest of the first bases within the bound
+    /// until a single base eliminates the remainder. Defaults are the same as to_hashtable
+    /// # NotSupported
+    /// The dimension is not a power of two of at least 2
+    pub fn to_hashtable_multi(
+        &self,
+        fixed: &[u64],
+        dimen: Option<usize>,
+        stride: usize,
+        multiplier: Option<u32>,
+        bound: Option<u64>,
+    ) -> FResult<HashTable> {
+        if dimen.is_some_and(|d| d < 2 || !d.is_power_of_two()) {
+            return FResult::NotSupported;
+        }
+        self.load_eval(&|x: Self| {
+            let survivors = x
+                .elements
+                .into_iter()
+                .filter(|c| fixed.iter().all(|b| c.sprp(T::from(*b))))
+                .collect::<Vec<T>>();
+
+            // The hash shift requires at least two buckets
+            let dim = dimen.unwrap_or((survivors.len() / 150).next_power_of_two().max(2));
+
+            let mul = match multiplier {
+                Some(mx) => mx,
+                None => {
+                    let max_iter = (self.memory_max as usize / (4 * dim)) as f64;
+                    let iterations = (max_iter * ((survivors.len() as f64).log2().max(1.0).recip())).ceil() as usize;
+                    hash_search(&survivors[..], dim, iterations)
+                }
+            };
+
+            match multi_ht_par(survivors, dim, stride, mul, bound.unwrap_or(65535), true) {
+                FResult::Value(table) => {
+                    let mut ht = HashTable::new(table, dim, mul);
+                    ht.set_fixed(fixed.to_vec());
+                    FResult::Value(ht)
+                }
+                failure => failure.recast(),
+            }
+        })
+    }
+
+    /// Constructs a multi-level hashtable, each level hashing the composites that pass the fixed bases and the
+    /// levels before it with its own multiplier, for each of the dimensions given. Levels other than the last take
+    /// the strongest stride bases of each bucket, the last must eliminate every remaining composite
+    /// # NotSupported
+    /// No dimensions are given, or a dimension is not a power of two of at least 2
+    pub fn to_hashtable_levels(
+        &self,
+        fixed: &[u64],
+        dimens: &[usize],
+        stride: usize,
+        bound: Option<u64>,
+    ) -> FResult<HashTable> {
+        if dimens.is_empty() || dimens.iter().any(|d| *d < 2 || !d.is_power_of_two()) {
+            return FResult::NotSupported;
+        }
+        self.load_eval(&|x: Self| {
+            let mut survivors = x
+                .elements
+                .into_iter()
+                .filter(|c| fixed.iter().all(|b| c.sprp(T::from(*b))))
+                .collect::<Vec<T>>();
+
+            let mut levels = vec![];
+            for (depth, dim) in dimens.iter().enumerate() {
+                // Empty levels still require a multiplier that distributes the primes
+                let max_iter = (self.memory_max as usize / (4 * dim)) as f64;
+                let iterations = (max_iter * ((survivors.len() as f64).log2().max(1.0).recip())).ceil() as usize;
+                let mul = hash_search(&survivors[..], *dim, iterations.max(1));
+                let complete = depth == dimens.len() - 1;
+                let level = match multi_ht_par(survivors.clone(), *dim, stride, mul, bound.unwrap_or(65535), complete) {
+                    FResult::Value(table) => HashTable::new(table, *dim, mul),
+                    failure => return failure.recast(),
+                };
+                survivors.retain(|c| level.primality(*c));
+                levels.push(level);
+            }
+
+            let mut iter = levels.into_iter();
+            let mut ht = iter.next().unwrap();
+            ht.set_fixed(fixed.to_vec());
+            for level in iter {
+                ht.set_next(level);
+            }
+            FResult::Value(ht)
+        })
+    }
+
     pub fn compute_hashtable(
         &self,
         dimen: Option<usize>,
@@ -179,7 +389,7 @@ impl<T: Natural> CompVector<T> {
                 }
 
                 None => {
-                    let mut dm = (self.elements.len() / 600).next_power_of_two();
+                    let mut dm = (self.elements.len() / 600).next_power_of_two().max(2);
 
                     loop {
                         match self.to_hashtable(Some(dm), multiplier, bound) {
@@ -199,51 +409,82 @@ impl<T: Natural> CompVector<T> {
         }) // end closure
     } // end function
 
-    /// Filters the composites using a BaseSeq
-    pub fn filter_bvector(&self, fil: &BaseSeq<T>) -> Self {
+    /// Constructs a hashtable by simulated annealing of the multiplier and bases, see AnnealParam for the parameters.
+    /// Unlike compute_hashtable the dimension is fixed, so smaller tables can be attempted
+    /// # Partial
+    /// No table eliminating every composite was found, returns the best table and the number of composites passing it
+    pub fn anneal_hashtable(&self, param: &AnnealParam) -> FResult<HashTable> {
+        self.load_eval(&|x: Self| match anneal_par(x.elements, param, None) {
+            FResult::Value((ht, 0)) => FResult::Value(ht),
+            FResult::Value((ht, count)) => FResult::Partial(ht, count as usize),
+            failure => failure.recast(),
+        })
+    }
+
+    /// Filters the composites using a BaseSeq, files are streamed see filter_streaming
+    /// # MemoryExceeded
+    /// The composites are in a file and the result does not fit within memory_max
+    pub fn filter_bvector(&self, fil: &BaseSeq<T>) -> FResult<Self> {
+        if self.file.is_some() {
+            return self.filter_bvector_stream(fil, None);
+        }
         let mut ce = self.clone();
 
         for i in fil.iter() {
-            ce = ce.filter_sprp_rt(*i);
+            ce = ce.filter_sprp_loaded(*i);
         }
-        ce
+        FResult::Value(ce)
+    }
+
+    /// Filters the composites using a BaseSeq in a single streaming pass, see filter_streaming
+    pub fn filter_bvector_stream(&self, fil: &BaseSeq<T>, filename: Option<&str>) -> FResult<Self> {
+        let bases = fil.clone();
+        self.filter_streaming(move |x: T| bases.primality(x), filename)
     }
 
-    /// Filters the composites using a Hashtable
-    pub fn filter_hashtable(&self, ht: &HashTable) -> Self {
+    /// Filters the composites using a Hashtable, files are streamed see filter_streaming
+    /// # MemoryExceeded
+    /// The composites are in a file and the result does not fit within memory_max
+    pub fn filter_hashtable(&self, ht: &HashTable) -> FResult<Self> {
+        if self.file.is_some() {
+            return self.filter_hashtable_stream(ht, None);
+        }
         let mut veccy = vec![];
-        for i in self.iter().unwrap() {
+        for i in self.elements.iter() {
             if ht.primality(*i) {
                 veccy.push(*i);
             }
         }
-        Self::from_vector_internal(veccy, self.memory_max, self.utf8_flag, self.auto_flag)
+        FResult::Value(Self::from_vector_internal(veccy, self.memory_max, self.utf8_flag, self.auto_flag))
+    }
+
+    /// Filters the composites using a Hashtable in a single streaming pass, see filter_streaming
+    pub fn filter_hashtable_stream(&self, ht: &HashTable, filename: Option<&str>) -> FResult<Self> {
+        let table = ht.clone();
+        self.filter_streaming(move |x: T| table.primality(x), filename)
     }
 
     /// Filter by a selected base, collecting all composites that pass
     /// # Usage
     /// This is the run-time equivalent to filter_sprp
-    pub fn filter_sprp_rt(&self, base: T) -> Self {
+    /// # MemoryExceeded
+    /// The composites are in a file and the result does not fit within memory_max
+    pub fn filter_sprp_rt(&self, base: T) -> FResult<Self> {
         match self.file {
-            Some(_) => {
-                let ce = filter_strong::<T>(
-                    self.file.as_ref().unwrap().try_clone().unwrap(),
-                    self.utf8_flag,
-                    true,
-                    base,
-                );
-                Self::from_vector_internal(ce, self.memory_max, self.utf8_flag, self.auto_flag)
-            }
-            None => {
-                let mut ce = self.elements.clone();
-                if ce.len() > 1000 {
-                    ce = strip_pseudo_st::<T>(&ce[..], base);
-                } else {
-                    ce = strip_pseudo_par::<T>(ce, base);
-                }
-                Self::from_vector_internal(ce, self.memory_max, self.utf8_flag, self.auto_flag)
-            }
-        } // match
+            Some(_) => self.filter_streaming(move |x: T| x.sprp(base), None),
+            None => FResult::Value(self.filter_sprp_loaded(base)),
+        }
+    }
+
+    // Filter by a selected base of the composites in memory, any file is ignored
+    fn filter_sprp_loaded(&self, base: T) -> Self {
+        let mut ce = self.elements.clone();
+        if ce.len() > 1000 {
+            ce = strip_pseudo_st::<T>(&ce[..], base);
+        } else {
+            ce = strip_pseudo_par::<T>(ce, base);
+        }
+        Self::from_vector_internal(ce, self.memory_max, self.utf8_flag, self.auto_flag)
     }
 
     pub fn k_iterative(&self, k: usize) -> BaseSeq<T> {
@@ -253,7 +494,7 @@ impl<T: Natural> CompVector<T> {
             let bound = 1_000;
             // FIXME change calibration
             let (c, _) = unary_strongest_par::<T>(ce.elements.clone(), 2, bound * (k as u64 + 1));
-            ce = ce.filter_sprp_rt(T::from(c));
+            ce = ce.filter_sprp_loaded(T::from(c));
             bv.append(T::from(c));
         }
         let x = exhaustive_par(ce.elements);
@@ -261,6 +502,19 @@ impl<T: Natural> CompVector<T> {
         bv
     }
 
+    /// Evolutionary search for a set of bases eliminating all the composites, see EvoParam for the parameters
+    /// # Partial
+    /// No set eliminating every composite was found, returns the fittest set and the number of composites it passes
+    pub fn evo_search(&self, param: &EvoParam) -> FResult<BaseSeq<T>> {
+        self.load_eval(&|x: Self| {
+            let (bases, count) = evo_base_par(x.elements, param);
+            if count == 0 {
+                return FResult::Value(bases);
+            }
+            FResult::Partial(bases, count as usize)
+        })
+    }
+
     /// Infinite search, this is short-circuiting and therefore much faster than a strongest search. However it has an unpredictable run time
     pub fn terminating_search(&self) -> FResult<BaseSeq<T>> {
         self.load_eval(&|x: Self| {
@@ -276,7 +530,7 @@ impl<T: Natural> CompVector<T> {
         while ce.len() > 100 {
             let bound = p / (ce.len() as u64);
             let (c, _) = unary_strongest_par::<T>(ce.elements.clone(), 2, bound);
-            ce = ce.filter_sprp_rt(T::from(c));
+            ce = ce.filter_sprp_loaded(T::from(c));
             bv.append(T::from(c));
         }
 
@@ -296,11 +550,11 @@ impl<T: Natural> CompVector<T> {
     pub fn bs_rand(&self) -> BaseSeq<T> {
         let b = T::gen_k(64).unwrap();
         let mut bv = BaseSeq::new(vec![b]);
-        let mut ce = self.filter_sprp_rt(b);
+        let mut ce = self.filter_sprp_loaded(b);
 
         while ce.len() > 0 {
             let b = T::gen_k(64).unwrap();
-            ce = ce.filter_sprp_rt(b);
+            ce = ce.filter_sprp_loaded(b);
             bv.append(b);
         }
         bv
@@ -340,3 +594,73 @@ impl CompVector<u64> {
         })
     }
 }
+
+#[test]
+fn hashtable_small_dimension() {
+    // Fewer than 150 composites, so the default dimension is the minimum of 2
+    let pseudos = vec![2047u64, 3277, 4033, 4681, 8321, 15841, 29341, 42799, 49141, 52633];
+    let ce = CompVector::from_vector(pseudos.clone());
+    match ce.to_hashtable_multi(&[], None, 1, None, None) {
+        FResult::Value(ht) => {
+            assert_eq!(ht.values().0, 2);
+            assert!(pseudos.iter().all(|x| !ht.primality(*x)));
+        }
+        _ => panic!("Hashtable of dimension 2 was not constructed"),
+    }
+    assert!(matches!(ce.to_hashtable_multi(&[], Some(1), 1, None, None), FResult::NotSupported));
+    assert!(matches!(ce.to_hashtable_levels(&[], &[4, 1], 1, None), FResult::NotSupported));
+}
+
+#[test]
+fn hashtable_multi_u128() {
+    // Strong pseudoprimes to base 2 of the form p(2p-1) beyond 2^64
+    let pseudos = (1u64 << 33..)
+        .filter(|p| p.is_prime() && (2 * p - 1).is_prime())
+        .map(|p| p as u128 * (2 * p as u128 - 1))
+        .filter(|n| n.sprp(2))
+        .take(400)
+        .collect::<Vec<u128>>();
+    let ce = CompVector::from_vector(pseudos.clone());
+    match ce.to_hashtable_multi(&[2], None, 2, Some(1234567), None) {
+        FResult::Value(ht) => {
+            assert!(matches!(ht.prove(&ce), FResult::Success));
+            assert!(!ht.primality(pseudos[0]));
+            assert!(ht.primality((1u128 << 89) - 1));
+        }
+        _ => panic!("Hashtable with a fixed base was not constructed for 128-bit composites"),
+    }
+}
+
+#[test]
+fn filter_streaming_chunks() {
+    let folder = std::env::temp_dir().join(format!("f-analysis-streaming-{}", std::process::id()));
+    let _ = std::fs::create_dir_all(&folder);
+    let locale = |name: &str| folder.join(name).to_str().unwrap().to_string();
+
+    let composites = (9u64..1 << 16).step_by(2).filter(|x| !x.is_prime()).collect::<Vec<u64>>();
+    let mut out = BinaryWriter::<u64>::create(&locale("composites"), "odd composites").unwrap();
+    for i in composites.iter() {
+        out.write(*i).unwrap();
+    }
+    out.finish().unwrap();
+
+    // Chunks of 256 elements, far fewer than the file
+    let mut ce = CompVector::<u64>::from_file(&locale("composites")).unwrap();
+    ce.set_memory_max(4096);
+    assert!(ce.chunk_length() < composites.len());
+
+    let expected = CompVector::from_vector(composites.clone()).filter_streaming(|x: u64| x.sprp(2), None).unwrap();
+    let expected = expected.to_vector();
+    assert_eq!(expected.len(), 11);
+    let chunked = ce.filter_streaming(|x: u64| x.sprp(2), None).unwrap();
+    assert_eq!(chunked.to_vector(), expected);
+    let written = ce.filter_streaming(|x: u64| x.sprp(2), Some(&locale("filtered"))).unwrap();
+    assert_eq!(written.load_to_memory().unwrap().to_vector(), expected);
+
+    // The result does not fit within memory_max, unless written to a file
+    assert!(matches!(ce.filter_streaming(|_| true, None), FResult::MemoryExceeded(_)));
+    let all = ce.filter_streaming(|_| true, Some(&locale("all"))).unwrap();
+    assert_eq!(all.len(), composites.len());
+
+    let _ = std::fs::remove_dir_all(&folder);
+}
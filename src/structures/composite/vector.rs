@@ -1,5 +1,5 @@
 use crate::search::{
-    bev_sprpv, binary_det_iter_st, binary_evo_par, binary_evo_st, filter_par, filter_st,
+    bev_sprpv, binary_det_iter_st, filter_par, filter_st,
     hash_search, strip_pseudo_par, strip_pseudo_st, unary_ht_par, unary_strongest_par,
     unary_strongest_st,
 };
@@ -235,16 +235,6 @@ pub fn det_search<F: BaseIterator<T>, T: Natural>(x: &Vec<T>, iter: F) -> (T, T)
     binary_det_iter_st::<T, F>(x, iter)
 }
 
-/// Evolutionary search for the two strongest bases within the exclusive interval
-/// First the strongest initial base is found and then a strong complement
-/// These two values are then swapped for up to four cycles
-pub fn evo_search<T: Natural>(x: &Vec<T>, inf: u64, sup: u64) -> (u64, u64) {
-    if x.len() > 10000 {
-        return binary_evo_par(x.clone(), inf, sup);
-    }
-    binary_evo_st(&x[..], inf, sup)
-}
-
 /// Infinite search until a base is found that eliminates all the composites
 pub fn terminating_search<F: BaseIterator<T>, T: Natural>(x: &Vec<T>, mut iter: F) -> T {
     loop {
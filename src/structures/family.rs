@@ -0,0 +1,483 @@
+use crate::car::MRC_18;
+use crate::enums::{Search, AUTO_FLAG, MEMORY_MAX, UTF8_FLAG};
+use crate::io::header::BinaryWriter;
+use crate::search::thread_count;
+use crate::structures::composite::file::{filter_chunk_par, Predicate};
+use crate::structures::Primes;
+use crate::{Carmichael, CompVector, FResult, Natural};
+use std::sync::Arc;
+
+/*
+   Generation of the families of composites searched by the heuristics
+
+   Each family is enumerated over the open interval (inf;sup) and passed to a sink, which filters and stores the
+   composites. The k-forms ∏(a_i k+1) are enumerated over the odd primes p = a_1 k+1 of the least multiplier, which are
+   less than the m-th root of sup for a form of m factors, terminating once the product exceeds sup. Composites
+   belonging to several families are produced once for each.
+
+   Primes are taken in segments, and every k-form is evaluated over contiguous blocks of the segment in parallel.
+   The composites of each block are buffered and passed to the sink in a fixed order once the segment is complete
+*/
+
+// Multipliers a of the (2k+1)(ak+1) semiprimes of the weak heuristic of Interval
+const WEAK_MULTIPLIERS: [u64; 4] = [3, 4, 5, 6];
+// Exclusive bound of the multipliers a of the (k+1)(ak+1) semiprimes of the strong heuristic of Interval
+const STRONG_BOUND: u64 = 2048;
+// The same for the heuristics of BaseSeq
+const BASE_WEAK_MULTIPLIERS: [u64; 3] = [3, 4, 6];
+const BASE_STRONG_BOUND: u64 = 64;
+// Number of primes evaluated in parallel before the composites are passed to the sink
+const SEGMENT: usize = 1 << 16;
+
+/// Family of composites enumerated by FamilyGenerator
+#[derive(Clone, Debug)]
+pub enum CompositeFamily {
+    /// Products ∏(a_i k+1) of at least two multipliers where every factor is prime, e.g (k+1)(2k+1) or the
+    /// Chernick form (6k+1)(12k+1)(18k+1)
+    KForm(Vec<u64>),
+    /// Carmichael numbers pqr with p, q and r all 3 mod 4, tabulated up to 10^18. The table is the output of
+    /// `Carmichael::mrc()`, checked by `Carmichael::check_tabulated`. Beyond 10^18 use the Carmichael family of `Carmichael::mrc()`
+    Tabulated,
+    /// Carmichael numbers of the generator, evaluated in 128-bit arithmetic
+    Carmichael(Carmichael),
+}
+
+impl CompositeFamily {
+    /// Every k-form of the number of factors with ascending multipliers in [1;max] and no common divisor. Each
+    /// composite of that many factors belongs to at most one of them, as k is the gcd of the factors less one
+    /// ```
+    /// use f_analysis::CompositeFamily;
+    ///
+    /// // (k+1)(2k+1)(3k+1) and (k+1)(2k+1)(4k+1) and so on, excluding (2k+1)(4k+1)(6k+1) among others
+    /// assert_eq!(CompositeFamily::k_forms(3, 6).len(), 19);
+    /// ```
+    pub fn k_forms(factors: usize, max: u64) -> Vec<Self> {
+        fn extend(factors: usize, max: u64, current: &mut Vec<u64>, res: &mut Vec<CompositeFamily>) {
+            if current.len() == factors {
+                if current.iter().fold(0u64, |acc, a| acc.gcd(*a)) == 1 {
+                    res.push(CompositeFamily::KForm(current.clone()));
+                }
+                return;
+            }
+            for a in current.last().map_or(1, |x| x + 1)..=max {
+                current.push(a);
+                extend(factors, max, current, res);
+                current.pop();
+            }
+        }
+        let mut res = vec![];
+        extend(factors, max, &mut vec![], &mut res);
+        res
+    }
+}
+
+impl std::fmt::Display for CompositeFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompositeFamily::KForm(m) => {
+                let m = m.iter().map(|a| a.to_string()).collect::<Vec<String>>().join(",");
+                write!(f, "k-form {}", m)
+            }
+            CompositeFamily::Tabulated => write!(f, "tabulated"),
+            CompositeFamily::Carmichael(gen) => write!(f, "{:?}", gen),
+        }
+    }
+}
+
+// Integers exceeding 2^128 are truncated to 2^128-1
+fn to_u128<T: Natural>(x: T) -> u128 {
+    if x.msb() > 128 {
+        return u128::MAX;
+    }
+    let mut interim = x.to_bytes();
+    interim.resize(16, 0u8);
+    u128::from_bytes(&interim[..])
+}
+
+fn from_u128<T: Natural>(x: u128) -> T {
+    let mut interim = x.to_bytes();
+    interim.resize(T::BYTE_LENGTH.max(16), 0u8);
+    T::from_bytes(&interim[..])
+}
+
+fn io_result(res: std::io::Result<()>) -> FResult<()> {
+    match res {
+        Ok(()) => FResult::Success,
+        Err(message) => FResult::IOError(message),
+    }
+}
+
+// a*k+1, None if it overflows
+fn factor<T: Natural>(k: T, a: u64) -> Option<T> {
+    let (ak, flag) = k.overflowing_mul(T::from(a));
+    match ak.overflow_add(T::ONE) {
+        (res, false) if !flag => Some(res),
+        _ => None,
+    }
+}
+
+/*
+   In: Ascending primes, sorted multipliers of a k-form, the interval and a predicate
+   Out: Composites of the k-form with least factor among the primes, and whether the products exceeded sup
+*/
+fn k_form_st<T: Natural>(
+    primes: &[u64],
+    m: &[u64],
+    inf: T,
+    sup: T,
+    pred: &Predicate<T>,
+) -> (Vec<T>, bool) {
+    let mut res = vec![];
+    for p in primes.iter() {
+        if (p - 1) % m[0] != 0 {
+            continue;
+        }
+        let k = T::from((p - 1) / m[0]);
+
+        // The products increase with p, so the enumeration ends with the first exceeding sup
+        let mut prod = Some(T::from(*p));
+        for a in m[1..].iter() {
+            prod = match (prod, factor(k, *a)) {
+                (Some(x), Some(y)) => match x.overflowing_mul(y) {
+                    (res, false) => Some(res),
+                    _ => None,
+                },
+                _ => None,
+            };
+        }
+        let prod = match prod {
+            Some(x) if x.is_bounded_by(T::ZERO, sup) => x,
+            _ => return (res, true),
+        };
+
+        if prod.is_bounded_by(inf, sup)
+            && m[1..].iter().all(|a| factor(k, *a).is_some_and(|q| q.is_prime()))
+            && pred(prod)
+        {
+            res.push(prod);
+        }
+    }
+    (res, false)
+}
+
+// Evaluates every k-form over contiguous blocks of the segment, one per thread, returned in the order of the blocks
+fn k_form_par<T: Natural>(
+    segment: Vec<u64>,
+    forms: &[(usize, Vec<u64>)],
+    inf: T,
+    sup: T,
+    pred: &Predicate<T>,
+    tc: usize,
+) -> Vec<Vec<(Vec<T>, bool)>> {
+    let eval = move |primes: &[u64], forms: &[Vec<u64>], pred: &Predicate<T>| {
+        forms.iter().map(|m| k_form_st(primes, m, inf, sup, pred)).collect::<Vec<(Vec<T>, bool)>>()
+    };
+    let multipliers = forms.iter().map(|(_, m)| m.clone()).collect::<Vec<Vec<u64>>>();
+
+    if tc == 1 {
+        return vec![eval(&segment[..], &multipliers[..], pred)];
+    }
+
+    let stride = segment.len().div_ceil(tc);
+    let s_arc = Arc::new(segment);
+    let m_arc = Arc::new(multipliers);
+    let mut threads = vec![];
+
+    for i in 0..tc {
+        let start = (i * stride).min(s_arc.len());
+        let stop = ((i + 1) * stride).min(s_arc.len());
+        let s_i = Arc::clone(&s_arc);
+        let m_i = Arc::clone(&m_arc);
+        let p_i = Arc::clone(pred);
+        threads.push(std::thread::spawn(move || eval(&s_i[start..stop], &m_i[..], &p_i)));
+    }
+
+    threads.into_iter().map(|t| t.join().unwrap()).collect()
+}
+
+/// Generator of the composites of several families within an interval
+///
+/// The heuristic searches of Interval and BaseSeq are generated by the families of their Search mode, see
+/// from_search. Families may also be combined freely
+/// ```
+/// use f_analysis::{CompositeFamily, FamilyGenerator};
+///
+/// let mut gen = FamilyGenerator::new(0u64, 1000);
+/// gen.push(CompositeFamily::KForm(vec![1, 2]));
+/// let mut res = vec![];
+/// gen.generate(&mut |x| Ok(res.push(x)));
+/// // (k+1)(2k+1) for k = 2, 6 and 18
+/// assert_eq!(res, vec![15, 91, 703]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FamilyGenerator<T: Natural> {
+    inf: T,
+    sup: T,
+    families: Vec<CompositeFamily>,
+}
+
+impl<T: Natural> FamilyGenerator<T> {
+    /// Generator over the open interval (inf;sup) with no families
+    pub fn new(inf: T, sup: T) -> Self {
+        let (inf, sup) = inf.min_max(sup);
+        Self {
+            inf,
+            sup,
+            families: vec![],
+        }
+    }
+
+    /// Families of the heuristic search mode of Interval
+    ///
+    /// - WeakHeuristic, the semiprimes (2k+1)(ak+1) for a in 3..=6 and the tabulated Carmichael numbers
+    /// - StrongHeuristic, the WeakHeuristic and the semiprimes (k+1)(ak+1) for a in 2..2048
+    /// - MRHeuristic, the Monier-Rabin semiprimes (k+1)(2k+1) and the tabulated Carmichael numbers
+    /// - SPKHeuristic(a), the semiprimes (k+1)(ak+1)
+    /// - SPKAHeuristic(a,b), the semiprimes (ak+1)(bk+1)
+    /// # NotSupported
+    /// Deterministic search, which is every composite
+    pub fn from_search(inf: T, sup: T, mode: Search) -> FResult<Self> {
+        Self::heuristic(inf, sup, mode, &WEAK_MULTIPLIERS, STRONG_BOUND)
+    }
+
+    /// Families of the heuristic search mode of BaseSeq, the same as from_search except that WeakHeuristic is the
+    /// semiprimes (2k+1)(ak+1) for a in {3,4,6} and StrongHeuristic adds (k+1)(ak+1) for a in 2..64
+    /// # NotSupported
+    /// Deterministic search, which is every composite
+    pub fn from_base_search(inf: T, sup: T, mode: Search) -> FResult<Self> {
+        Self::heuristic(inf, sup, mode, &BASE_WEAK_MULTIPLIERS, BASE_STRONG_BOUND)
+    }
+
+    fn heuristic(inf: T, sup: T, mode: Search, weak: &[u64], strong: u64) -> FResult<Self> {
+        let mut res = Self::new(inf, sup);
+        match mode {
+            Search::Deterministic => return FResult::NotSupported,
+            Search::WeakHeuristic | Search::StrongHeuristic => {
+                for a in weak {
+                    res.push(CompositeFamily::KForm(vec![2, *a]));
+                }
+                res.push(CompositeFamily::Tabulated);
+                if mode == Search::StrongHeuristic {
+                    for a in 2..strong {
+                        res.push(CompositeFamily::KForm(vec![1, a]));
+                    }
+                }
+            }
+            Search::MRHeuristic => {
+                res.push(CompositeFamily::KForm(vec![1, 2]));
+                res.push(CompositeFamily::Tabulated);
+            }
+            Search::SPKHeuristic(a) => res.push(CompositeFamily::KForm(vec![1, a as u64])),
+            Search::SPKAHeuristic(a, b) => res.push(CompositeFamily::KForm(vec![a as u64, b as u64])),
+        }
+        FResult::Value(res)
+    }
+
+    pub fn push(&mut self, family: CompositeFamily) {
+        self.families.push(family);
+    }
+
+    pub fn families(&self) -> &[CompositeFamily] {
+        &self.families[..]
+    }
+
+    /// Passes the composites of each family to sink, see generate_filtered
+    /// # Err
+    /// A k-form has fewer than two multipliers or a multiplier of zero, or a Carmichael generator fewer than 3 factors
+    /// # IOError
+    /// The sink failed, generation stops at the first failure
+    pub fn generate(&self, sink: &mut dyn FnMut(T) -> std::io::Result<()>) -> FResult<()> {
+        self.generate_filtered(Arc::new(|_| true), sink)
+    }
+
+    /// Passes the composites of each family satisfying the predicate to sink
+    ///
+    /// The primes are evaluated in segments of 2^16, each divided between the threads. Composites are passed in
+    /// order of the segment, then the family in the order added, then the prime, so the output is identical for any
+    /// number of threads. The tabulated and Carmichael families are passed with the first segment
+    pub fn generate_filtered(
+        &self,
+        pred: Arc<dyn Fn(T) -> bool + Send + Sync>,
+        sink: &mut dyn FnMut(T) -> std::io::Result<()>,
+    ) -> FResult<()> {
+        self.generate_threads(pred, sink, thread_count())
+    }
+
+    // Generation with the segments divided between tc threads
+    fn generate_threads(
+        &self,
+        pred: Predicate<T>,
+        sink: &mut dyn FnMut(T) -> std::io::Result<()>,
+        tc: usize,
+    ) -> FResult<()> {
+        // Sorted multipliers of each k-form and its index among the families
+        let mut forms = vec![];
+        for (idx, family) in self.families.iter().enumerate() {
+            if let CompositeFamily::KForm(m) = family {
+                let mut m = m.clone();
+                m.sort();
+                if m.len() < 2 || m[0] == 0 {
+                    return FResult::Err("K-forms require at least two multipliers, all nonzero");
+                }
+                forms.push((idx, m));
+            }
+        }
+
+        // Primes of the least factor of every k-form, less than the m-th root of sup for m factors
+        let plist = match forms.iter().map(|(_, m)| m.len()).min() {
+            None => None,
+            Some(2) => Some(Primes::generate_or_restore(self.sup.isqrt().to_u64() as usize)),
+            Some(m) => {
+                let root = self.sup.to_float().powf(1.0 / m as f64).ceil() as usize + 1;
+                Some(Primes::generate_or_restore(root.max(16)))
+            }
+        };
+        let mut primes = match &plist {
+            Some(x) => x.iter(),
+            None => Box::new(std::iter::empty()),
+        };
+        let mut first = true;
+
+        loop {
+            let segment = primes.by_ref().take(SEGMENT).collect::<Vec<u64>>();
+            let len = segment.len();
+            let mut out = vec![vec![]; self.families.len()];
+
+            if first {
+                for (idx, family) in self.families.iter().enumerate() {
+                    let interim = match family {
+                        CompositeFamily::KForm(_) => continue,
+                        CompositeFamily::Tabulated => MRC_18
+                            .iter()
+                            .map(|x| T::from(*x))
+                            .filter(|x| x.is_bounded_by(self.inf, self.sup))
+                            .collect::<Vec<T>>(),
+                        CompositeFamily::Carmichael(gen) => {
+                            match gen.enumerate(to_u128(self.inf), to_u128(self.sup)) {
+                                FResult::Value(x) => x.into_iter().map(from_u128).collect::<Vec<T>>(),
+                                failure => return failure.recast(),
+                            }
+                        }
+                    };
+                    out[idx] = filter_chunk_par(interim, &pred);
+                }
+                first = false;
+            }
+
+            if len > 0 && !forms.is_empty() {
+                let blocks = k_form_par(segment, &forms, self.inf, self.sup, &pred, tc);
+                // Forms exceeding sup within the segment are complete
+                let mut exceeded = vec![false; forms.len()];
+                for block in blocks {
+                    for (jdx, (res, flag)) in block.into_iter().enumerate() {
+                        out[forms[jdx].0].extend(res);
+                        exceeded[jdx] |= flag;
+                    }
+                }
+                let mut jdx = 0;
+                forms.retain(|_| {
+                    jdx += 1;
+                    !exceeded[jdx - 1]
+                });
+            }
+
+            for x in out.into_iter().flatten() {
+                if let Err(message) = sink(x) {
+                    return FResult::IOError(message);
+                }
+            }
+            if len < SEGMENT || forms.is_empty() {
+                return FResult::Success;
+            }
+        }
+    }
+
+    /// Composites of every family, written to the file if locale is Some otherwise stored in memory
+    pub fn to_compvector(&self, locale: Option<&str>) -> FResult<CompVector<T>> {
+        let families = self.families.iter().map(|f| f.to_string()).collect::<Vec<String>>().join("; ");
+        let metadata = format!("interval {},{} {}", self.inf, self.sup, families);
+        self.collect(locale, &metadata, Arc::new(|_| true))
+    }
+
+    // Composites of every family satisfying the predicate, the file header records the metadata
+    pub(crate) fn collect(
+        &self,
+        locale: Option<&str>,
+        metadata: &str,
+        pred: Predicate<T>,
+    ) -> FResult<CompVector<T>> {
+        match locale {
+            Some(x) => {
+                let mut out = match BinaryWriter::<T>::create(x, metadata) {
+                    Ok(f) => f,
+                    Err(message) => return FResult::IOError(message),
+                };
+                match self.generate_filtered(pred, &mut |c| out.write(c)) {
+                    FResult::Success => (),
+                    failure => return failure.recast(),
+                }
+                match out.finish() {
+                    Ok(f) => FResult::Value(CompVector::from_file_internal(f, MEMORY_MAX, UTF8_FLAG, AUTO_FLAG)),
+                    Err(message) => FResult::IOError(message),
+                }
+            }
+            None => {
+                let mut ce = vec![];
+                let res = self.generate_filtered(pred, &mut |c| {
+                    ce.push(c);
+                    Ok(())
+                });
+                match res {
+                    FResult::Success => FResult::Value(CompVector::from_vector(ce)),
+                    failure => failure.recast(),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn heuristic_families() {
+    use crate::Epz;
+
+    // BaseSeq evaluates fewer semiprimes than Interval
+    let base = FamilyGenerator::<u64>::from_base_search(0, 1 << 40, Search::StrongHeuristic).unwrap();
+    let interval = FamilyGenerator::<u64>::from_search(0, 1 << 40, Search::StrongHeuristic).unwrap();
+    assert_eq!(base.families().len(), 3 + 1 + 62);
+    assert_eq!(interval.families().len(), 4 + 1 + 2046);
+
+    // Extended precision integers produce the same composites, including the tabulated Carmichael numbers
+    let (inf, sup) = (1_000_000u64, 1 << 36);
+    let u = FamilyGenerator::<u64>::from_search(inf, sup, Search::WeakHeuristic).unwrap();
+    let e = FamilyGenerator::<Epz<2>>::from_search(Epz::from(inf), Epz::from(sup), Search::WeakHeuristic).unwrap();
+    let u = u.to_compvector(None).unwrap().to_vector();
+    let e = e.to_compvector(None).unwrap().to_vector();
+    assert!(u.iter().any(|x| MRC_18.contains(x)));
+    assert_eq!(u.into_iter().map(Epz::<2>::from).collect::<Vec<Epz<2>>>(), e);
+}
+
+#[test]
+fn family_thread_count() {
+    // Two segments of primes for the semiprimes, the Chernick form and Carmichael numbers with the first segment
+    let mut gen = FamilyGenerator::<u64>::new(1 << 20, 1_000_000_000_000);
+    gen.push(CompositeFamily::KForm(vec![1, 2]));
+    gen.push(CompositeFamily::KForm(vec![6, 12, 18]));
+    gen.push(CompositeFamily::Tabulated);
+    gen.push(CompositeFamily::KForm(vec![2, 1, 4]));
+
+    let run = |tc: usize| {
+        let mut out = vec![];
+        let mut sink = |x: u64| {
+            out.extend_from_slice(&x.to_le_bytes());
+            Ok(())
+        };
+        let res = gen.generate_threads(Arc::new(|x: u64| x.sprp(2)), &mut sink, tc);
+        assert!(matches!(res, FResult::Success));
+        out
+    };
+    let single = run(1);
+    assert!(!single.is_empty());
+    for tc in [2, 3, 7] {
+        assert!(run(tc) == single, "{} threads", tc);
+    }
+}
@@ -1,8 +1,10 @@
+mod export;
+
 use crate::io::write::format_block;
 use crate::structures::store::Persistent;
 use crate::Natural;
 use crate::{cvec, CompVector};
-use crate::{search::thread_count, FResult};
+use crate::{search::{anneal_par, thread_count}, AnnealParam, FResult};
 use machine_prime::is_prime_wc;
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
@@ -10,11 +12,17 @@ use std::sync::{
 };
 
 /// Structure for hashtable primality test
+///
+/// Integers are tested by each of the fixed bases, and then by the bases of the bucket they hash to. Tables have one
+/// base per bucket unless constructed with a stride, as needed for 128-bit integers. Tables may be chained into
+/// levels with their own multipliers, so that the bases of every level are selected by hashing
 #[derive(Clone)]
 pub struct HashTable {
     dimen: usize,
     multiplier: u32,
     table: Vec<u64>,
+    fixed: Vec<u64>,
+    next: Option<Box<HashTable>>,
 }
 
 impl Persistent for HashTable {
@@ -34,57 +42,112 @@ impl Persistent for HashTable {
         }
     }
 
-    /// Fix errors
+    /// Fails with an error rather than panicking if the file is corrupt
     fn from_persistent(filename: &str) -> FResult<Self> {
         use std::io::BufRead;
 
-        //FIXME handle parsing error
-        let parser = |x: &str| -> u64 {
-            let z = x.chars().filter(|k| k.is_digit(10)).collect::<String>();
-            z.parse::<u64>().unwrap()
-        };
-
-        let splitter = |x: &str| -> Vec<u64> {
-            let mut z = x.split(",").collect::<Vec<&str>>();
-            z.pop();
-            z.iter().map(|y| parser(y)).collect::<Vec<u64>>()
+        let lines = match std::fs::File::open(filename) {
+            Ok(x) => match std::io::BufReader::new(x).lines().collect::<Result<Vec<String>, _>>() {
+                Ok(l) => l,
+                Err(message) => return FResult::IOError(message),
+            },
+            Err(file_error) => return FResult::IOError(file_error),
         };
+        parse_lines(&lines[..])
+    }
+}
 
-        let mut div = 0usize;
-        let mut mul = 0u32;
-        let mut param = vec![];
-
-        match std::fs::File::open(filename) {
-            Ok(x) => {
-                let r = std::io::BufReader::new(x);
-
-                for (idx, el) in r.lines().enumerate() {
-                    let interim = el.unwrap();
-                    if idx == 0 {
-                        let q = interim.split(':').collect::<Vec<&str>>();
-                        div = (1usize << 32) / (parser(q[1]) as usize);
-                        mul = parser(q[2]) as u32;
-                    }
-                    if idx > 2 {
-                        let mut z = splitter(interim.clone().as_str());
-                        param.extend_from_slice(&mut z[..]);
-                    }
-                }
+// Parses the levels of a table as written by Display
+pub(crate) fn parse_lines(lines: &[String]) -> FResult<HashTable> {
+    // Each level begins with its divisor, the levels are chained from the last
+    let mut starts = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.trim_start().starts_with("divisor:"))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<usize>>();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(lines.len());
 
-                if param.len() != div {
-                    return FResult::Err("Table of incorrect dimensions");
-                }
+    let mut res: Option<HashTable> = None;
+    for w in starts.windows(2).rev() {
+        let mut level = match parse_level(&lines[w[0]..w[1]]) {
+            FResult::Value(x) => x,
+            failure => return failure,
+        };
+        level.next = res.map(Box::new);
+        res = Some(level);
+    }
+    match res {
+        Some(x) => FResult::Value(x),
+        None => FResult::Err("Malformed hashtable divisor or multiplier"),
+    }
+}
 
-                let res = HashTable {
-                    dimen: div,
-                    multiplier: mul,
-                    table: param,
+// Parses a single level of the table, the header, hash and values
+fn parse_level(lines: &[String]) -> FResult<HashTable> {
+    let parser = |x: &str| -> Option<u64> {
+        let z = x.chars().filter(|k| k.is_ascii_digit()).collect::<String>();
+        z.parse::<u64>().ok()
+    };
+
+    let splitter = |x: &str| -> Option<Vec<u64>> {
+        let mut z = x.split(",").collect::<Vec<&str>>();
+        z.pop();
+        z.iter().map(|y| parser(y)).collect::<Option<Vec<u64>>>()
+    };
+
+    let mut div = 0usize;
+    let mut mul = 0u32;
+    let mut stride = 1usize;
+    let mut fixed = vec![];
+    let mut param = vec![];
+
+    for (idx, interim) in lines.iter().enumerate() {
+        if idx == 0 {
+            let q = interim.split(':').collect::<Vec<&str>>();
+            let (d, m) = match (q.get(1).and_then(|d| parser(d)), q.get(2).and_then(|m| parser(m))) {
+                (Some(d), Some(m)) if d != 0 && m <= u32::MAX as u64 => (d, m),
+                _ => return FResult::Err("Malformed hashtable divisor or multiplier"),
+            };
+            div = (1usize << 32) / (d as usize);
+            mul = m as u32;
+            // Stride and fixed bases are only written if the table has them
+            if let Some(s) = q.get(3) {
+                stride = match parser(s) {
+                    Some(s) if s != 0 => s as usize,
+                    _ => return FResult::Err("Malformed hashtable stride"),
                 };
-                FResult::Value(res)
             }
-            Err(file_error) => FResult::IOError(file_error),
+            if let Some(f) = q.get(4) {
+                let bases = f.split(',').filter(|b| !b.trim().is_empty());
+                fixed = match bases.map(parser).collect::<Option<Vec<u64>>>() {
+                    Some(x) => x,
+                    None => return FResult::Err("Malformed hashtable fixed bases"),
+                };
+            }
+        }
+        if idx > 2 {
+            match splitter(interim.as_str()) {
+                Some(z) => param.extend_from_slice(&z[..]),
+                None => return FResult::Err("Malformed hashtable value"),
+            }
         }
     }
+
+    if div == 0 || param.len() != div * stride {
+        return FResult::Err("Table of incorrect dimensions");
+    }
+
+    FResult::Value(HashTable {
+        dimen: div,
+        multiplier: mul,
+        table: param,
+        fixed,
+        next: None,
+    })
 }
 
 impl HashTable {
@@ -93,9 +156,48 @@ impl HashTable {
             table,
             dimen,
             multiplier,
+            fixed: vec![],
+            next: None,
+        }
+    }
+
+    /// Appends a level evaluated after the bases of this table and any levels already chained
+    pub fn set_next(&mut self, next: HashTable) {
+        match &mut self.next {
+            Some(x) => x.set_next(next),
+            None => self.next = Some(Box::new(next)),
         }
     }
 
+    /// The level following this table
+    pub fn next(&self) -> Option<&HashTable> {
+        self.next.as_deref()
+    }
+
+    /// Number of levels, including this table
+    pub fn levels(&self) -> usize {
+        1 + self.next.as_ref().map_or(0, |x| x.levels())
+    }
+
+    /// Number of strong fermat tests evaluated for a prime
+    pub fn test_count(&self) -> usize {
+        self.fixed.len() + self.stride() + self.next.as_ref().map_or(0, |x| x.test_count())
+    }
+
+    /// Bases evaluated for every integer before the hashed bases
+    pub fn set_fixed(&mut self, fixed: Vec<u64>) {
+        self.fixed = fixed;
+    }
+
+    pub fn fixed(&self) -> &[u64] {
+        &self.fixed
+    }
+
+    /// Number of bases in each bucket
+    pub fn stride(&self) -> usize {
+        self.table.len() / self.dimen
+    }
+
     /// Returns dimension, multiplier, and hashvalues
     pub fn values(&self) -> (usize, u32, Vec<u64>) {
         (self.dimen, self.multiplier, self.table.clone())
@@ -109,47 +211,76 @@ impl HashTable {
         self.table[idx]
     }
 
-    // Returns the index and the base that gets selected
+    // Returns the index and the first base of the bucket that gets selected
     pub fn lut_values<T: Natural>(&self, x: T) -> (usize, u64) {
         let idx = x.hash_shift((32 - self.dimen.trailing_zeros()) as usize, self.multiplier);
-        (idx, self.table[idx])
+        (idx, self.table[idx * self.stride()])
     }
 
     /// Evaluates primality for an integer, utilizing the hashtable computed
     pub fn primality<T: Natural>(&self, x: T) -> bool {
-        x.sprp(T::from(self.lut_values(x).1))
+        if !self.fixed.iter().all(|b| x.sprp(T::from(*b))) {
+            return false;
+        }
+        let stride = self.stride();
+        let idx = self.lut_values(x).0 * stride;
+        if !self.table[idx..idx + stride].iter().all(|b| x.sprp(T::from(*b))) {
+            return false;
+        }
+        match &self.next {
+            Some(level) => level.primality(x),
+            None => true,
+        }
     }
 
-    // FIXME Allow Composite to be a file
-    /// Checks that the hashtable eliminates all composites from the vector
+    /// Checks that the hashtable eliminates all composites from the vector, files are streamed
+    /// # Failure
+    /// Some composite passes the hashtable, see list_failure
     pub fn prove<T: Natural>(&self, cvec: &CompVector<T>) -> FResult<T> {
-        for i in cvec.to_vector().iter() {
-            if self.primality(*i) {
-                return FResult::Failure;
-            }
+        match cvec.filter_hashtable_stream(self, None) {
+            FResult::Value(x) if x.len() == 0 => FResult::Success,
+            FResult::Value(_) => FResult::Failure,
+            failure => failure.recast(),
         }
-        return FResult::Success;
     }
 
-    /// FIXME Allow composite to be file
-    pub fn count_failure<T: Natural>(&self, cvec: &CompVector<T>) -> u64 {
-        let mut count = 0u64;
-        for i in cvec.to_vector().iter() {
-            if self.primality(*i) {
-                count += 1;
-            }
+    pub fn count_failure<T: Natural>(&self, cvec: &CompVector<T>) -> FResult<u64> {
+        match self.list_failure(cvec) {
+            FResult::Value(x) => FResult::Value(x.len() as u64),
+            failure => failure.recast(),
         }
-        count
     }
 
-    pub fn list_failure<T: Natural>(&self, cvec: &CompVector<T>) -> CompVector<T> {
-        let mut veccy = vec![];
-        for i in cvec.to_vector().iter() {
-            if self.primality(*i) {
-                veccy.push(*i);
+    /// Composites passing the table, files are streamed see CompVector::filter_streaming
+    /// # MemoryExceeded
+    /// The composites are in a file and the result does not fit within memory_max
+    pub fn list_failure<T: Natural>(&self, cvec: &CompVector<T>) -> FResult<CompVector<T>> {
+        cvec.filter_hashtable_stream(self, None)
+    }
+
+    /// Improves the table by simulated annealing against the composites, starting from the current multiplier and
+    /// bases. The dimension and fixed bases of the table are kept, the dimension of param is ignored
+    /// # NotSupported
+    /// The table has more than one base per bucket
+    /// # Partial
+    /// Composites still pass the best table found, returns it and the number of composites passing it
+    pub fn anneal<T: Natural>(&self, cvec: &CompVector<T>, param: &AnnealParam) -> FResult<Self> {
+        cvec.load_eval(&|x: CompVector<T>| {
+            let survivors = x
+                .elements
+                .into_iter()
+                .filter(|c| self.fixed.iter().all(|b| c.sprp(T::from(*b))))
+                .collect::<Vec<T>>();
+            let (mut ht, count) = match anneal_par(survivors, param, Some(self.clone())) {
+                FResult::Value(x) => x,
+                failure => return failure.recast(),
+            };
+            ht.set_fixed(self.fixed.clone());
+            if count == 0 {
+                return FResult::Value(ht);
             }
-        }
-        CompVector::<T>::from_vector(veccy)
+            FResult::Partial(ht, count as usize)
+        })
     }
 
     pub fn failure_interval(&self, inf: u64, sup: u64, total: bool) -> CompVector<u64> {
@@ -165,16 +296,13 @@ impl HashTable {
                 let mut veccy = vec![];
                 if total {
                     for i in start..stop {
-                        let base = ht.lut_values(i).1;
                         if i & 2 == 0 || i % 3 == 0 || i % 5 == 0 || i % 7 == 0 {
-                            if i.sprp(base) {
+                            if ht.primality(i) {
                                 veccy.push(i);
                             }
                         } else {
-                            if i.sprp(base) {
-                                if !i.sprp(2) {
-                                    veccy.push(i)
-                                }
+                            if ht.primality(i) && !i.sprp(2) {
+                                veccy.push(i)
                             }
                         }
                     }
@@ -184,16 +312,13 @@ impl HashTable {
                         if i & 1 == 0 {
                             continue;
                         }
-                        let base = ht.lut_values(i).1;
                         if i % 3 == 0 || i % 5 == 0 || i % 7 == 0 {
-                            if i.sprp(base) {
+                            if ht.primality(i) {
                                 veccy.push(i);
                             }
                         } else {
-                            if i.sprp(base) {
-                                if !i.sprp(2) {
-                                    veccy.push(i)
-                                }
+                            if ht.primality(i) && !i.sprp(2) {
+                                veccy.push(i)
                             }
                         }
                     }
@@ -210,7 +335,8 @@ impl HashTable {
     }
 
     pub fn update(&self, sup: u64, flag: bool) -> FResult<Self> {
-        if !sup.is_power_of_two() {
+        // Corrections are only computed for single-level tables of one base per bucket
+        if !sup.is_power_of_two() || self.stride() != 1 || self.next.is_some() {
             return FResult::NotSupported;
         }
 
@@ -446,10 +572,23 @@ impl std::fmt::Display for HashTable {
         let m = self.multiplier.to_string();
         let q = format_block::<16, u64>(&self.table);
 
+        // Tables of the original form are written unchanged
+        let extension = if self.stride() == 1 && self.fixed.is_empty() {
+            String::new()
+        } else {
+            let fixed = self.fixed.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(",");
+            format!("stride: {} fixed: {}", self.stride(), fixed)
+        };
+
         write!(
             f,
-            "divisor: {} multiplier: {} \n hash(x)  = (x as u32).wrapping_mul({})/{} \n {}",
-            d, m, m, d, q
-        )
+            "divisor: {} multiplier: {} {}\n hash(x)  = (x as u32).wrapping_mul({})/{} \n {}",
+            d, m, extension, m, d, q
+        )?;
+        // Subsequent levels follow on their own lines
+        match &self.next {
+            Some(level) => write!(f, "\n{}", level),
+            None => Ok(()),
+        }
     }
 }
@@ -0,0 +1,520 @@
+use crate::structures::HashTable;
+use crate::{FResult, SourceLang};
+
+/*
+   Source code export of hashtables
+
+   Each level is written as constants named HT_MULTIPLIER, HT_SHIFT, HT_STRIDE, HT_FIXED and HT_TABLE, subsequent
+   levels replacing HT_ with HT1_, HT2_ and so on. The constants are declared one per statement in every language,
+   so that the importer reads the names and values of each declaration irrespective of the language
+*/
+
+// Number of bases written on each line of the table
+const ROW: usize = 16;
+
+// Prefix of the constants of the level
+fn prefix(level: usize) -> String {
+    if level == 0 {
+        "HT_".to_string()
+    } else {
+        format!("HT{}_", level)
+    }
+}
+
+// Smallest unsigned type holding every base of the table
+fn width(table: &[u64]) -> u32 {
+    match table.iter().max() {
+        Some(x) if *x > u32::MAX as u64 => 64,
+        Some(x) if *x > u16::MAX as u64 => 32,
+        _ => 16,
+    }
+}
+
+// Comma-separated rows of the values, indented
+fn rows(values: &[u64], indent: &str) -> String {
+    values
+        .chunks(ROW)
+        .map(|row| {
+            let row = row.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(", ");
+            format!("{}{},", indent, row)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl HashTable {
+    fn chain(&self) -> Vec<&HashTable> {
+        let mut res = vec![self];
+        while let Some(level) = res[res.len() - 1].next() {
+            res.push(level);
+        }
+        res
+    }
+
+    fn shift(&self) -> u32 {
+        32 - self.dimen.trailing_zeros()
+    }
+
+    /// Source code of a 64-bit primality test using the table. Integers are tested by the fixed bases, or by base 2
+    /// for tables without fixed bases as they are constructed against base-2 pseudoprimes, and then by the hashed
+    /// bases of each level. The test is only as deterministic as the composites the table was verified against
+    /// ```
+    /// use f_analysis::{HashTable, SourceLang};
+    ///
+    /// let ht = HashTable::new(vec![3, 5, 7, 11], 4, 1234567);
+    /// let source = ht.to_source(SourceLang::Python);
+    /// assert!(source.contains("HT_MULTIPLIER = 1234567"));
+    ///
+    /// let imported = HashTable::from_source(&source).unwrap();
+    /// assert_eq!(imported.values(), ht.values());
+    /// ```
+    pub fn to_source(&self, lang: SourceLang) -> String {
+        match lang {
+            SourceLang::Rust => self.rust_source(),
+            SourceLang::C => self.c_source(),
+            SourceLang::Python => self.python_source(),
+        }
+    }
+
+    /// Writes the source code to the file
+    pub fn write_source(&self, lang: SourceLang, locale: &str) -> FResult<()> {
+        match std::fs::write(locale, self.to_source(lang)) {
+            Ok(_) => FResult::Success,
+            Err(message) => FResult::IOError(message),
+        }
+    }
+
+    fn summary(&self, comment: &str) -> String {
+        let chain = self.chain();
+        let pretest = if self.fixed.is_empty() { ", preceded by base 2" } else { "" };
+        [
+            format!("{} Hashtable primality test generated by f-analysis", comment),
+            format!(
+                "{} {} level(s), {} strong fermat test(s) for primes{}",
+                comment,
+                chain.len(),
+                self.test_count(),
+                pretest
+            ),
+            format!("{} Deterministic only for the composites the table was verified against", comment),
+        ]
+        .join("\n")
+    }
+
+    fn rust_source(&self) -> String {
+        let mut constants = vec![];
+        let mut checks = vec![];
+        if self.fixed.is_empty() {
+            checks.push("    if !sprp(n, 2) {\n        return false;\n    }".to_string());
+        }
+
+        for (level, ht) in self.chain().iter().enumerate() {
+            let p = prefix(level);
+            constants.push(format!("pub const {}MULTIPLIER: u32 = {};", p, ht.multiplier));
+            constants.push(format!("pub const {}SHIFT: u32 = {};", p, ht.shift()));
+            constants.push(format!("pub const {}STRIDE: usize = {};", p, ht.stride()));
+            if !ht.fixed.is_empty() {
+                constants.push(format!(
+                    "pub const {}FIXED: [u64; {}] = [\n{}\n];",
+                    p,
+                    ht.fixed.len(),
+                    rows(&ht.fixed, "    ")
+                ));
+                checks.push(format!(
+                    "    if !{}FIXED.iter().all(|b| sprp(n, *b)) {{\n        return false;\n    }}",
+                    p
+                ));
+            }
+            constants.push(format!(
+                "pub const {}TABLE: [u{}; {}] = [\n{}\n];",
+                p,
+                width(&ht.table),
+                ht.table.len(),
+                rows(&ht.table, "    ")
+            ));
+            checks.push(format!(
+                "    let idx = hash(n, {p}MULTIPLIER, {p}SHIFT) * {p}STRIDE;\n    \
+                 if !{p}TABLE[idx..idx + {p}STRIDE].iter().all(|b| sprp(n, u64::from(*b))) {{\n        \
+                 return false;\n    }}",
+                p = p
+            ));
+        }
+
+        format!(
+            "{}\n\n{}\n\n{}\n\npub fn is_prime(n: u64) -> bool {{\n    if n < 2 {{\n        return false;\n    }}\n    \
+             if n & 1 == 0 {{\n        return n == 2;\n    }}\n{}\n    true\n}}\n",
+            self.summary("//"),
+            constants.join("\n"),
+            RUST_SPRP,
+            checks.join("\n")
+        )
+    }
+
+    fn c_source(&self) -> String {
+        let mut constants = vec![];
+        let mut checks = vec![];
+        if self.fixed.is_empty() {
+            checks.push("    if (!ht_sprp(n, 2)) {\n        return false;\n    }".to_string());
+        }
+
+        for (level, ht) in self.chain().iter().enumerate() {
+            let p = prefix(level);
+            constants.push(format!("static const uint32_t {}MULTIPLIER = {}u;", p, ht.multiplier));
+            constants.push(format!("static const uint32_t {}SHIFT = {};", p, ht.shift()));
+            constants.push(format!("static const uint32_t {}STRIDE = {};", p, ht.stride()));
+            if !ht.fixed.is_empty() {
+                constants.push(format!(
+                    "static const uint64_t {}FIXED[{}] = {{\n{}\n}};",
+                    p,
+                    ht.fixed.len(),
+                    rows(&ht.fixed, "    ")
+                ));
+                checks.push(format!(
+                    "    for (uint32_t i = 0; i < {}; i++) {{\n        if (!ht_sprp(n, {}FIXED[i])) {{\n            \
+                     return false;\n        }}\n    }}",
+                    ht.fixed.len(),
+                    p
+                ));
+            }
+            constants.push(format!(
+                "static const uint{}_t {}TABLE[{}] = {{\n{}\n}};",
+                width(&ht.table),
+                p,
+                ht.table.len(),
+                rows(&ht.table, "    ")
+            ));
+            checks.push(format!(
+                "    {{\n        uint32_t idx = ht_hash(n, {p}MULTIPLIER, {p}SHIFT) * {p}STRIDE;\n        \
+                 for (uint32_t i = 0; i < {p}STRIDE; i++) {{\n            \
+                 if (!ht_sprp(n, {p}TABLE[idx + i])) {{\n                return false;\n            }}\n        \
+                 }}\n    }}",
+                p = p
+            ));
+        }
+
+        format!(
+            "{}\n\n#ifndef HT_PRIMALITY_H\n#define HT_PRIMALITY_H\n\n#include <stdbool.h>\n#include <stdint.h>\n\n{}\n\n\
+             {}\n\nstatic inline bool is_prime(uint64_t n) {{\n    if (n < 2) {{\n        return false;\n    }}\n    \
+             if ((n & 1) == 0) {{\n        return n == 2;\n    }}\n{}\n    return true;\n}}\n\n#endif\n",
+            self.summary("//"),
+            constants.join("\n"),
+            C_SPRP,
+            checks.join("\n")
+        )
+    }
+
+    fn python_source(&self) -> String {
+        let mut constants = vec![];
+        let mut checks = vec![];
+        if self.fixed.is_empty() {
+            checks.push("    if not _sprp(n, 2):\n        return False".to_string());
+        }
+
+        for (level, ht) in self.chain().iter().enumerate() {
+            let p = prefix(level);
+            constants.push(format!("{}MULTIPLIER = {}", p, ht.multiplier));
+            constants.push(format!("{}SHIFT = {}", p, ht.shift()));
+            constants.push(format!("{}STRIDE = {}", p, ht.stride()));
+            if !ht.fixed.is_empty() {
+                constants.push(format!("{}FIXED = (\n{}\n)", p, rows(&ht.fixed, "    ")));
+                checks.push(format!(
+                    "    if not all(_sprp(n, b) for b in {}FIXED):\n        return False",
+                    p
+                ));
+            }
+            constants.push(format!("{}TABLE = (\n{}\n)", p, rows(&ht.table, "    ")));
+            checks.push(format!(
+                "    idx = _hash(n, {p}MULTIPLIER, {p}SHIFT) * {p}STRIDE\n    \
+                 if not all(_sprp(n, b) for b in {p}TABLE[idx:idx + {p}STRIDE]):\n        return False",
+                p = p
+            ));
+        }
+
+        format!(
+            "{}\n\n{}\n\n{}\n\ndef is_prime(n):\n    if n < 2:\n        return False\n    if n & 1 == 0:\n        \
+             return n == 2\n{}\n    return True\n",
+            self.summary("#"),
+            constants.join("\n"),
+            PYTHON_SPRP,
+            checks.join("\n")
+        )
+    }
+
+    /// Reads a table from the source code written by to_source, in any of the languages
+    /// # Err
+    /// A constant is missing or malformed, or the table does not have the dimensions given by the shift and stride
+    pub fn from_source(source: &str) -> FResult<Self> {
+        let decls = match declarations(source) {
+            Some(x) => x,
+            None => return FResult::Err("Malformed hashtable constant"),
+        };
+
+        let mut levels: Vec<HashTable> = vec![];
+        loop {
+            let p = prefix(levels.len());
+            let get = |name: &str| decls.iter().find(|(n, _)| *n == format!("{}{}", p, name)).map(|(_, v)| v);
+
+            let (multiplier, shift, table) = match (get("MULTIPLIER"), get("SHIFT"), get("TABLE")) {
+                (Some(m), Some(s), Some(t)) => (m, s, t),
+                (None, None, None) if !levels.is_empty() => break,
+                _ => return FResult::Err("Missing hashtable constant"),
+            };
+            let (multiplier, shift) = match (&multiplier[..], &shift[..]) {
+                ([m], [s]) if *m <= u32::MAX as u64 && (1..=32).contains(s) => (*m as u32, *s),
+                _ => return FResult::Err("Malformed hashtable multiplier or shift"),
+            };
+            let stride = match get("STRIDE").map(|s| &s[..]) {
+                Some([s]) if *s != 0 => *s as usize,
+                None => 1,
+                _ => return FResult::Err("Malformed hashtable stride"),
+            };
+            let dimen = 1usize << (32 - shift);
+            if table.len() != dimen * stride {
+                return FResult::Err("Table of incorrect dimensions");
+            }
+
+            let mut ht = HashTable::new(table.clone(), dimen, multiplier);
+            ht.set_fixed(get("FIXED").cloned().unwrap_or_default());
+            levels.push(ht);
+        }
+
+        let mut iter = levels.into_iter();
+        let mut res = iter.next().unwrap();
+        for level in iter {
+            res.set_next(level);
+        }
+        FResult::Value(res)
+    }
+
+    /// Reads a table from a source file written by write_source
+    pub fn read_source(locale: &str) -> FResult<Self> {
+        match std::fs::read_to_string(locale) {
+            Ok(source) => Self::from_source(&source),
+            Err(message) => FResult::IOError(message),
+        }
+    }
+}
+
+/*
+   In: Source code
+   Out: Name and values of each declared constant, None if a value is not a list of integers
+
+   Declarations are the statements whose first assignment is to a name beginning with HT, values enclosed in
+   brackets extend over the following lines until the bracket is closed
+*/
+fn declarations(source: &str) -> Option<Vec<(String, Vec<u64>)>> {
+    let lines = source.lines().collect::<Vec<&str>>();
+    let mut res = vec![];
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let line = lines[idx];
+        idx += 1;
+
+        let eq = match line.find('=') {
+            Some(x) => x,
+            None => continue,
+        };
+        // Comparisons are not assignments
+        if line[eq + 1..].starts_with('=') || line[..eq].ends_with(['<', '>', '!']) {
+            continue;
+        }
+        let name = line[..eq]
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .find(|t| t.starts_with("HT") && t.contains('_'));
+        let name = match name {
+            Some(x) => x.to_string(),
+            None => continue,
+        };
+
+        let mut value = line[eq + 1..].to_string();
+        let mut depth = bracket_depth(&value);
+        while depth > 0 && idx < lines.len() {
+            value.push_str(lines[idx]);
+            depth += bracket_depth(lines[idx]);
+            idx += 1;
+        }
+        res.push((name, integers(&value)?));
+    }
+    Some(res)
+}
+
+fn bracket_depth(x: &str) -> i64 {
+    x.chars()
+        .map(|c| match c {
+            '[' | '{' | '(' => 1,
+            ']' | '}' | ')' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+// Integers of a comma-separated list, suffixes such as the u of C literals are ignored
+fn integers(x: &str) -> Option<Vec<u64>> {
+    x.split([',', ';', '[', ']', '{', '}', '(', ')'])
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let digits = t.chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+            digits.parse::<u64>().ok()
+        })
+        .collect()
+}
+
+const RUST_SPRP: &str = "fn mul_mod(a: u64, b: u64, n: u64) -> u64 {
+    ((a as u128 * b as u128) % n as u128) as u64
+}
+
+fn sprp(n: u64, base: u64) -> bool {
+    // Bases divisible by n do not pass, as for Natural::sprp
+    let mut b = base % n;
+    if b == 0 {
+        return false;
+    }
+    let tzc = (n - 1).trailing_zeros();
+    let mut d = (n - 1) >> tzc;
+    let mut x = 1u64;
+    while d > 0 {
+        if d & 1 == 1 {
+            x = mul_mod(x, b, n);
+        }
+        b = mul_mod(b, b, n);
+        d >>= 1;
+    }
+    if x == 1 || x == n - 1 {
+        return true;
+    }
+    for _ in 1..tzc {
+        x = mul_mod(x, x, n);
+        if x == n - 1 {
+            return true;
+        }
+    }
+    false
+}
+
+fn hash(n: u64, multiplier: u32, shift: u32) -> usize {
+    (((n as u32).wrapping_mul(multiplier) as u64) >> shift) as usize
+}";
+
+const C_SPRP: &str = "static inline uint64_t ht_mul_mod(uint64_t a, uint64_t b, uint64_t n) {
+    return (uint64_t)(((unsigned __int128)a * b) % n);
+}
+
+static inline bool ht_sprp(uint64_t n, uint64_t base) {
+    // Bases divisible by n do not pass, as for Natural::sprp
+    uint64_t b = base % n;
+    if (b == 0) {
+        return false;
+    }
+    uint32_t tzc = (uint32_t)__builtin_ctzll(n - 1);
+    uint64_t d = (n - 1) >> tzc;
+    uint64_t x = 1;
+    while (d > 0) {
+        if (d & 1) {
+            x = ht_mul_mod(x, b, n);
+        }
+        b = ht_mul_mod(b, b, n);
+        d >>= 1;
+    }
+    if (x == 1 || x == n - 1) {
+        return true;
+    }
+    for (uint32_t i = 1; i < tzc; i++) {
+        x = ht_mul_mod(x, x, n);
+        if (x == n - 1) {
+            return true;
+        }
+    }
+    return false;
+}
+
+static inline uint32_t ht_hash(uint64_t n, uint32_t multiplier, uint32_t shift) {
+    return (uint32_t)(((uint64_t)((uint32_t)n * multiplier)) >> shift);
+}";
+
+const PYTHON_SPRP: &str = "def _sprp(n, base):
+    # Bases divisible by n do not pass, as for Natural::sprp
+    b = base % n
+    if b == 0:
+        return False
+    d = n - 1
+    tzc = 0
+    while d & 1 == 0:
+        d >>= 1
+        tzc += 1
+    x = pow(b, d, n)
+    if x == 1 or x == n - 1:
+        return True
+    for _ in range(1, tzc):
+        x = x * x % n
+        if x == n - 1:
+            return True
+    return False
+
+
+def _hash(n, multiplier, shift):
+    return ((n & 0xFFFFFFFF) * multiplier & 0xFFFFFFFF) >> shift";
+
+#[cfg(test)]
+fn run_exported(folder: &std::path::Path, lang: SourceLang, source: &str, sup: u64) -> Option<Vec<u64>> {
+    use std::process::Command;
+
+    let run = |cmd: &mut Command| -> Option<String> {
+        let out = cmd.output().ok()?;
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        Some(String::from_utf8(out.stdout).unwrap())
+    };
+    let binary = folder.join("exported");
+    let output = match lang {
+        SourceLang::Rust => {
+            let main = format!("fn main() {{ for n in 0..{}u64 {{ if is_prime(n) {{ println!(\"{{}}\", n); }} }} }}", sup);
+            std::fs::write(folder.join("exported.rs"), format!("{}\n{}\n", source, main)).unwrap();
+            let rustc = std::env::var("RUSTC").unwrap_or("rustc".to_string());
+            run(Command::new(rustc).args(["-O", "-A", "warnings", "-o"]).arg(&binary).arg(folder.join("exported.rs")))?;
+            run(&mut Command::new(&binary))?
+        }
+        SourceLang::C => {
+            let main = format!(
+                "#include <stdio.h>\nint main(void) {{ for (uint64_t n = 0; n < {}u; n++) {{ if (is_prime(n)) {{ \
+                 printf(\"%llu\\n\", (unsigned long long)n); }} }} return 0; }}",
+                sup
+            );
+            std::fs::write(folder.join("exported.c"), format!("{}\n{}\n", source, main)).unwrap();
+            run(Command::new("cc").args(["-O2", "-o"]).arg(&binary).arg(folder.join("exported.c")))?;
+            run(&mut Command::new(&binary))?
+        }
+        SourceLang::Python => {
+            let main = format!("for n in range({}):\n    if is_prime(n):\n        print(n)", sup);
+            std::fs::write(folder.join("exported.py"), format!("{}\n\n{}\n", source, main)).unwrap();
+            run(Command::new("python3").arg(folder.join("exported.py")))?
+        }
+    };
+    Some(output.lines().map(|x| x.parse::<u64>().unwrap()).collect())
+}
+
+#[test]
+fn exported_primality() {
+    use crate::Natural;
+
+    let folder = std::env::temp_dir().join(format!("f-analysis-export-{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let sup = 50_000u64;
+    // Every base of the first level is a multiple of the base-2 pseudoprimes 2047 and 3277
+    let table = (1..=8).map(|k| 2 * k * 2047 * 3277).collect::<Vec<u64>>();
+    let mut ht = HashTable::new(table, 8, 1234567);
+    ht.set_next(HashTable::new(vec![6, 10, 14, 22, 26, 34, 38, 46], 4, 7654321));
+    let expected = (0..sup)
+        .filter(|n| *n == 2 || (*n > 2 && n & 1 == 1 && n.sprp(2) && ht.primality(*n)))
+        .collect::<Vec<u64>>();
+    assert!(!ht.primality(2047u64) && !ht.primality(3277u64));
+
+    // The interpreter or compiler of each language may be absent, except that of Rust
+    for lang in [SourceLang::Rust, SourceLang::C, SourceLang::Python] {
+        match run_exported(&folder, lang, &ht.to_source(lang), sup) {
+            Some(primes) => assert_eq!(primes, expected, "{:?}", lang),
+            None => assert!(lang != SourceLang::Rust),
+        }
+    }
+    let _ = std::fs::remove_dir_all(&folder);
+}
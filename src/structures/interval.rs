@@ -1,13 +1,14 @@
 use crate::car::MRC_18;
-use crate::filter::WeakFermat;
+use crate::filter::{FilterExpr, WeakFermat};
+use crate::io::header::BinaryWriter;
 use crate::iterator::BaseIterator;
 use crate::natural::montcore::NTCore;
 use crate::primes::{PRIMORIAL, SMALL_PRIMES, WHEEL};
 use crate::search::{hash_search, thread_count, unary_ht_par};
-use crate::structures::Primes;
+use crate::structures::{CompositeFamily, FamilyGenerator, Primes};
 use crate::FResult;
 use crate::Natural;
-use crate::{CompVector, HashTable, SOSet, SmallOrd, WieferichPrime};
+use crate::{BaseSeq, Carmichael, CompVector, HashTable, SOSet, SmallOrd, WieferichPrime};
 
 use crate::enums::{Search, AUTO_FLAG, MEMORY_MAX, UTF8_FLAG};
 use std::fs::File;
@@ -46,29 +47,51 @@ impl<T: Natural> Interval<T> {
         self.mode = Search::SPKHeuristic(k)
     }
 
+    pub fn set_spka_heuristic(&mut self, a: usize, b: usize) {
+        self.mode = Search::SPKAHeuristic(a, b)
+    }
+
+    pub fn set_mr_heuristic(&mut self) {
+        self.mode = Search::MRHeuristic
+    }
+
     pub fn set_deterministic(&mut self) {
         self.mode = Search::Deterministic;
     }
 
+    // Block indices of the primorial covering the interval, primes are restricted to less than 2^64 so that p^2
+    // is within 128-bit Montgomery arithmetic
+    fn wieferich_blocks(&self) -> (u64, u64) {
+        let bound = T::from(u64::MAX);
+        let sup = if self.sup > bound { u64::MAX } else { self.sup.to_u64() };
+        let inf = if self.inf > bound { u64::MAX } else { self.inf.to_u64() };
+        (inf / PRIMORIAL, sup / PRIMORIAL)
+    }
+
     /// Searches for generalised Wieferich primes within the interval. p such that a^{p-1} mod p^2 = 1
-    pub fn wieferich_search_st(&self, base: u64) -> WieferichPrime {
-        let supremum = self.sup.to_u64() / PRIMORIAL;
-        let infimum = self.inf.to_u64() / PRIMORIAL;
+    ///
+    /// Primes are less than 2^64, intervals exceeding 2^64 are truncated
+    pub fn wieferich_search_st(&self, base: T) -> WieferichPrime<T> {
+        let (infimum, supremum) = self.wieferich_blocks();
         // Check for small primes
 
         let mut res = vec![];
 
-        for i in [2, 3, 5, 7, 11, 13] {
-            if base.exp_residue(i - 1, i * i) == 1 {
-                res.push(i);
+        // The primes dividing the primorial, all others are in the wheel
+        if infimum == 0 {
+            for i in [2u64, 3, 5, 7, 11] {
+                if base.exp_residue(T::from(i - 1), T::from(i * i)) == T::ONE {
+                    res.push(T::from(i));
+                }
             }
         }
 
-        for i in infimum..supremum {
+        // Includes the block containing the supremum, so values up to a block above the interval may be returned
+        for i in infimum..=supremum {
             let n = PRIMORIAL * i;
 
             for i in WHEEL {
-                let p = n + i;
+                let p = T::from(n + i);
                 if base.sqr_fermat(p) {
                     if p.is_prime() {
                         res.push(p);
@@ -80,7 +103,17 @@ impl<T: Natural> Interval<T> {
         return WieferichPrime::new(base, res);
     }
 
-    pub fn wieferich_search(&self, a: u64) -> WieferichPrime {
+    /// Searches for generalised Wieferich primes to the base within the interval, see wieferich_table
+    pub fn wieferich_search(&self, a: T) -> WieferichPrime<T> {
+        self.wieferich_table(&[a]).remove(0)
+    }
+
+    /// Generalised Wieferich primes to each of the bases, in the order of the bases
+    ///
+    /// Each prime is generated once and evaluated against all bases, so searching many bases together is
+    /// considerably faster than searching them individually. Primes are less than 2^64, intervals exceeding 2^64
+    /// are truncated
+    pub fn wieferich_table(&self, bases: &[T]) -> Vec<WieferichPrime<T>> {
     
         fn mod_prime(x: u64) -> bool{
            let mut idx = 10;
@@ -93,74 +126,74 @@ impl<T: Natural> Interval<T> {
            }
            machine_prime::is_prime_wc(x)
         }
-        /*
-            let subproc = |inf: u64, sup: u64, base: u64| -> Vec<u64> {
-              let mut res = vec![];
-               for i in inf..sup{
-                  if i.is_prime(){
-                    if base.sqr_fermat(i){
-                       res.push(i);
-                    }
-                  }
-               }
-               res
-            };
-            */
-    
-        let subproc = |inf: u64, sup: u64, base: u64| -> Vec<u64> {
+
+        // Pairs of the index of the base and the prime
+        fn subproc<T: Natural>(inf: u64, sup: u64, bases: &[T]) -> Vec<(usize, T)> {
             let mut res = vec![];
             for i in inf..sup { // FIXME Replace with simple addition
                 let n = PRIMORIAL * i;
 
                 for i in WHEEL {
                     let p = n + i;
-                        if mod_prime(p){
-                        if base.sqr_fermat(p){
-                            res.push(p);
+                    // 1 is the first element of the wheel
+                    if p != 1 && mod_prime(p) {
+                        let p = T::from(p);
+                        for (idx, base) in bases.iter().enumerate() {
+                            if base.sqr_fermat(p) {
+                                res.push((idx, p));
+                            }
                         }
-                      }
+                    }
                 }
             }
             res
-        };
-/**/
-        let supremum = self.sup.to_u64() / PRIMORIAL;
-        let infimum = self.inf.to_u64() / PRIMORIAL;
+        }
+
+        let (infimum, supremum) = self.wieferich_blocks();
         // Check for small primes
 
         let mut res = vec![];
-        if infimum < 12{
-        for i in [2u64, 3, 5, 7, 11] {
-            if a.exp_residue(i - 1, i * i) == 1u64 {
-                res.push(i);
+        if infimum == 0 {
+            for i in [2u64, 3, 5, 7, 11] {
+                for (idx, base) in bases.iter().enumerate() {
+                    if base.exp_residue(T::from(i - 1), T::from(i * i)) == T::ONE {
+                        res.push((idx, T::from(i)));
+                    }
+                }
             }
         }
-       }
         let t_count = thread_count() as u64;
+        let shared = Arc::new(bases.to_vec());
 
         let mut threads = vec![];
-        // FIXME eliminate to_u64, Return error if beyond some bound
-        let stride = ((supremum - infimum) / t_count);
+        let stride = (supremum - infimum) / t_count;
         for i in 0..t_count - 1 {
             let start = infimum + i * stride;
             let stop = infimum + (i + 1) * stride;
-            let base = a.clone();
-            threads.push(std::thread::spawn(move || subproc(start, stop, a)))
+            let b_i = Arc::clone(&shared);
+            threads.push(std::thread::spawn(move || subproc(start, stop, &b_i)))
         }
 
+        let b_i = Arc::clone(&shared);
         threads.push(std::thread::spawn(move || {
-            subproc(infimum + (t_count - 1) * stride, supremum + 1, a)
+            subproc(infimum + (t_count - 1) * stride, supremum + 1, &b_i)
         }));
 
-        let mut collector = vec![];
-
         for j in threads {
-            collector.push(j.join().unwrap())
+            res.extend(j.join().unwrap())
+        }
+
+        let mut table = bases
+            .iter()
+            .map(|b| WieferichPrime::new(*b, vec![]))
+            .collect::<Vec<WieferichPrime<T>>>();
+        for (idx, p) in res {
+            table[idx].push(p);
+        }
+        for row in table.iter_mut() {
+            row.sort();
         }
-        collector.push(res);
-        let mut res = collector.into_iter().flatten().collect::<Vec<u64>>();
-        res.sort();
-        WieferichPrime::new(a, res)
+        table
     }
 
     // FIXME return a vector of Weiferich primes, instead of printing
@@ -206,7 +239,7 @@ impl<T: Natural> Interval<T> {
                 //    veccy.push(2);
                 // }
                 for p in pv_i.iter() {
-                    if p.sqr_fermat(*c_base) {
+                    if c_base.sqr_fermat(p) {
                         veccy.push(p)
                     }
                 }
@@ -219,197 +252,95 @@ impl<T: Natural> Interval<T> {
         }
     }
 
-    /*
-    pub fn eval_heuristic(&self, plist: &Primes, file: Option<std::fs::File>) -> FResult<CompVector<T>>{
-          match self.mode{
-               Search::MRHeuristic =>{
-                   for i in plist.iter(){
-
-                     let lhs = T::from(i);
-
-                     for j in [3,4,5,6].iter(){
-                  let rhs = lhs.even_complement(T::from(*j));
-
-                  if rhs.is_prime(){
-                     let (prod,flag) = lhs.overflowing_mul(rhs);
-                     // If multiplication overflowed or the prod exceeds the bound break loop
-                     if flag  || !prod.is_bounded_by(T::ZERO,self.sup){
-                       break;
-                     }
-
-                     if prod.is_bounded_by(self.inf,self.sup){
-                        out.write(&prod.to_bytes()[..]).unwrap();
-                     }
-                  }
-                }
-               }
-
-               for i in MRC_18{
-                 if T::from(i).is_bounded_by(self.inf,self.sup){
-                  out.write(&T::from(i).to_bytes()[..]).unwrap();
-                 }
-              }
-
-                out.flush().unwrap();
-
-               }
-               Search::SPKHeuristic(a) => {
-               for i in plist.iter(){
-
-                   let lhs = T::from(i);
-
-                      for j in 2..2048{
-
-                     let rhs = lhs.semi_k_complement(j);
-
-                      if rhs.is_prime(){
-                         let (prod,flag) = lhs.overflowing_mul(rhs);
-
-                          if flag || !prod.is_bounded_by(T::ZERO,self.sup){
-                             break;
-                          }
-
-                         if prod.is_bounded_by(self.inf,self.sup) && !flag{
-                           ce.push(prod);
-                         }
-                       }
-                  }
-                }
-              }
-               Search::SPKAHeuristic(a,b) =>
-          }
-    }
-     */
-    /// Calculates the set of composites generated by the heuristic
-    /// # None	   
+    /// Calculates the set of composites generated by the heuristic, see FamilyGenerator::from_search
+    /// # NotSupported
     /// Deterministic automatically fails, as this is simply all composites
     pub fn compute_heuristic(&self, locale: Option<&str>) -> FResult<CompVector<T>> {
-        if self.mode == Search::Deterministic {
-            return FResult::NotSupported;
-        }
-
-        let p_bound = self.sup.isqrt().to_u64() as usize;
-        let plist = Primes::generate_or_restore(p_bound);
-
-        match locale {
-            // Write all composites to file
-            Some(x) => {
-                let mut outfile = File::create(x).unwrap();
-                let mut out = std::io::BufWriter::new(outfile.try_clone().unwrap());
-                // Monier-Rabin Heuristic
-                for i in plist.iter() {
-                    let lhs = T::from(i);
-
-                    for j in [3, 4, 5, 6].iter() {
-                        let rhs = lhs.even_complement(T::from(*j));
-
-                        if rhs.is_prime() {
-                            let (prod, flag) = lhs.overflowing_mul(rhs);
-                            // If multiplication overflowed or the prod exceeds the bound break loop
-                            if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                break;
-                            }
-
-                            if prod.is_bounded_by(self.inf, self.sup) {
-                                out.write(&prod.to_bytes()[..]).unwrap();
-                            }
-                        }
-                    }
-                }
-
-                for i in MRC_18 {
-                    if T::from(i).is_bounded_by(self.inf, self.sup) {
-                        out.write(&T::from(i).to_bytes()[..]).unwrap();
-                    }
-                }
-
-                out.flush().unwrap();
-
-                if self.mode == Search::StrongHeuristic {
-                    for i in plist.iter() {
-                        let lhs = T::from(i);
-
-                        for j in 2..2048 {
-                            let rhs = lhs.semi_k_complement(j);
-
-                            if rhs.is_prime() {
-                                let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                                if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                    break;
-                                }
+        let gen = match FamilyGenerator::from_search(self.inf, self.sup, self.mode) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+        let metadata = format!("interval {},{} {:?}", self.inf, self.sup, self.mode);
+        gen.collect(locale, &metadata, Arc::new(|_| true))
+    }
 
-                                if prod.is_bounded_by(self.inf, self.sup) {
-                                    out.write(&prod.to_bytes()[..]).unwrap();
-                                }
-                            }
-                        }
-                    }
-                    out.flush().unwrap();
-                }
-                return FResult::Value(CompVector::from_file_internal(
-                    outfile.try_clone().unwrap(),
-                    MEMORY_MAX,
-                    UTF8_FLAG,
-                    AUTO_FLAG,
-                ));
-            } // End file write
+    /// Generates all Carmichael numbers in the interval with the number of factors and residue class of the generator
+    /// # File
+    /// If locale is Some, the Carmichael numbers are written to the file as they are computed, otherwise they are stored in memory
+    /// # Bound
+    /// Carmichael numbers are evaluated in 128-bit arithmetic, intervals exceeding 2^128 are truncated
+    pub fn generate_carmichael(
+        &self,
+        generator: &Carmichael,
+        locale: Option<&str>,
+    ) -> FResult<CompVector<T>> {
+        let mut gen = FamilyGenerator::new(self.inf, self.sup);
+        gen.push(CompositeFamily::Carmichael(generator.clone()));
+        let metadata = format!("interval {},{} {:?}", self.inf, self.sup, generator);
+        gen.collect(locale, &metadata, Arc::new(|_| true))
+    }
 
-            // Write all composites to volatile memory (i.e vector)
+    /// Generates the composites of the families in the interval, restricted to the pseudoprimes to the bases if Some
+    ///
+    /// Applicable to the k-forms of three and four factors, see CompositeFamily::k_forms, which are enumerated over
+    /// primes less than the cube or fourth root of the interval and so reach far beyond 2^64 for u128
+    /// ```
+    /// use f_analysis::{bseq, BaseSeq, CompositeFamily, Interval};
+    ///
+    /// // Chernick's Carmichael numbers (6k+1)(12k+1)(18k+1), the least strong pseudoprime to 2 and 3 among them
+    /// let chernick = vec![CompositeFamily::KForm(vec![6, 12, 18])];
+    /// let res = Interval::new(0u128, 1u128 << 60).generate_families(chernick, Some(&bseq![2, 3]), None).unwrap();
+    /// assert_eq!(res.to_vector()[0], 548962252005961);
+    /// ```
+    /// # File
+    /// If locale is Some, the composites are written to the file as they are computed, otherwise they are stored in
+    /// memory
+    pub fn generate_families(
+        &self,
+        families: Vec<CompositeFamily>,
+        bases: Option<&BaseSeq<T>>,
+        locale: Option<&str>,
+    ) -> FResult<CompVector<T>> {
+        let mut gen = FamilyGenerator::new(self.inf, self.sup);
+        // Long lists of families are summarised by their number
+        let description = match families.len() {
+            0..=4 => families.iter().map(|f| f.to_string()).collect::<Vec<String>>().join("; "),
+            len => format!("{} families", len),
+        };
+        for f in families {
+            gen.push(f);
+        }
+        match bases {
+            Some(b) => {
+                let metadata = format!("bases {} interval {},{} {}", b, self.inf, self.sup, description);
+                let b = b.clone();
+                gen.collect(locale, &metadata, Arc::new(move |x| b.primality(x)))
+            }
             None => {
-                let mut ce = Vec::<T>::new();
-
-                for i in plist.iter() {
-                    let lhs = T::from(i);
-
-                    for j in [3, 4, 5, 6].iter() {
-                        let rhs = lhs.even_complement(T::from(*j));
-
-                        if rhs.is_prime() {
-                            let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                            if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                break;
-                            }
-
-                            if prod.is_bounded_by(self.inf, self.sup) {
-                                ce.push(prod);
-                            }
-                        }
-                    }
-                }
-
-                for i in MRC_18 {
-                    if T::from(i).is_bounded_by(self.inf, self.sup) {
-                        ce.push(T::from(i))
-                    }
-                }
-
-                if self.mode == Search::StrongHeuristic {
-                    for i in plist.iter() {
-                        let lhs = T::from(i);
-
-                        for j in 2..2048 {
-                            let rhs = lhs.semi_k_complement(j);
-
-                            if rhs.is_prime() {
-                                let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                                if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                    break;
-                                }
-
-                                if prod.is_bounded_by(self.inf, self.sup) && !flag {
-                                    ce.push(prod);
-                                }
-                            }
-                        }
-                    }
-                }
-                return FResult::Value(CompVector::from_vector(ce));
+                let metadata = format!("interval {},{} {}", self.inf, self.sup, description);
+                gen.collect(locale, &metadata, Arc::new(|_| true))
             }
-        } // end match
-    } // end function
+        }
+    }
+
+    /// Constructs a hashtable of fixed bases and stride hashed bases against the composites of the strong heuristic
+    /// in the interval, see CompVector::to_hashtable_multi. Unlike to_hashtable the table is not verified against
+    /// every integer of the interval, so it is applicable to 128-bit intervals
+    pub fn to_hashtable_heuristic(
+        &self,
+        fixed: &[u64],
+        dimen: Option<usize>,
+        stride: usize,
+        multiplier: Option<u32>,
+        bound: Option<u64>,
+    ) -> FResult<HashTable> {
+        let mut x = self.clone();
+        x.set_strong_heuristic();
+        match x.compute_heuristic(None) {
+            FResult::Value(ce) => ce.to_hashtable_multi(fixed, dimen, stride, multiplier, bound),
+            failure => failure.recast(),
+        }
+    }
 
     /*
         Algorithm
@@ -619,133 +550,6 @@ impl<T: Natural> Interval<T> {
         FResult::Value(HashTable::new(veccy, dim, mul))
     }
 
-    fn generate_ce(&self, locale: Option<&str>) -> FResult<CompVector<T>> {
-        if self.mode == Search::Deterministic {
-            return FResult::NotSupported;
-        }
-
-        let p_bound = self.sup.isqrt().to_u64() as usize;
-        let plist = Primes::generate_or_restore(p_bound);
-
-        match locale {
-            // Write all composites to file
-            Some(x) => {
-                let mut outfile = File::create(x).unwrap();
-                let mut out = std::io::BufWriter::new(outfile.try_clone().unwrap());
-                // Monier-Rabin Heuristic
-                for i in plist.iter() {
-                    let lhs = T::from(i);
-
-                    for j in [3, 4, 6].iter() {
-                        let rhs = lhs.even_complement(T::from(*j));
-
-                        if rhs.is_prime() {
-                            let (prod, flag) = lhs.overflowing_mul(rhs);
-                            // If multiplication overflowed or the prod exceeds the bound break loop
-                            if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                break;
-                            }
-
-                            if prod.is_bounded_by(self.inf, self.sup) {
-                                out.write(&prod.to_bytes()[..]).unwrap();
-                            }
-                        }
-                    }
-                }
-
-                for i in MRC_18 {
-                    if T::from(i).is_bounded_by(self.inf, self.sup) {
-                        out.write(&T::from(i).to_bytes()[..]).unwrap();
-                    }
-                }
-
-                out.flush().unwrap();
-
-                if self.mode == Search::StrongHeuristic {
-                    for i in plist.iter() {
-                        let lhs = T::from(i);
-
-                        for j in 2..64 {
-                            let rhs = lhs.semi_k_complement(j);
-
-                            if rhs.is_prime() {
-                                let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                                if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                    break;
-                                }
-
-                                if prod.is_bounded_by(self.inf, self.sup) {
-                                    out.write(&prod.to_bytes()[..]).unwrap();
-                                }
-                            }
-                        }
-                    }
-                    out.flush().unwrap();
-                }
-                return FResult::Value(CompVector::from_file_internal(
-                    outfile.try_clone().unwrap(),
-                    MEMORY_MAX,
-                    UTF8_FLAG,
-                    AUTO_FLAG,
-                ));
-            } // End file write
-
-            // Write all composites to volatile memory (i.e vector)
-            None => {
-                let mut ce = Vec::<T>::new();
-
-                for i in plist.iter() {
-                    let lhs = T::from(i);
-                    for j in [3, 4, 5, 6].iter() {
-                        let rhs = lhs.even_complement(T::from(*j));
-
-                        if rhs.is_prime() {
-                            let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                            if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                break;
-                            }
-
-                            if prod.is_bounded_by(self.inf, self.sup) {
-                                ce.push(prod);
-                            }
-                        }
-                    }
-                }
-
-                for i in MRC_18 {
-                    if T::from(i).is_bounded_by(self.inf, self.sup) {
-                        ce.push(T::from(i))
-                    }
-                }
-
-                if self.mode == Search::StrongHeuristic {
-                    for i in plist.iter() {
-                        let lhs = T::from(i);
-
-                        for j in 2..2048 {
-                            let rhs = lhs.semi_k_complement(j);
-
-                            if rhs.is_prime() {
-                                let (prod, flag) = lhs.overflowing_mul(rhs);
-
-                                if flag || !prod.is_bounded_by(T::ZERO, self.sup) {
-                                    break;
-                                }
-
-                                if prod.is_bounded_by(self.inf, self.sup) && !flag {
-                                    ce.push(prod);
-                                }
-                            }
-                        }
-                    }
-                }
-                return FResult::Value(CompVector::from_vector(ce));
-            }
-        } // end match
-    }
-
     pub fn generate_fermat<F: WeakFermat>(&self) -> CompVector<T> {
         let subproc = |mut start: T, fstride: u64| -> Vec<T> {
             let mut veccy = Vec::new();
@@ -787,6 +591,58 @@ impl<T: Natural> Interval<T> {
         CompVector::<T>::from_vector(res)
     }
 
+    /// Composites within the interval that satisfy a filter expression constructed at runtime
+    ///
+    /// Deterministic mode evaluates every integer in the interval, heuristic modes filter the composites produced by
+    /// compute_heuristic
+    pub fn filter_expr(&self, expr: &FilterExpr<T>) -> FResult<CompVector<T>> {
+        if self.mode != Search::Deterministic {
+            return match self.compute_heuristic(None) {
+                FResult::Value(ce) => ce.filter_expr(expr, None),
+                failure => failure,
+            };
+        }
+
+        let t_count = thread_count() as u64;
+        // FIXME eliminate to_u64, Return error if beyond some bound
+        let stride = self
+            .sup
+            .finite_sub(self.inf)
+            .euclidean(T::from(t_count))
+            .0
+            .to_u64();
+
+        let mut threads = vec![];
+        for i in 0..t_count {
+            let fexpr = expr.clone();
+            let mut start = self.inf;
+            start.inc_by(stride * i);
+            // The final thread also evaluates the remainder of the interval
+            let length = if i == t_count - 1 {
+                self.sup.finite_sub(start).to_u64()
+            } else {
+                stride
+            };
+            threads.push(std::thread::spawn(move || {
+                let mut veccy = Vec::new();
+                for _ in 0..length {
+                    if fexpr.check(start) && start > T::ONE && !start.is_prime() {
+                        veccy.push(start)
+                    }
+                    start.successor();
+                }
+                veccy
+            }))
+        }
+
+        let mut collector = vec![];
+        for j in threads {
+            collector.push(j.join().unwrap())
+        }
+        let res = collector.into_iter().flatten().collect::<Vec<T>>();
+        FResult::Value(CompVector::<T>::from_vector(res))
+    }
+
     /// Generate Fermat Pseudoprimes runtime base
     pub fn generate_fermat_rt(&self, base: T) -> CompVector<T> {
         let subproc = |mut start: T, fstride: u64, base: T, fact: Vec<u64>| -> Vec<T> {
@@ -804,9 +660,10 @@ impl<T: Natural> Interval<T> {
             return veccy;
         };
 
+        // Evaluates fstride odd integers
         let subproc_two = |mut start: T, fstride: u64, base: T, fact: Vec<u64>| -> Vec<T> {
             let mut veccy = Vec::new();
-            for _ in 0..fstride / 2 {
+            for _ in 0..fstride {
                 if !start.div_vector(&fact[..]) {
                     if start.fermat(base) {
                         if !start.is_prime() {
@@ -834,17 +691,27 @@ impl<T: Natural> Interval<T> {
             let sf_i = sf.clone();
             let mut start = self.inf;
             start.inc_by(stride * (i as u64));
+            // The final thread also evaluates the remainder of the interval
+            let length = if i == t_count - 1 {
+                self.sup.finite_sub(start).to_u64()
+            } else {
+                stride
+            };
             if base.is_even() {
-                if start.is_even() {
+                // Number of odd integers in [start;start+length)
+                let odd_count = if start.is_even() {
                     start.successor();
-                }
+                    length / 2
+                } else {
+                    length.div_ceil(2)
+                };
 
                 threads.push(std::thread::spawn(move || {
-                    subproc_two(start, stride, base, sf_i)
+                    subproc_two(start, odd_count, base, sf_i)
                 }))
             } else {
                 threads.push(std::thread::spawn(move || {
-                    subproc(start, stride, base, sf_i)
+                    subproc(start, length, base, sf_i)
                 }))
             }
         }
@@ -945,4 +812,88 @@ impl Interval<u64> {
         k.sort();
         k
     }
+
+    /// Enumerates all base-a Fermat pseudoprimes in the interval using R.G.E Pinch's algorithm
+    ///
+    /// Pseudoprimes are split by their largest prime factor p. If p > sqrt(sup) then the cofactor k satisfies k = 1 mod ord(p) and
+    /// ord(p) < sqrt(sup), so p is computed from the primes of small order. Otherwise all prime factors are at most sqrt(sup)
+    /// and the pseudoprime is constructed from the orders of the small primes
+    ///
+    /// The cost grows roughly linearly with sup, see SOSet::from_order. Base 2 to 10^12 takes about 5 minutes on a single
+    /// thread, so intervals much beyond 10^14 are impractical
+    /// # File
+    /// If locale is Some, the pseudoprimes are written to the file in ascending order, otherwise they are stored in memory
+    pub fn generate_fermat_pinch(&self, a: u64, locale: Option<&str>) -> FResult<CompVector<u64>> {
+        if a < 2 {
+            return FResult::Err("Base must be greater than 1");
+        }
+
+        if self.sup < 5 {
+            return FResult::Value(CompVector::from_vector(vec![]));
+        }
+
+        let root = self.sup.isqrt();
+
+        let mut pseudos = SmallOrd::initialise_prime(a, root).smooth_pseudoprimes(self.inf, self.sup);
+        let large = SOSet::from_order(a, self.sup).cofactor_pseudoprimes(self.inf, self.sup);
+        // The arithmetic progressions of the small search may also reach pseudoprimes with a large prime factor
+        pseudos.extend_from_slice(&large[..]);
+        pseudos.sort();
+        pseudos.dedup();
+
+        match locale {
+            Some(x) => {
+                let metadata = format!("interval {},{} fermat base {} (Pinch)", self.inf, self.sup, a);
+                let mut out = match BinaryWriter::<u64>::create(x, &metadata) {
+                    Ok(f) => f,
+                    Err(message) => return FResult::IOError(message),
+                };
+
+                for i in pseudos {
+                    if let Err(message) = out.write(i) {
+                        return FResult::IOError(message);
+                    }
+                }
+
+                let outfile = match out.finish() {
+                    Ok(f) => f,
+                    Err(message) => return FResult::IOError(message),
+                };
+
+                FResult::Value(CompVector::from_file_internal(
+                    outfile,
+                    MEMORY_MAX,
+                    UTF8_FLAG,
+                    AUTO_FLAG,
+                ))
+            }
+            None => FResult::Value(CompVector::from_vector(pseudos)),
+        }
+    }
+}
+
+// a^e mod n by square and multiply, independent of the library arithmetic
+#[cfg(test)]
+fn pow_mod(a: u64, mut e: u64, n: u64) -> u64 {
+    let (mut acc, mut base, n) = (1u128 % n as u128, a as u128 % n as u128, n as u128);
+    while e > 0 {
+        if e & 1 == 1 {
+            acc = acc * base % n;
+        }
+        base = base * base % n;
+        e >>= 1;
+    }
+    acc as u64
+}
+
+#[test]
+fn fermat_pinch() {
+    let sup = 1_000_000u64;
+    for a in 2..32u64 {
+        let pinch = Interval::new(0u64, sup).generate_fermat_pinch(a, None).unwrap().to_vector();
+        let brute = (4..sup)
+            .filter(|n| !n.is_prime() && pow_mod(a, *n - 1, *n) == 1)
+            .collect::<Vec<u64>>();
+        assert_eq!(pinch, brute, "base {}", a);
+    }
 }
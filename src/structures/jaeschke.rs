@@ -0,0 +1,339 @@
+use crate::car::MRC_18;
+use crate::structures::{base::sprp_reduced, BaseSeq};
+use crate::{FResult, Natural};
+
+/*
+   Rigorous bounds for strong pseudoprimes to arbitrary bases, as computed by Jaeschke for the first primes
+
+   A candidate pseudoprime is first found by searching the tabulated composites and the Monier-Rabin semiprimes,
+   which are the least pseudoprime for most base sets. Every strong pseudoprime less than the candidate is then
+   enumerated by the algorithm of Sorenson and Webster, so the least of these and the candidate is the bound
+*/
+
+// Memory bound of the residue classes of the Monier-Rabin search
+const RESIDUE_MEMORY: u64 = 1 << 27;
+
+/// Family of composites searched by BaseSeq::jaeschke_bound
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundFamily {
+    /// Carmichael numbers pqr with p, q and r all 3 mod 4, tabulated up to 10^18
+    Tabulated,
+    /// Semiprimes (k+1)(2k+1) where 2k+1 lies in the residue classes permitted by the bases
+    MonierRabin,
+    /// Composites whose prime factors all share a signature and are at most the square root of the bound
+    Signature,
+    /// Composites with a prime factor p greater than the square root of the bound, sieved by the orders modulo p
+    LargeFactor,
+}
+
+impl std::fmt::Display for BoundFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoundFamily::Tabulated => write!(f, "tabulated"),
+            BoundFamily::MonierRabin => write!(f, "monier-rabin"),
+            BoundFamily::Signature => write!(f, "signature"),
+            BoundFamily::LargeFactor => write!(f, "large-factor"),
+        }
+    }
+}
+
+/// Extent to which a family of composites was searched
+#[derive(Clone, Debug)]
+pub struct FamilyCover {
+    family: BoundFamily,
+    sup: u128,
+    exhaustive: bool,
+    evaluated: u64,
+    found: u64,
+}
+
+impl FamilyCover {
+    pub fn family(&self) -> BoundFamily {
+        self.family
+    }
+
+    /// Members of the family less than sup were searched
+    pub fn sup(&self) -> u128 {
+        self.sup
+    }
+
+    /// Whether every member of the family less than sup was evaluated
+    pub fn exhaustive(&self) -> bool {
+        self.exhaustive
+    }
+
+    /// Extent of the search in the units of the family. Composites for the tabulated family, residue class scalars
+    /// for Monier-Rabin, primes for signatures and the number of orders for large factors
+    pub fn evaluated(&self) -> u64 {
+        self.evaluated
+    }
+
+    /// Number of strong pseudoprimes to the bases found in the family
+    pub fn found(&self) -> u64 {
+        self.found
+    }
+}
+
+/// Least strong pseudoprime to a set of bases, and the families of composites searched to prove it
+///
+/// The bound is proven if the signature and large factor families are exhaustive to at least the bound, as together
+/// they contain every strong pseudoprime
+#[derive(Clone, Debug)]
+pub struct BoundCertificate {
+    bases: Vec<u64>,
+    bound: u128,
+    candidate: u128,
+    source: BoundFamily,
+    cover: Vec<FamilyCover>,
+}
+
+impl BoundCertificate {
+    pub fn bases(&self) -> &[u64] {
+        &self.bases
+    }
+
+    /// The least composite that is a strong pseudoprime to every base
+    pub fn bound(&self) -> u128 {
+        self.bound
+    }
+
+    /// Pseudoprime found by the search of the source family, an upper bound before the enumeration
+    pub fn candidate(&self) -> u128 {
+        self.candidate
+    }
+
+    /// Family the candidate belongs to
+    pub fn source(&self) -> BoundFamily {
+        self.source
+    }
+
+    pub fn families(&self) -> &[FamilyCover] {
+        &self.cover[..]
+    }
+
+    /// Checks the certificate without repeating the enumeration
+    ///
+    /// The bound and candidate are re-verified as composite strong pseudoprimes to each base with the bound at most the
+    /// candidate, the candidate is re-verified as a member of its source family, and the signature and large factor
+    /// families must be exhaustive to at least the bound. A bound less than the candidate must have been found by them
+    pub fn check(&self) -> bool {
+        let cover = |family: BoundFamily| self.cover.iter().find(|c| c.family == family);
+        let covered = |family: BoundFamily| cover(family).is_some_and(|c| c.exhaustive && c.sup >= self.bound);
+        let pseudoprime = |n: u128| n > 1 && !n.is_prime() && self.bases.iter().all(|b| sprp_reduced(n, *b));
+
+        let member = match self.source {
+            BoundFamily::Tabulated => {
+                self.candidate < 1 << 64 && MRC_18.binary_search(&(self.candidate as u64)).is_ok()
+            }
+            // n = q(q+1)/2 with q and (q+1)/2 prime
+            BoundFamily::MonierRabin => {
+                let q = (self.candidate * 8 + 1).isqrt() >> 1;
+                q * (q + 1) / 2 == self.candidate && q.is_prime() && q.div_ceil(2).is_prime()
+            }
+            _ => false,
+        };
+        let found = [BoundFamily::Signature, BoundFamily::LargeFactor]
+            .iter()
+            .map(|f| cover(*f).map_or(0, |c| c.found))
+            .sum::<u64>();
+
+        pseudoprime(self.bound)
+            && pseudoprime(self.candidate)
+            && self.bound <= self.candidate
+            && member
+            && (self.bound == self.candidate || found > 0)
+            && covered(BoundFamily::Signature)
+            && covered(BoundFamily::LargeFactor)
+    }
+}
+
+impl std::fmt::Display for BoundCertificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bases = self.bases.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(",");
+        writeln!(f, "bases: {}", bases)?;
+        writeln!(f, "bound: {}", self.bound)?;
+        writeln!(f, "candidate: {} {}", self.candidate, self.source)?;
+        for c in self.cover.iter() {
+            let extent = if c.exhaustive { "exhaustive" } else { "partial" };
+            writeln!(
+                f,
+                "family: {} sup: {} {} evaluated: {} found: {}",
+                c.family, c.sup, extent, c.evaluated, c.found
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl BaseSeq<u64> {
+    /*
+       In: Inclusive bound of the composites, zero if there is none
+       Out: Cover of the Monier-Rabin semiprimes and the least found
+
+       The height of the scalars is doubled until a semiprime is found or the semiprimes exceed the bound, every
+       semiprime with 2k+1 less than the height times the ring is evaluated. Semiprimes are q(q+1)/2 for q = 2k+1
+    */
+    fn monier_rabin_cover(&self, bound: u128) -> (FamilyCover, Option<u128>) {
+        let residues = self.mr_residues(RESIDUE_MEMORY);
+        let ring = residues.ring as u128;
+        let semiprime = |q: u128| q * (q + 1) / 2;
+
+        let mut floor = 0u64;
+        let mut ceiling = 1u64;
+
+        loop {
+            let found = self
+                .mr_semiprimes_par(&residues, floor, ceiling)
+                .q_iter()
+                .map(|rhs| *rhs * ((*rhs >> 1) + 1))
+                .filter(|n| self.sprp_128(*n))
+                .collect::<Vec<u128>>();
+            let least = found.iter().copied().min();
+
+            let sup = ceiling as u128 * ring;
+            let exceeded = bound != 0 && semiprime(sup) > bound;
+            // Semiprimes beyond 2^125 could not be enumerated below, a search stopped here without a semiprime is partial
+            if least.is_some() || exceeded || sup >= 1 << 63 {
+                let cover = FamilyCover {
+                    family: BoundFamily::MonierRabin,
+                    sup: semiprime(sup),
+                    exhaustive: least.is_some() || exceeded,
+                    evaluated: ceiling,
+                    found: found.len() as u64,
+                };
+                return (cover, least);
+            }
+            floor = ceiling;
+            ceiling <<= 1;
+        }
+    }
+
+    /// Computes the least strong pseudoprime to the bases with a certificate of the families of composites searched
+    ///
+    /// A candidate is found among the tabulated composites and Monier-Rabin semiprimes, and every strong pseudoprime
+    /// less than it is enumerated by signature, see sorenson_webster. The enumeration dominates the cost and grows
+    /// with the candidate
+    /// ```
+    /// use f_analysis::{bseq, BaseSeq};
+    ///
+    /// let cert = bseq![2, 3].jaeschke_bound().unwrap();
+    /// assert_eq!(cert.bound(), 1373653);
+    /// assert!(cert.check());
+    /// ```
+    /// Bases are reduced modulo each composite, a composite dividing a base is not a pseudoprime to it
+    /// # Err
+    /// The sequence is empty or has a base less than 2
+    /// # NoCandidate
+    /// No candidate was found in the tabulated composites or Monier-Rabin semiprimes less than 2^125
+    pub fn jaeschke_bound(&self) -> FResult<BoundCertificate> {
+        if self.len() == 0 {
+            return FResult::Err("Empty base sequence");
+        }
+        if self.iter().any(|b| *b < 2) {
+            return FResult::Err("Bases must be at least 2");
+        }
+
+        let mut cover = vec![];
+
+        let tabulated = MRC_18.iter().position(|x| self.sprp_128(*x as u128));
+        cover.push(FamilyCover {
+            family: BoundFamily::Tabulated,
+            sup: tabulated.map_or(MRC_18[MRC_18.len() - 1] as u128 + 1, |idx| MRC_18[idx] as u128 + 1),
+            exhaustive: false,
+            evaluated: tabulated.map_or(MRC_18.len(), |idx| idx + 1) as u64,
+            found: tabulated.iter().count() as u64,
+        });
+        let tabulated = tabulated.map(|idx| MRC_18[idx] as u128);
+
+        let (mr, semiprime) = self.monier_rabin_cover(tabulated.unwrap_or(0));
+        cover.push(mr);
+
+        let (candidate, source) = match (tabulated, semiprime) {
+            (Some(t), Some(m)) if t < m => (t, BoundFamily::Tabulated),
+            (_, Some(m)) => (m, BoundFamily::MonierRabin),
+            (Some(t), None) => (t, BoundFamily::Tabulated),
+            (None, None) => return FResult::NoCandidate,
+        };
+
+        let enumeration = match self.sw_cover(candidate) {
+            FResult::Value(x) => x,
+            failure => return failure.recast(),
+        };
+
+        let pseudoprimes = |x: &[u128]| {
+            let mut res = x
+                .iter()
+                .copied()
+                .filter(|n| !n.is_prime() && self.sprp_128(*n))
+                .collect::<Vec<u128>>();
+            res.sort();
+            res.dedup();
+            res
+        };
+        let small = pseudoprimes(&enumeration.small[..]);
+        let large = pseudoprimes(&enumeration.large[..]);
+
+        cover.push(FamilyCover {
+            family: BoundFamily::Signature,
+            sup: candidate,
+            exhaustive: true,
+            evaluated: enumeration.primes,
+            found: small.len() as u64,
+        });
+        cover.push(FamilyCover {
+            family: BoundFamily::LargeFactor,
+            sup: candidate,
+            exhaustive: true,
+            evaluated: enumeration.lambdas,
+            found: large.len() as u64,
+        });
+
+        let bound = small.iter().chain(large.iter()).copied().min().unwrap_or(candidate).min(candidate);
+
+        FResult::Value(BoundCertificate {
+            bases: self.iter().copied().collect(),
+            bound,
+            candidate,
+            source,
+            cover,
+        })
+    }
+}
+
+#[test]
+fn jaeschke_single_base() {
+    use crate::structures::base::naive_sprp;
+
+    for b in 2..64u64 {
+        let least = (9u64..)
+            .step_by(2)
+            .find(|n| !n.is_prime() && naive_sprp(*n, b))
+            .unwrap();
+        let cert = BaseSeq::new(vec![b]).jaeschke_bound().unwrap();
+        assert_eq!(cert.bound(), least as u128, "base {}", b);
+        assert!(cert.check(), "base {}", b);
+    }
+    assert!(matches!(BaseSeq::new(vec![2u64, 1]).jaeschke_bound(), FResult::Err(_)));
+    assert!(matches!(BaseSeq::new(vec![0u64]).jaeschke_bound(), FResult::Err(_)));
+}
+
+#[test]
+fn jaeschke_check() {
+    let cert = BaseSeq::new(vec![2u64, 3]).jaeschke_bound().unwrap();
+    assert!(cert.check());
+    assert_eq!(cert.source(), BoundFamily::MonierRabin);
+    assert!(cert.families().iter().all(|c| c.exhaustive() || c.family() == BoundFamily::Tabulated));
+
+    let tampered = |f: &dyn Fn(&mut BoundCertificate)| {
+        let mut t = cert.clone();
+        f(&mut t);
+        t.check()
+    };
+    // Not a strong pseudoprime to base 3
+    assert!(!tampered(&|t| t.bound = 2047));
+    // Not a Monier-Rabin semiprime, nor tabulated
+    assert!(!tampered(&|t| t.candidate = 3215031751));
+    assert!(!tampered(&|t| t.source = BoundFamily::Tabulated));
+    assert!(!tampered(&|t| t.cover[2].exhaustive = false));
+    assert!(!tampered(&|t| t.cover[3].sup = t.bound - 1));
+}
@@ -1,6 +1,12 @@
 use crate::natural::montcore::NTCore;
+use crate::search::thread_count;
 use crate::structures::store::Persistent;
 use crate::{CompVector, FResult, Natural, Primes};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Maximum length of a residue class that is sieved instead of factorising base^ord - 1
+pub(crate) const SIEVE_MAX: u64 = 1 << 24;
 
 /*
    Preliminary structures to evaluate Fermat pseudoprimes
@@ -140,6 +146,113 @@ impl SOSet {
         res
     }
 
+    /// Calculates all primes p in the interval (sqrt(bound);bound/(ord+1)) grouped by their exact multiplicative order
+    ///
+    /// These are the only primes greater than sqrt(bound) that may divide a pseudoprime less than bound. Primes with orders
+    /// such that base^ord - 1 < 2^128 are calculated by factorisation, and the rest by sieving the residue class 1 mod ord
+    /// # Bound
+    /// Sieving an order costs O(bound/ord), so the orders too large to factorise cost O(bound*log2(base)/128) in total.
+    /// This is practical to about 10^14, far short of 2^64
+    pub fn from_order(base: u64, bound: u64) -> Self {
+        let root = bound.isqrt();
+        let ord_idx = Arc::new(AtomicU64::new(1));
+        let tc = thread_count();
+
+        let mut thread_vec: Vec<std::thread::JoinHandle<Vec<SharedOrd>>> = Vec::new();
+
+        for _ in 0..tc {
+            let o_i = Arc::clone(&ord_idx);
+
+            thread_vec.push(std::thread::spawn(move || {
+                let mut res = vec![];
+                loop {
+                    let ord = o_i.fetch_add(1, Ordering::SeqCst);
+                    // Largest prime such that a cofactor of 1 + ord exists
+                    let hi = (bound - 1) / (ord + 1);
+
+                    if hi <= root {
+                        break;
+                    }
+
+                    let mut ordp = SharedOrd::new_ord(ord);
+                    // Primes are odd so an odd order restricts them to 1 mod 2*ord
+                    let stride = if ord & 1 == 1 { ord * 2 } else { ord };
+                    // Factorising large Cunningham numbers is slower than sieving a short residue class
+                    let factorable = if hi / stride < SIEVE_MAX {
+                        None
+                    } else {
+                        (base as u128).checked_pow(ord as u32)
+                    };
+
+                    match factorable {
+                        Some(x) => {
+                            let fctr = (x - 1).factor().unwrap();
+                            for (p, _) in fctr.pair_iter() {
+                                if *p > root as u128 && *p <= hi as u128 {
+                                    let p = *p as u64;
+                                    // Pollard-Brent may return composite factors
+                                    if p.is_prime() && p.p_ord(base) == ord {
+                                        ordp.append(p);
+                                    }
+                                }
+                            }
+                        }
+                        None => {
+                            let mut p = (root / stride) * stride + 1;
+
+                            while p <= hi {
+                                if p > root && base.exp_unit(ord, p) && p.is_prime() && p.p_ord(base) == ord {
+                                    ordp.append(p);
+                                }
+                                p += stride;
+                            }
+                        }
+                    }
+
+                    if !ordp.is_empty() {
+                        res.push(ordp);
+                    }
+                }
+                res
+            }));
+        }
+
+        let mut values = SOSet::new(base, bound);
+
+        for handle in thread_vec {
+            for ordp in handle.join().unwrap() {
+                values.append(ordp);
+            }
+        }
+        values.elements.sort_by_key(|x| x.ord);
+        values
+    }
+
+    /// Pseudoprimes n = pk in (inf;sup) where p is an element of the set and k = 1 mod ord(p)
+    ///
+    /// If the set was constructed by from_order this is every pseudoprime whose largest prime factor exceeds sqrt(sup)
+    pub fn cofactor_pseudoprimes(&self, inf: u64, sup: u64) -> Vec<u64> {
+        let mut values = vec![];
+
+        for i in self.elements.iter() {
+            for p in i.primes.iter() {
+                let k_max = (sup - 1) / *p;
+                let mut k = 1 + i.ord;
+
+                while k <= k_max {
+                    let n = *p * k;
+
+                    if n > inf && Natural::fermat(&n, self.a) {
+                        values.push(n);
+                    }
+                    k += i.ord;
+                }
+            }
+        }
+        values.sort();
+        values
+    }
+
     pub fn pseudoprimes(&self) -> Vec<u64> {
         let mut values = std::collections::HashSet::new();
 
@@ -164,10 +277,17 @@ impl SOSet {
 
 impl SmallOrd {
     pub fn initialise_prime(a: u64, p_bound: u64) -> Self {
-        let plist = Primes::generate_or_restore(p_bound as usize);
+        let plist = Primes::generate_or_restore(p_bound.max(16) as usize);
         let mut elements = vec![];
+        // The prime list starts at 3, and the order of an odd base over 2 is always 1
+        if a & 1 == 1 && p_bound >= 2 {
+            elements.push((2 << 32) + 1);
+        }
 
         for i in plist.iter() {
+            if i > p_bound {
+                break;
+            }
             if i.gcd(a) == 1 {
                 elements.push((i << 32) + i.p_ord(a));
             } else {
@@ -564,6 +684,147 @@ impl SmallOrd {
         res
     }
 
+    // Extends the product m of prime powers by smaller primes, lambda is the lcm of their orders
+    // Every pseudoprime divisible by m is of the form mk where k = m^-1 mod lambda, so if the arithmetic progression
+    // is shorter than the remaining primes it is evaluated directly
+    fn descend(&self, idx: usize, m: u64, lambda: u64, (inf, sup): (u64, u64), out: &mut Vec<u64>) {
+        const MASK: u64 = 0xFFFFFFFF;
+
+        if lambda >= sup {
+            return;
+        }
+        // m itself is a pseudoprime, primality is checked by the caller
+        if (m - 1).is_multiple_of(lambda) && m > inf && m < sup {
+            out.push(m);
+        }
+
+        let k_max = (sup - 1) / m;
+
+        if idx == 0 || k_max < 2 {
+            return;
+        }
+
+        if k_max / lambda <= idx as u64 {
+            let mut k = if lambda == 1 {
+                1
+            } else {
+                m.mul_inverse(lambda).unwrap() % lambda
+            };
+
+            while k < 2 {
+                k += lambda;
+            }
+
+            while k <= k_max {
+                let n = m * k;
+                if n > inf && Natural::fermat(&n, self.a) {
+                    out.push(n);
+                }
+                k += lambda;
+            }
+            return;
+        }
+
+        for (jdx, el) in self.elements[..idx].iter().enumerate() {
+            let (q, qord) = (el >> 32, el & MASK);
+
+            if q > k_max {
+                break;
+            }
+            // Skip primes that divide the base, or q | lambda or gcd(ord(q),m) != 1 which contradict m*q | n and lambda | n-1
+            if q == 0 || lambda.is_multiple_of(q) || qord.gcd(m) != 1 {
+                continue;
+            }
+
+            let n_lambda = match lambda.lcm(qord) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let mut qpow = q;
+            // Higher powers are only possible for generalised Wieferich primes
+            loop {
+                self.descend(jdx, m * qpow, n_lambda, (inf, sup), out);
+
+                match qpow.checked_mul(q) {
+                    Some(x) => {
+                        if x > k_max || self.a.exp_residue(qord, x) != 1 {
+                            break;
+                        }
+                        qpow = x;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Pseudoprimes in (inf;sup) whose prime factors are all elements of the set
+    ///
+    /// If the set was constructed by initialise_prime to sqrt(sup) this is every pseudoprime whose largest prime factor
+    /// does not exceed sqrt(sup)
+    pub fn smooth_pseudoprimes(&self, inf: u64, sup: u64) -> Vec<u64> {
+        const MASK: u64 = 0xFFFFFFFF;
+
+        let ord = Arc::new(self.clone());
+        let idx = Arc::new(AtomicUsize::new(0usize));
+        let tc = thread_count();
+
+        let mut thread_vec: Vec<std::thread::JoinHandle<Vec<u64>>> = Vec::new();
+
+        // Threads take the largest prime factor from a shared index
+        for _ in 0..tc {
+            let ord_i = Arc::clone(&ord);
+            let idx_i = Arc::clone(&idx);
+
+            thread_vec.push(std::thread::spawn(move || {
+                let mut res = vec![];
+                loop {
+                    let c_idx = idx_i.fetch_add(1, Ordering::SeqCst);
+
+                    if c_idx >= ord_i.elements.len() {
+                        break;
+                    }
+
+                    let el = ord_i.elements[c_idx];
+                    let (p, pord) = (el >> 32, el & MASK);
+
+                    if p == 0 || p >= sup {
+                        continue;
+                    }
+
+                    let mut ppow = p;
+
+                    loop {
+                        ord_i.descend(c_idx, ppow, pord, (inf, sup), &mut res);
+
+                        match ppow.checked_mul(p) {
+                            Some(x) => {
+                                if x >= sup || ord_i.a.exp_residue(pord, x) != 1 {
+                                    break;
+                                }
+                                ppow = x;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                res
+            }));
+        }
+
+        let mut values = vec![];
+
+        for handle in thread_vec {
+            values.extend_from_slice(&handle.join().unwrap()[..]);
+        }
+
+        values.sort();
+        values.dedup();
+        values.retain(|x| !x.is_prime());
+        values
+    }
+
     pub fn iter(&self) -> std::slice::Iter<'_, u64> {
         self.elements.iter()
     }
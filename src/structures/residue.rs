@@ -407,16 +407,20 @@ impl Persistent for ResidueClass{
             Ok(mut out) => {
                 let mut w = std::io::BufWriter::new(out);
                 
-                match w.write(&self.ring.to_bytes()[..]) {
+                match w.write_all(&self.ring.to_bytes()[..]) {
                     Ok(_) => (),
                     Err(message) => return FResult::IOError(message),
                 }
                 for i in self.elements.iter(){
-                   match w.write(&i.to_bytes()[..]){
+                   match w.write_all(&i.to_bytes()[..]){
                      Ok(_) => (),
                      Err(message) => return FResult::IOError(message),
                    }
                 }
+                // Errors when flushing on drop would otherwise be ignored
+                if let Err(message) = w.flush(){
+                   return FResult::IOError(message);
+                }
             }
             Err(message) => return FResult::IOError(message),
         }
@@ -424,32 +428,15 @@ impl Persistent for ResidueClass{
    }
    
    fn from_persistent(locale: &str) -> FResult<Self>{
-        use std::fs::File;
-        use std::io::Read;
-          let mut ring = 0u64;
-          let mut res = vec![];
-        match File::open(locale) {
-            Ok(mut out) => {
-                let mut r = std::io::BufReader::new(out);
-                let mut interim = [0u8;8];
-                
-                match r.read(&mut interim){
-                    Ok(_) => ring = u64::from_bytes(&interim),
-                    Err(message) => return FResult::IOError(message),
-                }
-                
-                loop {
-                match r.read(&mut interim[..]){
-                   Ok(totalbytes) => {
-                     if totalbytes == 0{
-                        break;
-                        }
-                        res.push(u64::from_bytes(&interim));
-                     }
-                   Err(message) => return FResult::IOError(message),
+        match std::fs::read(locale) {
+            Ok(bytes) => {
+                // The ring followed by the elements, all 8 bytes
+                if bytes.len() < 8 || bytes.len() % 8 != 0{
+                   return FResult::Err("Residue class file is truncated");
                 }
-               }
-               return FResult::Value(Self::new(res,ring));
+                let ring = u64::from_bytes(&bytes[..8]);
+                let res = bytes[8..].chunks_exact(8).map(u64::from_bytes).collect::<Vec<u64>>();
+                FResult::Value(Self::new(res,ring))
                }
              Err(message) => FResult::IOError(message)  
               } 
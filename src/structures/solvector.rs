@@ -1,4 +1,4 @@
-use crate::{FResult,CompVector,Natural,Persistent,cvec,filter::GenericFilter,Stats};
+use crate::{FResult,CompVector,Natural,Persistent,cvec,filter::{FilterExpr,GenericFilter},Stats};
 
 
 //CompVector with the number of fermat solutions to each integer
@@ -84,6 +84,27 @@ impl<T: Natural> SolVector<T>{
       Self::new(v)
   }
   
+  pub fn nfilter_expr(&self, expr: &FilterExpr<T>) -> Self{
+      let mut v = vec![];
+      for i in self.elements.iter(){
+          if !expr.check(i.0){
+            v.push(*i);
+          }
+      }
+      Self::new(v)
+  }
+  
+  /// Filters by an expression constructed at runtime
+  pub fn filter_expr(&self, expr: &FilterExpr<T>) -> Self{
+      let mut v = vec![];
+      for i in self.elements.iter(){
+          if expr.check(i.0){
+            v.push(*i);
+          }
+      }
+      Self::new(v)
+  }
+  
   pub fn filter_generic<F: GenericFilter>(&self) -> Self{
       let mut v = vec![];
       for i in self.elements.iter(){
@@ -2,31 +2,42 @@ use crate::natural::ftrait::Natural;
 
 /// Set of generalised Wieferich primes. Primes such that a^(p-1) mod p^2 = 1
 #[derive(Debug, Clone)]
-pub struct WieferichPrime {
-    base: u64,
-    primes: Vec<u64>,
+pub struct WieferichPrime<T: Natural = u64> {
+    base: T,
+    primes: Vec<T>,
 }
 
-impl WieferichPrime {
-    pub(crate) fn new(base: u64, primes: Vec<u64>) -> Self {
+impl<T: Natural> WieferichPrime<T> {
+    pub(crate) fn new(base: T, primes: Vec<T>) -> Self {
         Self { base, primes }
     }
 
-    pub fn base(&self) -> u64 {
+    pub(crate) fn push(&mut self, p: T) {
+        self.primes.push(p);
+    }
+
+    pub(crate) fn sort(&mut self) {
+        self.primes.sort();
+    }
+
+    pub fn base(&self) -> T {
         self.base
     }
 
-    pub fn prime_set(&self) -> Vec<u64> {
+    pub fn prime_set(&self) -> Vec<T> {
         self.primes.clone()
     }
 
-    pub fn iter(&self) -> std::slice::Iter<u64> {
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
         self.primes.iter()
     }
 
     pub fn cardinality(&self) -> usize {
         self.primes.len()
     }
+}
+
+impl WieferichPrime<u64> {
     // calculate the pseudoprimes with a square factor less than b
     pub fn pseudoprimes(&self, b: u64) -> Vec<u64> {
         let mut res = vec![];
@@ -48,7 +59,7 @@ impl WieferichPrime {
     }
 }
 
-impl std::fmt::Display for WieferichPrime {
+impl<T: Natural> std::fmt::Display for WieferichPrime<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let b = self.base.to_string() + " p: ";
         let pstring = self